@@ -1,7 +1,30 @@
 extern crate cmake;
 
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+#[path = "build/header.rs"]
+mod header;
+
+fn write_file(path: &Path, contents: &str) {
+	let mut file: File = File::create(path).expect("Unable to create header file");
+	file.write_all(contents.as_bytes()).expect("Unable to write header file");
+}
+
+// Generate quick3d.h in OUT_DIR, the tests compare it with the checked-in copy
+fn generate_header() {
+	let header: String = header::generate(Path::new("src")).expect("Unable to generate quick3d.h");
+	let out_dir: String = env::var("OUT_DIR").expect("Unable to get OUT_DIR env");
+	write_file(&Path::new(&out_dir).join("quick3d.h"), &header);
+	if env::var("QUICK3D_UPDATE_HEADER").is_ok() {
+		write_file(Path::new("quick3d.h"), &header);
+	}
+}
+
 #[cfg(feature = "sqlite")]
-fn main() {
+fn build_dependencies() {
 	// cmake build for language wrappers
 	let dst = cmake::Config::new("dependencies").build();
 	println!("cargo:rustc-link-search=dependencies");
@@ -9,4 +32,9 @@ fn main() {
 }
 
 #[cfg(not(feature = "sqlite"))]
-fn main() {}
+fn build_dependencies() {}
+
+fn main() {
+	generate_header();
+	build_dependencies();
+}
@@ -0,0 +1,516 @@
+// Copyright (C) 2016 Chris Liebert
+
+//! Generates quick3d.h from the `#[no_mangle]` functions and `#[repr(C)]` types in the sources
+//!
+//! Modules are read in the order they are declared in lib.rs. A `#[repr(C)]` struct whose
+//! fields all have C equivalents is declared as a struct, any other type passed across the
+//! boundary is an opaque handle declared as `typedef void* Name;`. A `pub type` alias of an
+//! `extern "C" fn` is declared as a function pointer type for callbacks.
+
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+
+const PREAMBLE: &'static str = "/* Copyright (C) 2016 Chris Liebert */
+
+/* Generated from the Rust sources by build.rs, do not edit.
+ * Build with QUICK3D_UPDATE_HEADER set to regenerate this file.
+ */
+
+#ifndef _QUICK3D_H_
+#define _QUICK3D_H_
+
+#include <stdbool.h>
+#include <stdint.h>
+
+/* Errors
+ * No function panics across the C boundary. On failure handles are NULL, status functions
+ * return false, counts return -1, and quick3d_last_error() describes the error.
+ */
+";
+
+const POSTAMBLE: &'static str = "#endif /* _QUICK3D_H_ */
+";
+
+/// A `#[repr(C)]` enum or struct
+///
+struct CType {
+    name: String,
+    is_enum: bool,
+    /// Enum variants, or struct fields as (name, Rust type)
+    members: Vec<(String, String)>,
+}
+
+/// A `#[no_mangle]` function
+///
+struct Function {
+    name: String,
+    args: Vec<(String, String)>,
+    ret: Option<String>,
+    /// The function is only compiled when a feature is disabled
+    cfg_not: bool,
+}
+
+struct Module {
+    name: String,
+    types: Vec<CType>,
+    callbacks: Vec<Function>,
+    functions: Vec<Function>,
+}
+
+fn read_file(path: &Path) -> io::Result<String> {
+    let mut file: File = try!(File::open(path));
+    let mut source: String = String::new();
+    try!(file.read_to_string(&mut source));
+    Ok(source.replace("\r\n", "\n"))
+}
+
+/// Remove line comments, doc comments included
+///
+fn strip_comments(source: &str) -> String {
+    source.lines()
+        .map(|line| match line.find("//") {
+            Some(i) if !line[..i].contains('"') => &line[..i],
+            _ => line,
+        })
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+        .replace("< ", "<").replace(" >", ">").replace(" ,", ",")
+        .replace("& ", "&").replace(":: ", "::").replace(" ::", "::")
+}
+
+/// Find the index of the bracket closing the one at `open`
+///
+fn matching(source: &str, open: usize) -> usize {
+    let bytes: &[u8] = source.as_bytes();
+    let (open_char, close_char) = match bytes[open] {
+        b'(' => (b'(', b')'),
+        b'{' => (b'{', b'}'),
+        b'[' => (b'[', b']'),
+        c => panic!("Unexpected bracket {}", c as char),
+    };
+    let mut depth: usize = 0;
+    let mut i: usize = open;
+    let mut in_string: bool = false;
+    while i < bytes.len() {
+        let c: u8 = bytes[i];
+        if in_string {
+            if c == b'\\' {
+                i += 1;
+            } else if c == b'"' {
+                in_string = false;
+            }
+        } else if c == b'"' {
+            in_string = true;
+        } else if c == b'\'' && i + 2 < bytes.len() && bytes[i + 2] == b'\'' {
+            // A character literal such as '"' or '{'
+            i += 2;
+        } else if c == b'\'' && i + 3 < bytes.len() && bytes[i + 1] == b'\\' && bytes[i + 3] == b'\'' {
+            i += 3;
+        } else if c == open_char {
+            depth += 1;
+        } else if c == close_char {
+            depth -= 1;
+            if depth == 0 {
+                return i;
+            }
+        }
+        i += 1;
+    }
+    panic!("Unmatched bracket");
+}
+
+/// Split on commas which are not nested in brackets
+///
+fn split_top_level(text: &str) -> Vec<String> {
+    let mut parts: Vec<String> = Vec::new();
+    let mut depth: i32 = 0;
+    let mut current: String = String::new();
+    for c in text.chars() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' => depth -= 1,
+            _ => (),
+        }
+        if c == ',' && depth == 0 {
+            parts.push(current.clone());
+            current.clear();
+        } else {
+            current.push(c);
+        }
+    }
+    parts.push(current);
+    parts.into_iter().map(|p| normalize(&p)).filter(|p| !p.is_empty()).collect()
+}
+
+/// The attributes written directly above `position`
+///
+fn attributes_before(source: &str, position: usize) -> Vec<String> {
+    source[..position].lines().rev()
+        .map(|line| line.trim())
+        .skip_while(|line| line.is_empty())
+        .take_while(|line| line.starts_with("#["))
+        .map(|line| String::from(line))
+        .collect()
+}
+
+fn parse_types(source: &str) -> Vec<CType> {
+    let mut types: Vec<CType> = Vec::new();
+    let mut offset: usize = 0;
+    while let Some(found) = source[offset..].find("#[repr(C)]") {
+        let start: usize = offset + found;
+        let item_start: usize = start + source[start..].find("pub ").expect("Expected an item after #[repr(C)]");
+        let open: usize = item_start + source[item_start..].find('{').expect("Expected a body");
+        let close: usize = matching(source, open);
+        let header: Vec<&str> = source[item_start..open].split_whitespace().collect();
+        let is_enum: bool = header[1] == "enum";
+        let members: Vec<(String, String)> = split_top_level(&source[open + 1..close]).into_iter()
+            .map(|member| {
+                if is_enum {
+                    (member, String::new())
+                } else {
+                    let member: String = member.trim_left_matches("pub ").to_string();
+                    let colon: usize = member.find(':').expect("Expected a field type");
+                    (member[..colon].trim().to_string(), member[colon + 1..].trim().to_string())
+                }
+            })
+            .collect();
+        types.push(CType { name: header[2].to_string(), is_enum: is_enum, members: members });
+        offset = close;
+    }
+    types
+}
+
+fn parse_args(args: &str) -> Vec<(String, String)> {
+    split_top_level(args).into_iter()
+        .map(|arg| {
+            let colon: usize = arg.find(':').expect("Expected an argument type");
+            let arg_name: String = arg[..colon].trim().trim_left_matches("mut ").trim_left_matches('_')
+                .trim_right_matches("_cstr").to_string();
+            (arg_name, arg[colon + 1..].trim().to_string())
+        })
+        .collect()
+}
+
+/// Find `pub type Name = extern "C" fn(...)` aliases, the arguments must be named
+///
+fn parse_callbacks(source: &str) -> Vec<Function> {
+    let mut callbacks: Vec<Function> = Vec::new();
+    let mut offset: usize = 0;
+    while let Some(found) = source[offset..].find("pub type ") {
+        let start: usize = offset + found;
+        let end: usize = start + source[start..].find(';').expect("Expected a type alias");
+        offset = end;
+        let alias: &str = &source[start + 9..end];
+        let equals: usize = match alias.find('=') {
+            Some(equals) => equals,
+            None => continue,
+        };
+        let target: &str = alias[equals + 1..].trim();
+        if !target.starts_with("extern \"C\" fn") {
+            continue;
+        }
+        let open: usize = start + 9 + equals + 1 + alias[equals + 1..].find('(').expect("Expected arguments");
+        let close: usize = matching(source, open);
+        let ret: String = normalize(source[close + 1..end].trim().trim_left_matches("->"));
+        callbacks.push(Function {
+            name: alias[..equals].trim().to_string(),
+            args: parse_args(&source[open + 1..close]),
+            ret: if ret.is_empty() { None } else { Some(ret) },
+            cfg_not: false,
+        });
+    }
+    callbacks
+}
+
+fn parse_functions(source: &str) -> Vec<Function> {
+    let mut functions: Vec<Function> = Vec::new();
+    let mut offset: usize = 0;
+    while let Some(found) = source[offset..].find("#[no_mangle]") {
+        let start: usize = offset + found;
+        let cfg_not: bool = attributes_before(source, start).iter().any(|a| a.starts_with("#[cfg(not("));
+        let fn_start: usize = start + source[start..].find("fn ").expect("Expected a function after #[no_mangle]");
+        let open: usize = fn_start + source[fn_start..].find('(').expect("Expected arguments");
+        let close: usize = matching(source, open);
+        let body: usize = close + source[close..].find('{').expect("Expected a function body");
+        let name: String = source[fn_start + 3..open].trim().to_string();
+        let args: Vec<(String, String)> = parse_args(&source[open + 1..close]);
+        let ret: String = normalize(source[close + 1..body].trim().trim_left_matches("->"));
+        functions.push(Function {
+            name: name,
+            args: args,
+            ret: if ret.is_empty() { None } else { Some(ret) },
+            cfg_not: cfg_not,
+        });
+        offset = matching(source, body);
+    }
+    // Prefer the definition used when features are enabled
+    let names: Vec<String> = functions.iter().filter(|f| !f.cfg_not).map(|f| f.name.clone()).collect();
+    let mut seen: BTreeSet<String> = BTreeSet::new();
+    functions.into_iter()
+        .filter(|f| !(f.cfg_not && names.contains(&f.name)))
+        .filter(|f| seen.insert(f.name.clone()))
+        .collect()
+}
+
+/// The C name of a primitive Rust type
+///
+fn primitive(rust: &str) -> Option<&'static str> {
+    let rust: &str = rust.trim_left_matches("libc::");
+    Some(match rust {
+        "bool" => "bool",
+        "c_char" => "char",
+        "c_int" | "i32" | "int32_t" => "int",
+        "c_uint" | "u32" | "uint32_t" => "unsigned int",
+        "c_float" | "f32" => "float",
+        "c_double" | "f64" => "double",
+        "u8" | "c_uchar" | "uint8_t" => "unsigned char",
+        "i64" | "int64_t" => "long long",
+        "u64" | "uint64_t" => "unsigned long long",
+        "usize" | "size_t" => "size_t",
+        "c_void" => "void",
+        _ => return None,
+    })
+}
+
+/// The C name of an opaque Rust type
+///
+fn handle_name(rust: &str) -> String {
+    match rust.rsplit("::").next().unwrap_or(rust) {
+        "GlutinFacade" => String::from("Display"),
+        "Program" => String::from("Shader"),
+        name => String::from(name),
+    }
+}
+
+struct Translator {
+    structs: BTreeSet<String>,
+    enums: BTreeSet<String>,
+    callbacks: BTreeSet<String>,
+    handles: BTreeSet<String>,
+}
+
+impl Translator {
+    /// The C declaration of a value of a Rust type
+    ///
+    fn translate(&mut self, rust: &str) -> String {
+        let rust: &str = rust.trim();
+        if rust.starts_with("Option<") && rust.ends_with('>') {
+            return self.translate(&rust[7..rust.len() - 1]);
+        }
+        if rust.starts_with("Box<") && rust.ends_with('>') {
+            return self.pointer(&rust[4..rust.len() - 1], false);
+        }
+        if rust.starts_with("*const ") {
+            return self.pointer(&rust[7..], true);
+        }
+        if rust.starts_with("*mut ") {
+            return self.pointer(&rust[5..], false);
+        }
+        if rust.starts_with("&mut ") {
+            return self.pointer(&rust[5..], false);
+        }
+        if rust.starts_with('&') {
+            return self.pointer(&rust[1..], true);
+        }
+        if let Some(c) = primitive(rust) {
+            return String::from(c);
+        }
+        if self.structs.contains(rust) || self.enums.contains(rust) || self.callbacks.contains(rust) {
+            return String::from(rust);
+        }
+        panic!("Unable to pass {} by value across the C boundary", rust);
+    }
+
+    /// The C declaration of a pointer to a Rust type, opaque types are already pointers
+    ///
+    fn pointer(&mut self, rust: &str, constant: bool) -> String {
+        let rust: &str = rust.trim();
+        if let Some(c) = primitive(rust) {
+            return match (constant, c) {
+                (_, "void") => String::from("void*"),
+                (true, _) => format!("const {}*", c),
+                (false, _) => format!("{}*", c),
+            };
+        }
+        if self.structs.contains(rust) {
+            return format!("{}*", rust);
+        }
+        if rust.starts_with('&') || rust.starts_with("*") || rust.starts_with("Box<") {
+            return format!("{}*", self.translate(rust));
+        }
+        let name: String = handle_name(rust);
+        self.handles.insert(name.clone());
+        name
+    }
+}
+
+/// Declare a struct field, arrays put their length after the name
+///
+fn declare_field(name: &str, rust: &str, translator: &mut Translator) -> String {
+    let rust: &str = rust.trim();
+    if rust.starts_with('[') && rust.ends_with(']') {
+        let semicolon: usize = rust.rfind(';').expect("Expected an array length");
+        let inner: String = declare_field(name, &rust[1..semicolon], translator);
+        return format!("{}[{}]", inner, rust[semicolon + 1..rust.len() - 1].trim());
+    }
+    format!("{} {}", translator.translate(rust), name)
+}
+
+fn can_declare_field(rust: &str, enums: &BTreeSet<String>) -> bool {
+    let rust: &str = rust.trim();
+    if rust.starts_with('[') && rust.ends_with(']') {
+        return match rust.rfind(';') {
+            Some(semicolon) => can_declare_field(&rust[1..semicolon], enums),
+            None => false,
+        };
+    }
+    primitive(rust).is_some() && rust != "c_void" || enums.contains(rust)
+}
+
+fn declare_enum(t: &CType) -> String {
+    let mut text: String = format!("typedef enum {} {{\n", t.name);
+    let mut line: String = String::from("   ");
+    for &(ref variant, _) in t.members.iter() {
+        if line.len() + variant.len() + 2 > 100 {
+            text.push_str(&line);
+            text.push('\n');
+            line = String::from("   ");
+        }
+        line.push(' ');
+        line.push_str(variant);
+        line.push(',');
+    }
+    text.push_str(&line);
+    text.push_str(&format!("\n}} {};\n\n", t.name));
+    text
+}
+
+/// Declare an argument, a pointer to an array is declared as an array so that it's length is
+/// part of the prototype
+///
+fn declare_arg(name: &str, rust: &str, translator: &mut Translator) -> String {
+    let rust: &str = rust.trim();
+    for &(prefix, qualifier) in [("*mut ", ""), ("*const ", "const ")].iter() {
+        if rust.starts_with(prefix) && rust[prefix.len()..].trim().starts_with('[') {
+            return format!("{}{}", qualifier, declare_field(name, &rust[prefix.len()..], translator));
+        }
+    }
+    format!("{} {}", translator.translate(rust), name)
+}
+
+/// The C return type and argument list of a function
+///
+fn signature(f: &Function, translator: &mut Translator) -> (String, String) {
+    let ret: String = match f.ret {
+        Some(ref ret) => translator.translate(ret),
+        None => String::from("void"),
+    };
+    let args: Vec<String> = f.args.iter()
+        .map(|&(ref name, ref rust)| declare_arg(name, rust, translator))
+        .collect();
+    (ret, args.join(", "))
+}
+
+fn declare_function(f: &Function, translator: &mut Translator) -> String {
+    let (ret, args) = signature(f, translator);
+    format!("extern {} {}({});\n", ret, f.name, args)
+}
+
+fn declare_callback(f: &Function, translator: &mut Translator) -> String {
+    let (ret, args) = signature(f, translator);
+    format!("typedef {} (*{})({});\n", ret, f.name, args)
+}
+
+/// Read lib.rs and the modules it declares under `src_dir` and generate the header
+///
+pub fn generate(src_dir: &Path) -> io::Result<String> {
+    let lib: String = try!(read_file(&src_dir.join("lib.rs")));
+    let mut names: Vec<String> = strip_comments(&lib).lines()
+        .map(|line| line.trim())
+        .filter(|line| line.starts_with("pub mod ") && line.ends_with(';'))
+        .map(|line| line[8..line.len() - 1].trim().to_string())
+        .collect();
+    names.push(String::from("lib"));
+
+    let mut modules: Vec<Module> = Vec::new();
+    for name in names {
+        let source: String = if name == "lib" {
+            lib.clone()
+        } else {
+            try!(read_file(&src_dir.join(format!("{}.rs", name))))
+        };
+        let source: String = strip_comments(&source);
+        let source: &str = match source.find("#[cfg(test)]") {
+            Some(tests) => &source[..tests],
+            None => &source,
+        };
+        modules.push(Module {
+            name: name,
+            types: parse_types(source),
+            callbacks: parse_callbacks(source),
+            functions: parse_functions(source),
+        });
+    }
+
+    let enums: BTreeSet<String> = modules.iter()
+        .flat_map(|m| m.types.iter().filter(|t| t.is_enum).map(|t| t.name.clone()))
+        .collect();
+    let structs: BTreeSet<String> = modules.iter()
+        .flat_map(|m| m.types.iter().filter(|t| !t.is_enum && t.members.iter().all(|&(_, ref r)| can_declare_field(r, &enums))).map(|t| t.name.clone()))
+        .collect();
+    let callbacks: BTreeSet<String> = modules.iter()
+        .flat_map(|m| m.callbacks.iter().map(|c| c.name.clone()))
+        .collect();
+    let mut translator: Translator = Translator {
+        structs: structs,
+        enums: enums,
+        callbacks: callbacks,
+        handles: BTreeSet::new(),
+    };
+
+    let mut types: String = String::new();
+    for t in modules.iter().flat_map(|m| m.types.iter()) {
+        if t.is_enum {
+            types.push_str(&declare_enum(t));
+        } else if translator.structs.contains(&t.name) {
+            types.push_str(&format!("typedef struct {} {{\n", t.name));
+            for &(ref name, ref rust) in t.members.iter() {
+                types.push_str(&format!("    {};\n", declare_field(name, rust, &mut translator)));
+            }
+            types.push_str(&format!("}} {};\n\n", t.name));
+        }
+    }
+    for module in modules.iter().filter(|m| !m.callbacks.is_empty()) {
+        for callback in module.callbacks.iter() {
+            types.push_str(&declare_callback(callback, &mut translator));
+        }
+        types.push('\n');
+    }
+
+    let mut functions: String = String::new();
+    for module in modules.iter().filter(|m| !m.functions.is_empty()) {
+        functions.push_str(&format!("/* {}.rs */\n", module.name));
+        for f in module.functions.iter() {
+            functions.push_str(&declare_function(f, &mut translator));
+        }
+        functions.push('\n');
+    }
+
+    let mut header: String = String::from(PREAMBLE);
+    header.push('\n');
+    for handle in translator.handles.iter() {
+        header.push_str(&format!("typedef void* {};\n", handle));
+    }
+    header.push('\n');
+    header.push_str(&types);
+    header.push_str(&functions);
+    header.push_str(POSTAMBLE);
+    Ok(header)
+}
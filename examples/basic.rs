@@ -4,26 +4,26 @@ extern crate glium;
 extern crate nalgebra;
 extern crate quick3d;
 
-use glium::glutin;
-use glium::glutin::{ElementState, Event, MouseButton, VirtualKeyCode};
-use glium::DisplayBuild;
+use glium::glutin::Event;
 use glium::backend::glutin_backend::GlutinFacade;
 
 use nalgebra::Matrix4;
-use std::io::Error;
 
-use quick3d::camera::Camera;
-use quick3d::common::Mesh;
-use quick3d::renderer;
+use quick3d::app;
+use quick3d::app::{App, AppConfig, Context};
+use quick3d::error::Error;
+use quick3d::input::EventBuffer;
+use quick3d::inputmap::InputMap;
 use quick3d::shader::Shader;
 use quick3d::scene::Scene;
+use quick3d::text::{TextRenderer, DEFAULT_FONT_SIZE};
 
 fn load_scene_no_sqlite(filename: String) -> Scene {
     let scene: Scene = match filename.ends_with(".bin.gz") {
         true => {
             match Scene::from_compressed_binary_file(filename.clone()) {
                 Ok(s) => s,
-                Err(e) => panic!("Unable to load {}: {:?}", filename, e),
+                Err(e) => panic!("Unable to load {}: {}", filename, e),
             }
         },
         false => {
@@ -31,7 +31,7 @@ fn load_scene_no_sqlite(filename: String) -> Scene {
                 true => {
                     match Scene::from_binary_file(filename.clone()) {
                         Ok(s) => s,
-                        Err(e) => panic!("Unable to load {}: {:?}", filename, e),
+                        Err(e) => panic!("Unable to load {}: {}", filename, e),
                     }
                 },
                 false => {
@@ -75,16 +75,11 @@ fn load_scene(filename: String) -> Scene {
 }
 
 #[cfg(feature = "sqlite")]
-fn load_shader(display: &GlutinFacade) -> glium::program::Program {
+fn load_shader(display: &GlutinFacade) -> Result<glium::program::Program, Error> {
     use quick3d::dbloader::DBLoader;
     // sqlite enabled, load shaders.db
-    let shader_dbloader: DBLoader = match DBLoader::new("shaders.db") {
-        Ok(d) => {
-            println!("Loaded shaders.db");
-            d
-        },
-        Err(e) => panic!("Unable to load shader databse: {:?}", e),
-    };
+    let shader_dbloader: DBLoader = try!(DBLoader::new("shaders.db"));
+    println!("Loaded shaders.db");
     // Attempt to load GLSL version 330 if it is supported
     let desired_glsl_version = glium::Version(glium::Api::Gl, 3, 3);
     let shader_name = "default";
@@ -92,203 +87,142 @@ fn load_shader(display: &GlutinFacade) -> glium::program::Program {
                                                &shader_dbloader,
                                                &desired_glsl_version,
                                                &display) {
-        Ok(p) => p,
+        Ok(p) => Ok(p),
         Err(e) => {
-            println!("Unable to load {:?}: {:?}", desired_glsl_version, e);
-            match Shader::from_dbloader(&shader_name, &shader_dbloader, &display) {
-                Ok(s) => s,
-                Err(e2) => panic!("Unable to load shader: {:?}", e2),
-            }
+            println!("Unable to load {:?}: {}", desired_glsl_version, e);
+            Shader::from_dbloader(&shader_name, &shader_dbloader, &display)
         }
     }
 }
 
 #[cfg(not(feature = "sqlite"))]
-fn load_shader(display: &GlutinFacade) -> glium::program::Program {
+fn load_shader(display: &GlutinFacade) -> Result<glium::program::Program, Error> {
     // sqlite disabled, use built-in shader
-    match Shader::default(display) {
-        Ok(p) => p,
-        Err(e) => {
-            panic!("Unable to load default shader {:?}", e);
-        },
-    }
+    Shader::default(display)
 }
 
-fn main() {
-    let screen_width = 600;
-    let screen_height = 400;
+struct Example {
+    input: InputMap,
+    mouse_last_x: i32,
+    mouse_last_y: i32,
+    // The torus position after the last two updates, rendering interpolates between them
+    torus_previous: (f32, f32),
+    torus_current: (f32, f32),
+}
 
-    use std::env;
-    let mut filename = String::from("test.bin.gz");
-       
-    // Prints each argument on a separate line
-    let mut i = 0;
-    for argument in env::args() {
-        if i == 1 {
-            filename = argument;
+impl Example {
+    fn new() -> Example {
+        let mut input: InputMap = InputMap::new();
+        for line in ["action quit ESCAPE",
+                     "action aim MOUSE_LEFT",
+                     "axis camera_forward S W",
+                     "axis camera_right A D",
+                     "axis torus_vertical K I",
+                     "axis torus_horizontal J L"].iter() {
+            input.parse_line(line).expect("Invalid input binding");
+        }
+        Example {
+            input: input,
+            mouse_last_x: 0,
+            mouse_last_y: 0,
+            torus_previous: (0.0, 0.0),
+            torus_current: (0.0, 0.0),
         }
-        i += 1;
     }
-    
-    let scene: Scene = load_scene(filename);
-
-    let display: GlutinFacade = glutin::WindowBuilder::new()
-        .with_depth_buffer(24)
-        .with_title("Rust Window")
-        .with_gl_debug_flag(true)
-        .with_visibility(false) // Window is shown when scene finishes loading.
-        .with_dimensions(screen_width, screen_height)
-        .build_glium()
-        .unwrap();
 
-    let mut camera: Camera = Camera::new(screen_width as f32, screen_height as f32);
-    camera = camera.move_backward(6.0);
-
-    let renderer: renderer::Renderer = match renderer::Renderer::new(&display, scene) {
-        Ok(r) => r,
-        Err(e) => panic!("Unable to create renderer: {:?}", e),
-    };
-  
-    let shader_program: glium::program::Program = load_shader(&display);
-    // Shader compilation no longer required
-    display.release_shader_compiler();
-          
-    // Show the window once the data is loaded
-    let window = match display.get_window() {
-        Some(x) => {
-            x.show();
-            x
+    fn mouse_moved(&mut self, context: &mut Context, x: i32, y: i32) {
+        let mouse_dx: i32 = self.mouse_last_x - x;
+        let mouse_dy: i32 = self.mouse_last_y - y;
+        self.mouse_last_x = x;
+        self.mouse_last_y = y;
+        if !self.input.action_held("aim") {
+            return;
         }
-        None => {
-            panic!("Error retrieving window");
+        // Rotate the camera if the left mouse button is pressed
+        context.camera = context.camera.aim(mouse_dx as f64, mouse_dy as f64);
+
+        let (screen_width, screen_height) = context.dimensions();
+        let screen_center_x: i32 = (screen_width / 2) as i32;
+        let screen_center_y: i32 = (screen_height / 2) as i32;
+        let mouse_grab_margin: i32 = screen_center_y / 2;
+        if let Some(window) = context.display.get_window() {
+            if x + mouse_grab_margin >= screen_width as i32 || x <= mouse_grab_margin {
+                let _ = window.set_cursor_position(screen_center_x, y);
+                self.mouse_last_x = screen_center_x;
+            } else if y + mouse_grab_margin >= screen_height as i32 || y <= mouse_grab_margin {
+                let _ = window.set_cursor_position(x, screen_center_y);
+                self.mouse_last_y = screen_center_y;
+            }
         }
-    };
-
-    // The torus will be movable in the scene if it is found
-    let torus: Result<&Mesh, Error> = renderer.get_mesh("Torus");
-
-    let mut torus_x = 0.0f32;
-    let mut torus_y = 0.0f32;
-    let mut torus_vertical_speed = 0.0f32;
-    let mut torus_horizontal_speed = 0.0f32;
-    let mut left_button_pressed = false;
-
-    let mut mouse_last_x: i32 = 0;
-    let mut mouse_last_y: i32 = 0;
-
-    let mut _mouse_dx: i32 = 0;
-    let mut _mouse_dy: i32 = 0;
+    }
+}
 
-    let mut _camera_forward_speed = 0.0f32;
-    let mut _camera_left_speed = 0.0f32;
+impl App for Example {
+    fn load_shader(&mut self, display: &GlutinFacade) -> Result<glium::program::Program, Error> {
+        load_shader(display)
+    }
 
-    let mut _w_state = false;
-    let mut _camera_forward_speed = 0.0;
+    fn init(&mut self, context: &mut Context) -> Result<(), Error> {
+        context.camera = context.camera.move_backward(6.0);
+        context.renderer.text = Some(try!(TextRenderer::default_font(&context.display, DEFAULT_FONT_SIZE)));
+        Ok(())
+    }
 
-    let screen_center_x: i32 = (screen_width / 2) as i32;
-    let screen_center_y: i32 = (screen_height / 2) as i32;
+    fn events(&mut self, context: &mut Context, events: &EventBuffer) {
+        self.input.update(events);
+        if self.input.action_pressed("quit") {
+            context.quit();
+        }
+        for event in events.events() {
+            if let &Event::MouseMoved(x, y) = event {
+                self.mouse_moved(context, x, y);
+            }
+        }
+    }
 
-    let mouse_grab_margin: i32 = screen_center_y / 2;
-        
-    'running: loop {
-        match renderer.render(&display, &shader_program, &camera) {
-            Ok(_) => (),
-            Err(e) => panic!("{:?}", e),
-        };
-       
-        for event in display.poll_events() {
-            match event {
-                Event::Closed => break 'running,
-                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::Escape)) => {
-                    break 'running;
-                }
-                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::W)) => {
-                    _camera_forward_speed = 1.0;
-                }
-                Event::KeyboardInput(ElementState::Released, _, Some(VirtualKeyCode::W)) => {
-                    _camera_forward_speed = 0.0;
-                }
-                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::A)) => {
-                    camera = camera.move_left(1.0);
-                }
-                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::S)) => {
-                    camera = camera.move_backward(1.0);
-                }
-                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::D)) => {
-                    camera = camera.move_right(1.0);
-                }
-                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::I)) => {
-                    torus_vertical_speed = 0.001f32;
-                }
-                Event::KeyboardInput(ElementState::Released, _, Some(VirtualKeyCode::I)) => {
-                    torus_vertical_speed = 0.0f32;
-                }
-                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::J)) => {
-                    torus_horizontal_speed = -0.001f32;
-                }
-                Event::KeyboardInput(ElementState::Released, _, Some(VirtualKeyCode::J)) => {
-                    torus_horizontal_speed = 0.0f32;
-                }
-                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::K)) => {
-                    torus_vertical_speed = -0.001f32;
-                }
-                Event::KeyboardInput(ElementState::Released, _, Some(VirtualKeyCode::K)) => {
-                    torus_vertical_speed = 0.0f32;
-                }
-                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::L)) => {
-                    torus_horizontal_speed = 0.001f32;
-                }
-                Event::KeyboardInput(ElementState::Released, _, Some(VirtualKeyCode::L)) => {
-                    torus_horizontal_speed = 0.0f32;
-                }
-                Event::MouseInput(ElementState::Pressed, MouseButton::Left) => {
-                    left_button_pressed = true;
-                }
-                Event::MouseInput(ElementState::Released, MouseButton::Left) => {
-                    left_button_pressed = false;
-                }
-                Event::MouseMoved(x, y) => {
-                    _mouse_dx = mouse_last_x - x;
-                    _mouse_dy = mouse_last_y - y;
-                    mouse_last_x = x;
-                    mouse_last_y = y;
-                    if left_button_pressed {
-                        // Rotate the camera if the left mouse button is pressed
-                        camera = camera.aim(_mouse_dx as f64, _mouse_dy as f64);
+    fn update(&mut self, context: &mut Context, _dt: f64) {
+        context.camera = context.camera.move_forward(self.input.axis("camera_forward") * 0.01);
+        context.camera = context.camera.move_right(self.input.axis("camera_right") * 0.01);
 
-                        if x + mouse_grab_margin >= screen_width as i32 || x <= mouse_grab_margin {
-                            let _ = window.set_cursor_position(screen_center_x, y);
-                            mouse_last_x = screen_center_x;
-                            _mouse_dx = 0;
-                        } else if y + mouse_grab_margin >= screen_height as i32 ||
-                                  y <= mouse_grab_margin {
-                            let _ = window.set_cursor_position(x, screen_center_y);
-                            mouse_last_y = screen_center_y;
-                            _mouse_dy = 0;
-                        }
-                    }
-                }
+        self.torus_previous = self.torus_current;
+        self.torus_current.0 += self.input.axis("torus_horizontal") * 0.001;
+        self.torus_current.1 += self.input.axis("torus_vertical") * 0.001;
+    }
 
-                _ => (),
-            }
+    fn render(&mut self, context: &mut Context, alpha: f64) -> Result<(), Error> {
+        // Move the torus (if found) between it's last two positions
+        if let Ok(torus) = context.renderer.get_mesh("Torus") {
+            let alpha: f32 = alpha as f32;
+            let mut matrix: Matrix4<f32> = *torus.matrix.borrow();
+            matrix.m14 = self.torus_previous.0 + (self.torus_current.0 - self.torus_previous.0) * alpha;
+            matrix.m24 = self.torus_previous.1 + (self.torus_current.1 - self.torus_previous.1) * alpha;
+            *torus.matrix.borrow_mut() = matrix;
         }
-        
-        camera = camera.move_forward(_camera_forward_speed * 0.01);
+        let frame_time: f32 = context.renderer.average_frame_stats().frame_time;
+        if frame_time > 0.0 {
+            try!(context.renderer.draw_text(&format!("{:.0} FPS", 1000.0 / frame_time), 8.0, 8.0, [1.0, 1.0, 1.0, 1.0], 1.0));
+        }
+        context.renderer.render(&context.display, &context.program, &context.camera)
+    }
+}
 
-        // Move the torus (if found) based on changes from keyboard input
-        match torus {
-            Ok(torus) => {
-                // Get existing matrix
-                let mut matrix: Matrix4<f32> = *torus.matrix.borrow();
-                torus_x += torus_horizontal_speed;
-                torus_y += torus_vertical_speed;
-                matrix.m14 = torus_x;
-                matrix.m24 = torus_y;
-                // Mutate the matrix
-                *torus.matrix.borrow_mut() = matrix;
-            }
-            Err(_) => {}
+fn main() {
+    use std::env;
+    let mut filename = String::from("test.bin.gz");
+       
+    // Prints each argument on a separate line
+    let mut i = 0;
+    for argument in env::args() {
+        if i == 1 {
+            filename = argument;
         }
+        i += 1;
     }
     
+    let scene: Scene = load_scene(filename);
+
+    let config: AppConfig = AppConfig::new("Rust Window", 600, 400);
+    if let Err(e) = app::run(&mut Example::new(), &config, scene) {
+        panic!("{}", e);
+    }
 }
@@ -9,6 +9,10 @@
 
 int main(int argc, char** argv) {
 	Display* display = create_display(800, 600, "My C Test Window");
+	assert(display != NULL);
+	Renderer* renderer = create_renderer_from_binary("missing.bin", display);
+	assert(renderer == NULL);
+	assert(quick3d_last_error() != NULL);
 	free_display(display);
 	return 0;
 }
@@ -12,23 +12,23 @@ public class Camera extends Quick3DNativeWrapper {
 	}
 
 	public void moveForward(float amount) {
-		data = quick3dwrapper.camera_move_forward(data, amount);
+		quick3dwrapper.camera_move_forward_in_place(data, amount);
 	}
 
 	public void moveBackward(float amount) {
-		data = quick3dwrapper.camera_move_backward(data, amount);
+		quick3dwrapper.camera_move_backward_in_place(data, amount);
 	}
 
 	public void moveLeft(float amount) {
-		data = quick3dwrapper.camera_move_left(data, amount);
+		quick3dwrapper.camera_move_left_in_place(data, amount);
 	}
 
 	public void moveRight(float amount) {
-		data = quick3dwrapper.camera_move_right(data, amount);
+		quick3dwrapper.camera_move_right_in_place(data, amount);
 	}
 
 	public void aim(double x, double y) {
-		data = quick3dwrapper.camera_aim(data, x, y);	
+		quick3dwrapper.camera_aim_in_place(data, x, y);	
 	}
 
 }
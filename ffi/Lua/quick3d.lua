@@ -259,35 +259,58 @@ function eval(command)
   end
 end
 
+-- Raise an error with the message from quick3d when the last call failed
+local function check(result)
+  local message = wrapper.quick3d_last_error()
+  if message ~= nil then
+    -- A NULL char* is still truthy in the LuaJIT FFI api
+    if ffi ~= nil then message = ffi.string(message) end
+    error(message, 2)
+  end
+  return result
+end
+
 -- Camera object wrapper
 Camera = {}
 Camera.__index = Camera
 
 function Camera.aim(self, x, y)
-  self.struct = wrapper.camera_aim(self.struct, x, y)
+  wrapper.camera_aim_in_place(self.struct, x, y)
 end
 
 function Camera.create(self, screen_width, screen_height)
   local camera = {}
   setmetatable(camera, Camera)
-  self.struct = wrapper.create_camera(screen_width, screen_height)
+  camera.struct = wrapper.create_camera(screen_width, screen_height)
   return camera
 end
 
 function Camera.move_forward(self, amount)
-  self.struct = wrapper.camera_move_forward(self.struct, amount)
+  wrapper.camera_move_forward_in_place(self.struct, amount)
 end
 
 function Camera.move_backward(self, amount)
-  self.struct = wrapper.camera_move_backward(self.struct, amount)
+  wrapper.camera_move_backward_in_place(self.struct, amount)
 end
 
 function Camera.move_left(self, amount)
-  self.struct = wrapper.camera_move_left(self.struct, amount)
+  wrapper.camera_move_left_in_place(self.struct, amount)
 end
 
 function Camera.move_right(self, amount)
-  self.struct = wrapper.camera_move_right(self.struct, amount)
+  wrapper.camera_move_right_in_place(self.struct, amount)
+end
+
+function Camera.set_position(self, x, y, z)
+  wrapper.camera_set_position_in_place(self.struct, x, y, z)
+end
+
+function Camera.look_at(self, x, y, z)
+  wrapper.camera_look_at_in_place(self.struct, x, y, z, 0, 1, 0)
+end
+
+function Camera.resize(self, screen_width, screen_height)
+  wrapper.camera_resize_in_place(self.struct, screen_width, screen_height)
 end
 
 
@@ -298,7 +321,7 @@ Display.__index = Display
 function Display.create(self, screen_width, screen_height, window_title)
   local display = {}
   setmetatable(display, Display)
-  self.struct = wrapper.create_display(screen_width, screen_height, window_title)
+  display.struct = check(wrapper.create_display(screen_width, screen_height, window_title))
   return display
 end
 
@@ -318,28 +341,166 @@ Renderer.__index = Renderer
 function Renderer.create_from_binary(self, bin_filename, display)
   local renderer = {}
   setmetatable(renderer, Renderer)
-  self.struct = wrapper.create_renderer_from_binary(bin_filename, display.struct)
+  renderer.struct = check(wrapper.create_renderer_from_binary(bin_filename, display.struct))
   return renderer
 end
 
 function Renderer.create_from_compressed_binary(self, bin_filename, display)
   local renderer = {}
   setmetatable(renderer, Renderer)
-  self.struct = wrapper.create_renderer_from_compressed_binary(bin_filename, display.struct)
+  renderer.struct = check(wrapper.create_renderer_from_compressed_binary(bin_filename, display.struct))
   return renderer
 end
 
 function Renderer.create_from_database(self, db_filename, display)
   local renderer = {}
   setmetatable(renderer, Renderer)
-  local dbloader = wrapper.create_db_loader(db_filename)
-  self.struct = wrapper.create_renderer_from_db_loader(dbloader, display.struct)
+  local dbloader = check(wrapper.create_db_loader(db_filename))
+  renderer.struct = check(wrapper.create_renderer_from_db_loader(dbloader, display.struct))
   wrapper.free_db_loader(dbloader)
   return renderer
 end
 
+function Renderer.create_from_scene(self, scene, display)
+  local renderer = {}
+  setmetatable(renderer, Renderer)
+  renderer.struct = check(wrapper.create_renderer_from_scene(scene.struct, display.struct))
+  return renderer
+end
+
 function Renderer.render(self, shader, camera, display)
-  wrapper.render(self.struct, shader.struct, camera.struct, display.struct)
+  check(wrapper.render(self.struct, shader.struct, camera.struct, display.struct))
+end
+
+-- Get the FrameStats of the last frame, or the average of the recent frames
+-- The out parameter is only supported by the LuaJIT FFI api
+function Renderer.frame_stats(self, average)
+  local stats = ffi.new("FrameStats")
+  if average then
+    check(wrapper.renderer_average_frame_stats(self.struct, stats))
+  else
+    check(wrapper.renderer_frame_stats(self.struct, stats))
+  end
+  return stats
+end
+
+function Renderer.show_stats_overlay(self, display, visible)
+  if visible then
+    check(wrapper.renderer_enable_stats_overlay(self.struct, display.struct))
+  else
+    check(wrapper.renderer_disable_stats_overlay(self.struct))
+  end
+end
+
+function Renderer.enable_profiling(self, gpu_timing)
+  check(wrapper.renderer_enable_profiling(self.struct, gpu_timing or false))
+end
+
+function Renderer.disable_profiling(self)
+  check(wrapper.renderer_disable_profiling(self.struct))
+end
+
+function Renderer.profile_begin(self, name)
+  check(wrapper.renderer_profile_begin(self.struct, name))
+end
+
+function Renderer.profile_end(self)
+  check(wrapper.renderer_profile_end(self.struct))
+end
+
+function Renderer.save_chrome_trace(self, file)
+  check(wrapper.renderer_save_chrome_trace(self.struct, file))
+end
+
+-- Load a TrueType font for draw_text, the bundled font is used when file is nil
+function Renderer.load_font(self, display, file, size)
+  check(wrapper.renderer_load_font(self.struct, display.struct, file, size or 16))
+end
+
+-- Text is drawn over the next frame, color is {r, g, b, a} and defaults to white
+function Renderer.draw_text(self, text, x, y, color, scale)
+  local c = color or {1, 1, 1, 1}
+  check(wrapper.renderer_draw_text(self.struct, text, x, y, c[1], c[2], c[3], c[4] or 1, scale or 1))
+end
+
+function Renderer.draw_text_world(self, text, x, y, z, color, scale)
+  local c = color or {1, 1, 1, 1}
+  check(wrapper.renderer_draw_text_world(self.struct, text, x, y, z, c[1], c[2], c[3], c[4] or 1, scale or 1))
+end
+
+function Renderer.measure_text(self, text, scale)
+  local width = ffi.new("float[1]")
+  local height = ffi.new("float[1]")
+  check(wrapper.renderer_measure_text(self.struct, text, scale or 1, width, height))
+  return width[0], height[0]
+end
+
+-- Add a texture from the contents of an image file, for draw_image or materials
+function Renderer.add_texture(self, display, name, data)
+  check(wrapper.renderer_add_texture(self.struct, display.struct, name, data, #data))
+end
+
+-- Overlay items are drawn over the next frame in pixels from the top left of the window,
+-- items with a greater z are drawn on top
+function Renderer.draw_rect(self, x, y, width, height, color, z)
+  local c = color or {1, 1, 1, 1}
+  check(wrapper.renderer_draw_rect(self.struct, x, y, width, height, c[1], c[2], c[3], c[4] or 1, z or 0))
+end
+
+function Renderer.draw_line(self, x0, y0, x1, y1, width, color, z)
+  local c = color or {1, 1, 1, 1}
+  check(wrapper.renderer_draw_line(self.struct, x0, y0, x1, y1, width or 1, c[1], c[2], c[3], c[4] or 1, z or 0))
+end
+
+-- source is {u0, v0, u1, v1} from the top left of the texture, nil draws all of it
+function Renderer.draw_image(self, texture, x, y, width, height, source, color, z)
+  local c = color or {1, 1, 1, 1}
+  local s = nil
+  if source then s = ffi.new("float[4]", source) end
+  check(wrapper.renderer_draw_image(self.struct, texture, x, y, width, height, s, c[1], c[2], c[3], c[4] or 1, z or 0))
+end
+
+
+-- Scene object wrapper, for building geometry in memory
+Scene = {}
+Scene.__index = Scene
+
+function Scene.create(self)
+  local scene = {}
+  setmetatable(scene, Scene)
+  scene.struct = check(wrapper.create_scene())
+  return scene
+end
+
+function Scene.free(self)
+  wrapper.free_scene(self.struct)
+end
+
+-- Returns the index of the new material, texture_name is optional
+function Scene.add_material(self, name, r, g, b, texture_name)
+  return check(wrapper.scene_add_material(self.struct, name, r, g, b, texture_name))
+end
+
+-- Add a mesh from Lua tables of floats, normals and texcoords are optional
+-- The float arrays are only supported by the LuaJIT FFI api
+function Scene.add_mesh(self, name, positions, normals, texcoords, material_index)
+  local vertex_count = #positions / 3
+  local function float_array(values)
+    if values == nil then return nil end
+    return ffi.new("float[?]", #values, values)
+  end
+  return check(wrapper.scene_add_mesh(self.struct, name, float_array(positions), float_array(normals),
+                                      float_array(texcoords), vertex_count, material_index))
+end
+
+-- Add a texture from the contents of an encoded image file
+function Scene.add_texture(self, name, data)
+  check(wrapper.scene_add_texture(self.struct, name, data, #data))
+end
+
+-- Add a texture from a string of RGBA bytes
+function Scene.add_texture_rgba(self, name, pixels, width, height)
+  check(wrapper.scene_add_texture_rgba(self.struct, name, pixels, width, height))
 end
 
 
@@ -350,8 +511,8 @@ Shader.__index = Shader
 function Shader.create(self, name, db_filename, display)
   local shader = {}
   setmetatable(shader, Shader)
-  local dbloader = wrapper.create_db_loader(db_filename)
-  self.struct = wrapper.get_shader_from_dbloader(name, dbloader, display.struct)
+  local dbloader = check(wrapper.create_db_loader(db_filename))
+  shader.struct = check(wrapper.get_shader_from_dbloader(name, dbloader, display.struct))
   wrapper.free_db_loader(dbloader)
   return shader
 end
@@ -359,7 +520,7 @@ end
 function Shader.default(self, display)
   local shader = {}
   setmetatable(shader, Shader)
-  self.struct = wrapper.shader_default(display.struct)
+  shader.struct = check(wrapper.shader_default(display.struct))
   return shader
 end
 
@@ -415,4 +576,348 @@ end
 
 function EventBuffer.mouse_released_right(self, keycode)
   return wrapper.mouse_released_right(self.struct)
-end
\ No newline at end of file
+end
+
+function EventBuffer.count(self)
+  return wrapper.events_count(self.struct)
+end
+
+-- Get the EventData at index (starting at 0), the out parameter is only supported by the LuaJIT FFI api
+function EventBuffer.event(self, index)
+  local data = ffi.new("EventData")
+  if wrapper.events_get(self.struct, index, data) then return data end
+  return nil
+end
+
+-- Convert a char* returned by quick3d to a Lua string and free it
+local function take_string(c_string)
+  if ffi == nil then return c_string end
+  if c_string == nil then return nil end
+  local lua_string = ffi.string(c_string)
+  wrapper.free_string(c_string)
+  return lua_string
+end
+
+function EventBuffer.text(self)
+  return take_string(wrapper.events_text(self.struct))
+end
+
+function EventBuffer.dropped_files(self)
+  local files = {}
+  for i = 0, wrapper.events_dropped_file_count(self.struct) - 1 do
+    table.insert(files, take_string(wrapper.events_dropped_file(self.struct, i)))
+  end
+  return files
+end
+
+-- InputState object wrapper
+InputState = {}
+InputState.__index = InputState
+
+function InputState.create(self)
+  local state = {}
+  setmetatable(state, InputState)
+  state.struct = wrapper.create_input_state()
+  return state
+end
+
+function InputState.free(self)
+  wrapper.free_input_state(self.struct)
+end
+
+function InputState.update(self, events)
+  wrapper.input_state_update(self.struct, events.struct)
+end
+
+function InputState.key_down(self, keycode)
+  return wrapper.input_key_down(self.struct, keycode)
+end
+
+function InputState.key_pressed(self, keycode)
+  return wrapper.input_key_pressed(self.struct, keycode)
+end
+
+function InputState.key_released(self, keycode)
+  return wrapper.input_key_released(self.struct, keycode)
+end
+
+function InputState.button_down(self, button)
+  return wrapper.input_button_down(self.struct, button)
+end
+
+function InputState.button_pressed(self, button)
+  return wrapper.input_button_pressed(self.struct, button)
+end
+
+function InputState.button_released(self, button)
+  return wrapper.input_button_released(self.struct, button)
+end
+
+-- The out parameters of the following functions are only supported by the LuaJIT FFI api
+function InputState.mouse_delta(self)
+  local dx, dy = ffi.new("int[1]"), ffi.new("int[1]")
+  wrapper.input_mouse_delta(self.struct, dx, dy)
+  return dx[0], dy[0]
+end
+
+function InputState.scroll(self)
+  local x, y = ffi.new("float[1]"), ffi.new("float[1]")
+  wrapper.input_scroll(self.struct, x, y)
+  return x[0], y[0]
+end
+
+-- InputMap object wrapper
+InputMap = {}
+InputMap.__index = InputMap
+
+function InputMap.create(self, filename)
+  local map = {}
+  setmetatable(map, InputMap)
+  if filename == nil then
+    map.struct = wrapper.create_input_map()
+  else
+    map.struct = wrapper.input_map_from_file(filename)
+  end
+  return map
+end
+
+function InputMap.free(self)
+  wrapper.free_input_map(self.struct)
+end
+
+function InputMap.bind_key(self, action, keycode)
+  wrapper.input_map_bind_key(self.struct, action, keycode)
+end
+
+function InputMap.bind_mouse_button(self, action, button)
+  wrapper.input_map_bind_mouse_button(self.struct, action, button)
+end
+
+function InputMap.bind_axis_keys(self, axis, negative, positive, scale)
+  wrapper.input_map_bind_axis_keys(self.struct, axis, negative, positive, scale or 1.0)
+end
+
+function InputMap.bind_mouse_axis(self, axis, mouse_axis, scale)
+  wrapper.input_map_bind_mouse_axis(self.struct, axis, mouse_axis, scale or 1.0)
+end
+
+function InputMap.update(self, events)
+  wrapper.input_map_update(self.struct, events.struct)
+end
+
+function InputMap.action_pressed(self, action)
+  return wrapper.action_pressed(self.struct, action)
+end
+
+function InputMap.action_held(self, action)
+  return wrapper.action_held(self.struct, action)
+end
+
+function InputMap.action_released(self, action)
+  return wrapper.action_released(self.struct, action)
+end
+
+function InputMap.axis(self, axis)
+  return wrapper.axis_value(self.struct, axis)
+end
+
+-- InputRecorder object wrapper
+InputRecorder = {}
+InputRecorder.__index = InputRecorder
+
+function InputRecorder.create(self)
+  local recorder = {}
+  setmetatable(recorder, InputRecorder)
+  recorder.struct = wrapper.create_input_recorder()
+  return recorder
+end
+
+function InputRecorder.free(self)
+  wrapper.free_input_recorder(self.struct)
+end
+
+function InputRecorder.record(self, events)
+  wrapper.input_recorder_record(self.struct, events.struct)
+end
+
+function InputRecorder.save(self, filename)
+  return wrapper.input_recorder_save(self.struct, filename)
+end
+
+-- InputReplay object wrapper, next_events replaces EventBuffer:get
+InputReplay = {}
+InputReplay.__index = InputReplay
+
+function InputReplay.create(self, filename)
+  local replay = {}
+  setmetatable(replay, InputReplay)
+  replay.struct = wrapper.input_replay_from_file(filename)
+  return replay
+end
+
+function InputReplay.free(self)
+  wrapper.free_input_replay(self.struct)
+end
+
+function InputReplay.next_events(self)
+  local events = {}
+  setmetatable(events, EventBuffer)
+  events.struct = wrapper.input_replay_next(self.struct)
+  return events
+end
+
+function InputReplay.finished(self)
+  return wrapper.input_replay_finished(self.struct)
+end
+
+function InputReplay.time(self)
+  return wrapper.input_replay_time(self.struct)
+end
+
+-- Console object wrapper, runs commands and holds console variables
+Console = {}
+Console.__index = Console
+
+function Console.create(self)
+  local console = {}
+  setmetatable(console, Console)
+  console.struct = wrapper.create_console()
+  return console
+end
+
+function Console.free(self)
+  wrapper.free_console(self.struct)
+end
+
+function Console.execute(self, line)
+  return wrapper.console_execute(self.struct, line)
+end
+
+function Console.process_input(self, console_input)
+  return wrapper.console_process_input(self.struct, console_input)
+end
+
+function Console.register(self, name, value, help)
+  help = help or ""
+  if type(value) == "boolean" then
+    wrapper.console_register_bool(self.struct, name, value, help)
+  elseif type(value) == "number" then
+    wrapper.console_register_float(self.struct, name, value, help)
+  else
+    wrapper.console_register_string(self.struct, name, tostring(value), help)
+  end
+end
+
+function Console.set(self, name, value)
+  return wrapper.console_set(self.struct, name, tostring(value))
+end
+
+function Console.get_bool(self, name)
+  return wrapper.console_get_bool(self.struct, name)
+end
+
+function Console.get_number(self, name)
+  return wrapper.console_get_float(self.struct, name)
+end
+
+-- ConsoleServer object wrapper, accepts remote console connections
+ConsoleServer = {}
+ConsoleServer.__index = ConsoleServer
+
+-- Listen on a local TCP port, or on a Unix socket when given a path
+function ConsoleServer.create(self, port_or_path)
+  local server = {}
+  setmetatable(server, ConsoleServer)
+  if type(port_or_path) == "number" then
+    server.struct = wrapper.create_console_server_tcp(port_or_path)
+  else
+    server.struct = wrapper.create_console_server_unix(port_or_path)
+  end
+  return server
+end
+
+function ConsoleServer.free(self)
+  wrapper.free_console_server(self.struct)
+end
+
+function ConsoleServer.port(self)
+  return wrapper.console_server_port(self.struct)
+end
+
+function ConsoleServer.client_count(self)
+  return wrapper.console_server_client_count(self.struct)
+end
+
+function ConsoleServer.process(self, console)
+  return wrapper.console_server_process(self.struct, console.struct)
+end
+
+-- MainLoop object wrapper, calls Lua functions from a loop run by quick3d
+-- Callbacks are only supported by the LuaJIT FFI api
+MainLoop = {}
+MainLoop.__index = MainLoop
+
+function MainLoop.create(self)
+  local main_loop = {}
+  setmetatable(main_loop, MainLoop)
+  main_loop.struct = check(wrapper.create_main_loop(nil))
+  main_loop.callbacks = {}
+  return main_loop
+end
+
+function MainLoop.free(self)
+  for _, callback in pairs(self.callbacks) do
+    callback:free()
+  end
+  self.callbacks = {}
+  wrapper.free_main_loop(self.struct)
+end
+
+-- The C callbacks must stay referenced while the loop can call them
+local function set_callback(self, kind, ctype, setter, f)
+  if self.callbacks[kind] then self.callbacks[kind]:free() end
+  self.callbacks[kind] = ffi.cast(ctype, f)
+  check(setter(self.struct, self.callbacks[kind]))
+end
+
+-- f(dt)
+function MainLoop.on_update(self, f)
+  set_callback(self, "update", "UpdateCallback", wrapper.main_loop_set_update,
+               function(_, dt) f(dt) end)
+end
+
+-- f()
+function MainLoop.on_render(self, f)
+  set_callback(self, "render", "RenderCallback", wrapper.main_loop_set_render,
+               function(_) f() end)
+end
+
+-- f(keycode, scancode, pressed)
+function MainLoop.on_key(self, f)
+  set_callback(self, "key", "KeyCallback", wrapper.main_loop_set_key,
+               function(_, keycode, scancode, pressed) f(keycode, scancode, pressed) end)
+end
+
+-- f(event), event is an EventData
+function MainLoop.on_mouse(self, f)
+  set_callback(self, "mouse", "MouseCallback", wrapper.main_loop_set_mouse,
+               function(_, event) f(event) end)
+end
+
+-- f(width, height)
+function MainLoop.on_resize(self, f)
+  set_callback(self, "resize", "ResizeCallback", wrapper.main_loop_set_resize,
+               function(_, width, height) f(width, height) end)
+end
+
+function MainLoop.set_max_fps(self, max_fps)
+  check(wrapper.main_loop_set_max_fps(self.struct, max_fps))
+end
+
+function MainLoop.run(self, display)
+  check(wrapper.main_loop_run(self.struct, display.struct))
+end
+
+function MainLoop.quit(self)
+  wrapper.main_loop_quit(self.struct)
+end
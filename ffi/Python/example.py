@@ -13,7 +13,7 @@ screen_width, screen_height = 640, 480
 def init():
     display = q3d.create_display(screen_width, screen_height, "PyQuick3D")
     camera = q3d.create_camera(screen_width, screen_height)
-    camera = q3d.camera_move_backward(camera, 6)
+    q3d.camera_move_backward_in_place(camera, 6)
     renderer = q3d.create_renderer_from_compressed_binary("../../test.bin.gz", display)
     shader = q3d.shader_default(display)
     console_reader = q3d.create_console_reader()
@@ -39,17 +39,17 @@ def main():
         if q3d.key_pressed(events, q3d.ESCAPE):
             q3d.window_hide(display)
         if q3d.key_pressed(events, q3d.W):
-            camera = q3d.camera_move_forward(camera, move_speed)
+            q3d.camera_move_forward_in_place(camera, move_speed)
         if q3d.key_pressed(events, q3d.S):
-            camera = q3d.camera_move_backward(camera, move_speed)
+            q3d.camera_move_backward_in_place(camera, move_speed)
         if q3d.key_pressed(events, q3d.A):
-            camera = q3d.camera_move_left(camera, move_speed)
+            q3d.camera_move_left_in_place(camera, move_speed)
         if q3d.key_pressed(events, q3d.D):
-            camera = q3d.camera_move_right(camera, move_speed)
+            q3d.camera_move_right_in_place(camera, move_speed)
         if q3d.key_pressed(events, q3d.LEFT):
-            camera = q3d.camera_aim(camera, rotate_speed, 0)
+            q3d.camera_aim_in_place(camera, rotate_speed, 0)
         if q3d.key_pressed(events, q3d.RIGHT):
-            camera = q3d.camera_aim(camera, -rotate_speed, 0)
+            q3d.camera_aim_in_place(camera, -rotate_speed, 0)
         q3d.free_events(events)
         console_command = q3d.read_console_buffer(console_reader)
         if len(console_command) > 0:
@@ -12,7 +12,7 @@ def init():
     screen_width, screen_height = 640, 480
     display = q3d.create_display(screen_width, screen_height, "PyQuick3DTest")
     camera = q3d.create_camera(screen_width, screen_height)
-    camera = q3d.camera_move_backward(camera, 6)
+    q3d.camera_move_backward_in_place(camera, 6)
     renderer = q3d.create_renderer_from_compressed_binary("../../test.bin.gz", display)
     shader = q3d.shader_default(display)
     return display, camera, renderer, shader
@@ -1,82 +1,377 @@
 /* Copyright (C) 2016 Chris Liebert */
 
+/* Generated from the Rust sources by build.rs, do not edit.
+ * Build with QUICK3D_UPDATE_HEADER set to regenerate this file.
+ */
+
+#ifndef _QUICK3D_H_
+#define _QUICK3D_H_
+
+#include <stdbool.h>
+#include <stdint.h>
+
+/* Errors
+ * No function panics across the C boundary. On failure handles are NULL, status functions
+ * return false, counts return -1, and quick3d_last_error() describes the error.
+ */
+
 typedef void* Camera;
+typedef void* CameraPath;
+typedef void* Console;
+typedef void* ConsoleInput;
+typedef void* ConsoleServer;
 typedef void* DBLoader;
-typedef void* Renderer;
-typedef void* Shader;
 typedef void* Display;
-typedef void* ConsoleInput;
 typedef void* EventBuffer;
+typedef void* InputMap;
+typedef void* InputRecorder;
+typedef void* InputReplay;
+typedef void* InputState;
+typedef void* MainLoop;
+typedef void* OrbitController;
+typedef void* Renderer;
+typedef void* Scene;
+typedef void* Shader;
 
 typedef enum KeyCode {
-    KEY1, KEY2, KEY3, KEY4, KEY5, KEY6, KEY7, KEY8, KEY9, KEY0,
-    A, B, C, D, E, F, G, H,I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
-    ESCAPE,
-    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11,F12, F13, F14, F15,
-    SNAPSHOT, SCROLL, PAUSE, INSERT, HOME, DELETE, END, PAGEDOWN, PAGEUP,
-	LEFT, UP, RIGHT, DOWN, BACK, RETURN, SPACE, NUMLOCK,
-    NUMPAD0, NUMPAD1, NUMPAD2, NUMPAD3, NUMPAD4, NUMPAD5, NUMPAD6, NUMPAD7, NUMPAD8, NUMPAD9,
-    ABNTC1, ABNTC2, ADD, APOSTROPHE, APPS, AT, AX, BACKSLASH, CALCULATOR, CAPITAL, COLON, COMMA,
-    CONVERT, DECIMAL, DIVIDE, EQUALS, GRAVE, KANA, KANJI, LALT, LBRACKET, LCONTROL, LMENU, LSHIFT, LWIN,
-    MAIL, MEDIASELECT, MEDIASTOP, MINUS, MULTIPLY, MUTE, MYCOMPUTER, NAVIGATEFORWARD, NAVIGATEBACKWARD, NEXTTRACK,
-	NOCONVERT, NUMPADCOMMA, NUMPADENTER, NUMPADEQUALS, OEM102, PERIOD, PLAYPAUSE, POWER, PREVTRACK, RALT, RBRACKET,
-	RCONTROL, RMENU, RSHIFT, RWIN, SEMICOLON, SLASH, SLEEP, STOP, SUBTRACT, SYSRQ, TAB, UNDERLINE, UNLABELED,
-	VOLUMEDOWN, VOLUMEUP, WAKE, WEBBACK, WEBFAVORITES, WEBFORWARD, WEBHOME, WEBREFRESH, WEBSEARCH, WEBSTOP, YEN,
+    KEY1, KEY2, KEY3, KEY4, KEY5, KEY6, KEY7, KEY8, KEY9, KEY0, A, B, C, D, E, F, G, H, I, J, K, L,
+    M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z, ESCAPE, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11,
+    F12, F13, F14, F15, SNAPSHOT, SCROLL, PAUSE, INSERT, HOME, DELETE, END, PAGEDOWN, PAGEUP, LEFT,
+    UP, RIGHT, DOWN, BACK, RETURN, SPACE, NUMLOCK, NUMPAD0, NUMPAD1, NUMPAD2, NUMPAD3, NUMPAD4,
+    NUMPAD5, NUMPAD6, NUMPAD7, NUMPAD8, NUMPAD9, ABNTC1, ABNTC2, ADD, APOSTROPHE, APPS, AT, AX,
+    BACKSLASH, CALCULATOR, CAPITAL, COLON, COMMA, CONVERT, DECIMAL, DIVIDE, EQUALS, GRAVE, KANA,
+    KANJI, LALT, LBRACKET, LCONTROL, LMENU, LSHIFT, LWIN, MAIL, MEDIASELECT, MEDIASTOP, MINUS,
+    MULTIPLY, MUTE, MYCOMPUTER, NAVIGATEFORWARD, NAVIGATEBACKWARD, NEXTTRACK, NOCONVERT,
+    NUMPADCOMMA, NUMPADENTER, NUMPADEQUALS, OEM102, PERIOD, PLAYPAUSE, POWER, PREVTRACK, RALT,
+    RBRACKET, RCONTROL, RMENU, RSHIFT, RWIN, SEMICOLON, SLASH, SLEEP, STOP, SUBTRACT, SYSRQ, TAB,
+    UNDERLINE, UNLABELED, VOLUMEDOWN, VOLUMEUP, WAKE, WEBBACK, WEBFAVORITES, WEBFORWARD, WEBHOME,
+    WEBREFRESH, WEBSEARCH, WEBSTOP, YEN,
 } KeyCode;
 
 typedef struct Mouse {
-	int x, y;
+    int x;
+    int y;
 } Mouse;
 
+typedef enum EventKind {
+    EVENT_RESIZED, EVENT_MOVED, EVENT_CLOSED, EVENT_DROPPED_FILE, EVENT_RECEIVED_CHARACTER,
+    EVENT_FOCUSED, EVENT_KEYBOARD_INPUT, EVENT_MOUSE_MOVED, EVENT_MOUSE_ENTERED, EVENT_MOUSE_LEFT,
+    EVENT_MOUSE_WHEEL, EVENT_MOUSE_INPUT, EVENT_TOUCHPAD_PRESSURE, EVENT_AWAKENED, EVENT_REFRESH,
+    EVENT_SUSPENDED, EVENT_TOUCH,
+} EventKind;
+
+typedef struct EventData {
+    EventKind kind;
+    int x;
+    int y;
+    double fx;
+    double fy;
+    bool pressed;
+    int keycode;
+    int scancode;
+    int button;
+    int phase;
+    int index;
+    unsigned int character;
+    long long id;
+} EventData;
+
+typedef struct Ray {
+    float origin[3];
+    float direction[3];
+} Ray;
+
+typedef struct RayHit {
+    int mesh_index;
+    int triangle_index;
+    float distance;
+    float point[3];
+    float normal[3];
+    float barycentric[3];
+    float texcoord[2];
+} RayHit;
+
+typedef struct FrameStats {
+    float frame_time;
+    float render_time;
+    unsigned int draw_calls;
+    unsigned int meshes_drawn;
+    unsigned int meshes_culled;
+    unsigned long long vertices;
+    unsigned long long texture_memory;
+} FrameStats;
+
+typedef struct Viewport {
+    unsigned int x;
+    unsigned int y;
+    unsigned int width;
+    unsigned int height;
+    bool clear;
+    float clear_color[4];
+} Viewport;
+
+typedef void (*UpdateCallback)(void* user_data, double dt);
+typedef void (*RenderCallback)(void* user_data);
+typedef void (*KeyCallback)(void* user_data, int keycode, int scancode, bool pressed);
+typedef void (*MouseCallback)(void* user_data, EventData* event);
+typedef void (*ResizeCallback)(void* user_data, int width, int height);
+
+/* console.rs */
+extern Console create_console();
+extern void free_console(Console ptr);
+extern bool console_execute(Console console, const char* line);
+extern int console_process_input(Console console, ConsoleInput input);
+extern char* console_complete(Console console, const char* prefix);
+extern bool console_register_bool(Console console, const char* name, bool value, const char* help);
+extern bool console_register_int(Console console, const char* name, int value, const char* help);
+extern bool console_register_float(Console console, const char* name, double value, const char* help);
+extern bool console_register_string(Console console, const char* name, const char* value, const char* help);
+extern bool console_set(Console console, const char* name, const char* value);
+extern bool console_get_bool(Console console, const char* name);
+extern int console_get_int(Console console, const char* name);
+extern double console_get_float(Console console, const char* name);
+extern char* console_get_string(Console console, const char* name);
+
+/* dbloader.rs */
+extern void free_db_loader(DBLoader ptr);
+extern DBLoader create_db_loader(const char* filename);
+
+/* ffi.rs */
+extern const char* quick3d_last_error();
+extern void quick3d_clear_error();
+
+/* input.rs */
+extern bool display_closed(EventBuffer buffer);
+extern EventBuffer get_events(Display display);
+extern bool events_empty(EventBuffer buffer);
+extern bool events_resized(EventBuffer buffer, int* width, int* height);
+extern int events_count(EventBuffer buffer);
+extern bool events_get(EventBuffer buffer, int index, EventData* data);
+extern bool events_focused(EventBuffer buffer, bool* focused);
+extern bool events_scroll(EventBuffer buffer, float* x, float* y);
+extern char* events_text(EventBuffer buffer);
+extern int events_dropped_file_count(EventBuffer buffer);
+extern char* events_dropped_file(EventBuffer buffer, int index);
+extern void free_string(char* ptr);
+extern void free_events(EventBuffer ptr);
+extern void free_mouse(Mouse* ptr);
+extern bool print_events(EventBuffer ptr);
+extern bool key_pressed(EventBuffer buffer, KeyCode keycode);
+extern bool key_released(EventBuffer buffer, KeyCode keycode);
+extern Mouse* mouse_moved(EventBuffer buffer);
+extern bool mouse_pressed_left(EventBuffer buffer);
+extern bool mouse_pressed_right(EventBuffer buffer);
+extern bool mouse_released_left(EventBuffer buffer);
+extern bool mouse_released_right(EventBuffer buffer);
+extern InputState create_input_state();
+extern void free_input_state(InputState ptr);
+extern bool input_state_update(InputState state, EventBuffer buffer);
+extern bool input_key_down(InputState state, KeyCode keycode);
+extern bool input_key_pressed(InputState state, KeyCode keycode);
+extern bool input_key_released(InputState state, KeyCode keycode);
+extern bool input_button_down(InputState state, int button);
+extern bool input_button_pressed(InputState state, int button);
+extern bool input_button_released(InputState state, int button);
+extern bool input_mouse_position(InputState state, int* x, int* y);
+extern bool input_mouse_delta(InputState state, int* dx, int* dy);
+extern bool input_scroll(InputState state, float* x, float* y);
+extern ConsoleInput create_console_reader();
+extern bool console_is_closed(ConsoleInput console);
+extern char* read_console_buffer(ConsoleInput console);
+extern void wait_console_quit(ConsoleInput handle);
+
+/* inputmap.rs */
+extern InputMap create_input_map();
+extern InputMap input_map_from_file(const char* filename);
+extern void free_input_map(InputMap ptr);
+extern bool input_map_bind_key(InputMap map, const char* action, KeyCode key);
+extern bool input_map_bind_mouse_button(InputMap map, const char* action, int button);
+extern bool input_map_bind_axis_keys(InputMap map, const char* axis, KeyCode negative, KeyCode positive, float scale);
+extern bool input_map_bind_mouse_axis(InputMap map, const char* axis, int mouse_axis, float scale);
+extern bool input_map_parse_line(InputMap map, const char* line);
+extern bool input_map_update(InputMap map, EventBuffer events);
+extern bool action_pressed(InputMap map, const char* action);
+extern bool action_held(InputMap map, const char* action);
+extern bool action_released(InputMap map, const char* action);
+extern float axis_value(InputMap map, const char* axis);
+
+/* recording.rs */
+extern InputRecorder create_input_recorder();
+extern void free_input_recorder(InputRecorder ptr);
+extern bool input_recorder_record(InputRecorder recorder, EventBuffer events);
+extern bool input_recorder_save(InputRecorder recorder, const char* file);
+extern InputReplay input_replay_from_file(const char* file);
+extern void free_input_replay(InputReplay ptr);
+extern EventBuffer input_replay_next(InputReplay replay);
+extern bool input_replay_finished(InputReplay replay);
+extern double input_replay_time(InputReplay replay);
+
+/* remote.rs */
+extern ConsoleServer create_console_server_tcp(int port);
+extern ConsoleServer create_console_server_unix(const char* path);
+extern void free_console_server(ConsoleServer ptr);
+extern int console_server_port(ConsoleServer server);
+extern int console_server_client_count(ConsoleServer server);
+extern int console_server_process(ConsoleServer server, Console console);
+extern char* console_server_read(ConsoleServer server);
+
+/* camera.rs */
 extern Camera camera_aim(Camera camera, double x, double y);
 extern Camera camera_move_forward(Camera camera, float amount);
 extern Camera camera_move_backward(Camera camera, float amount);
 extern Camera camera_move_left(Camera camera, float amount);
 extern Camera camera_move_right(Camera camera, float amount);
+extern Camera camera_look_at(Camera camera, float target_x, float target_y, float target_z, float up_x, float up_y, float up_z);
+extern Camera camera_set_position(Camera camera, float x, float y, float z);
+extern Camera camera_rotate(Camera camera, float axis_x, float axis_y, float axis_z, float angle);
+extern Camera camera_roll(Camera camera, float angle);
+extern Camera camera_set_fov(Camera camera, float fov);
+extern Camera camera_set_clip_planes(Camera camera, float near, float far);
+extern Camera camera_set_orthographic(Camera camera, float zoom);
+extern Camera camera_set_perspective(Camera camera);
+extern Camera camera_resize(Camera camera, float screen_width, float screen_height);
+extern Camera camera_frame_scene(Camera camera, Renderer renderer);
 extern Camera create_camera(float screen_width, float screen_height);
-extern ConsoleInput create_console_reader();
-extern DBLoader create_db_loader(const char* filename);
-extern Display create_display(int screen_width, int screen_height, const char* title);
-extern Display create_hidden_display(int screen_width, int screen_height, const char* title);
-extern Renderer create_renderer_from_binary(const char* filename, Display display);
-extern Renderer create_renderer_from_compressed_binary(const char* filename, Display display);
-extern Renderer create_renderer_from_db_loader(DBLoader loader, Display display);
-extern bool console_is_closed(ConsoleInput console);
+extern void free_camera(Camera ptr);
+extern bool camera_aim_in_place(Camera camera, double x, double y);
+extern bool camera_move_forward_in_place(Camera camera, float amount);
+extern bool camera_move_backward_in_place(Camera camera, float amount);
+extern bool camera_move_left_in_place(Camera camera, float amount);
+extern bool camera_move_right_in_place(Camera camera, float amount);
+extern bool camera_look_at_in_place(Camera camera, float target_x, float target_y, float target_z, float up_x, float up_y, float up_z);
+extern bool camera_rotate_in_place(Camera camera, float axis_x, float axis_y, float axis_z, float angle);
+extern bool camera_roll_in_place(Camera camera, float angle);
+extern bool camera_frame_scene_in_place(Camera camera, Renderer renderer);
+extern bool camera_resize_in_place(Camera camera, float screen_width, float screen_height);
+extern bool camera_set_fov_in_place(Camera camera, float fov);
+extern bool camera_set_clip_planes_in_place(Camera camera, float near, float far);
+extern bool camera_set_orthographic_in_place(Camera camera, float zoom);
+extern bool camera_set_perspective_in_place(Camera camera);
+extern bool camera_set_position_in_place(Camera camera, float x, float y, float z);
+extern bool camera_set_direction_in_place(Camera camera, float x, float y, float z);
+extern bool camera_set_orientation_in_place(Camera camera, float x, float y, float z, float w);
+extern bool camera_get_position(Camera camera, float out[3]);
+extern bool camera_get_direction(Camera camera, float out[3]);
+extern bool camera_get_right(Camera camera, float out[3]);
+extern bool camera_get_up(Camera camera, float out[3]);
+extern bool camera_get_orientation(Camera camera, float out[4]);
+extern bool camera_get_modelview_matrix(Camera camera, float out[16]);
+extern bool camera_get_projection_matrix(Camera camera, float out[16]);
 
-extern bool display_closed(EventBuffer event);
-extern EventBuffer get_events(Display display);
-extern bool events_empty(EventBuffer events);
-
-extern void print_events(EventBuffer events);
-extern bool key_pressed(EventBuffer event, KeyCode keycode);
-extern bool key_released(EventBuffer event, KeyCode keycode);
-extern bool mouse_pressed_left(EventBuffer event);
-extern bool mouse_pressed_right(EventBuffer event);
-extern bool mouse_released_left(EventBuffer event);
-extern bool mouse_released_right(EventBuffer event);
-extern Mouse* mouse_moved(EventBuffer event);
-
-extern void free_camera(Camera camera);
-extern void free_db_loader(DBLoader dbloader);
-extern void free_display(Display memory);
-extern void free_events(EventBuffer events);
-extern void free_mouse(Mouse* mouse);
-extern void free_renderer(Renderer renderer);
-extern void free_shader(Shader shader);
-
-extern Shader get_shader_from_dbloader(const char* name, DBLoader dbloader, Display display);
-extern Shader get_shader_from_source(const char* vertex, const char* fragment, Display display);
+/* animation.rs */
+extern CameraPath create_camera_path(bool catmull_rom);
+extern CameraPath camera_path_from_file(const char* file);
+extern bool camera_path_to_file(CameraPath path, const char* file);
+extern bool camera_path_add_keyframe(CameraPath path, float time, Camera camera);
+extern bool camera_path_set_looping(CameraPath path, bool looping);
+extern bool camera_path_set_loop_period(CameraPath path, float period);
+extern bool camera_path_set_interpolation(CameraPath path, int interpolation);
+extern bool camera_path_set_easing(CameraPath path, int easing);
+extern bool camera_path_finished(CameraPath path, float time);
+extern Camera camera_path_apply(CameraPath path, Camera camera, float time);
+extern bool camera_path_apply_in_place(CameraPath path, Camera camera, float time);
+extern void free_camera_path(CameraPath ptr);
+
+/* orbit.rs */
+extern OrbitController create_orbit_controller(float x, float y, float z, float distance);
+extern void free_orbit_controller(OrbitController ptr);
+extern bool orbit_rotate(OrbitController orbit, double x, double y);
+extern bool orbit_pan(OrbitController orbit, float x, float y);
+extern bool orbit_dolly(OrbitController orbit, float amount);
+extern bool orbit_zoom(OrbitController orbit, float steps);
+extern bool orbit_frame_scene(OrbitController orbit, Renderer renderer, Camera camera);
+extern Camera orbit_apply(OrbitController orbit, Camera camera);
+extern bool orbit_apply_in_place(OrbitController orbit, Camera camera);
+
+/* scene.rs */
+extern Scene create_scene();
+extern void free_scene(Scene ptr);
+extern int scene_add_material(Scene scene, const char* name, float r, float g, float b, const char* diffuse_texname);
+extern int scene_add_mesh(Scene scene, const char* name, const float* positions, const float* normals, const float* texcoords, int vertex_count, int material_index);
+extern bool scene_add_texture(Scene scene, const char* name, const unsigned char* data, int length);
+extern bool scene_add_texture_rgba(Scene scene, const char* name, const unsigned char* pixels, int width, int height);
+extern int scene_mesh_count(Scene scene);
+extern Renderer create_renderer_from_scene(Scene scene, Display display);
+
+/* shader.rs */
 extern Shader shader_default(Display display);
+extern void free_shader(Shader ptr);
+extern Shader get_shader_from_dbloader(const char* shader_name, DBLoader dbloader, Display display);
+extern Shader get_shader_from_source(const char* vertex, const char* fragment, Display display);
 extern bool shader_source_is_valid(const char* vertex, const char* fragment, Display display);
-extern char* read_console_buffer(ConsoleInput console);
-extern void render(Renderer renderer, Shader shader, Camera camera, Display display);
-extern void wait_console_quit(ConsoleInput console);
-extern void window_hide(Display display);
-extern void window_show(Display display);
+
+/* renderer.rs */
+extern Renderer create_renderer_from_db_loader(DBLoader dbloader, Display display);
+extern Renderer create_renderer_from_compressed_binary(const char* file, Display display);
+extern Renderer create_renderer_from_binary(const char* file, Display display);
+extern void free_renderer(Renderer ptr);
+extern bool render(Renderer renderer, Shader shader_program, Camera camera, Display display);
+
+/* raycast.rs */
+extern Ray* camera_ray_from_mouse(Camera camera, Mouse* mouse, float screen_width, float screen_height);
+extern RayHit* renderer_raycast(Renderer renderer, Ray* ray);
+extern RayHit* renderer_pick_ray(Renderer renderer, Camera camera, Mouse* mouse, float screen_width, float screen_height);
+extern void free_ray(Ray* ptr);
+extern void free_ray_hit(RayHit* ptr);
+
+/* picking.rs */
+extern bool renderer_enable_picking(Renderer renderer, Display display, int screen_width, int screen_height);
+extern bool renderer_disable_picking(Renderer renderer);
+extern int renderer_pick(Renderer renderer, Mouse* mouse);
+extern int renderer_pick_region(Renderer renderer, int x0, int y0, int x1, int y1, int* indices, int capacity);
+
+/* stats.rs */
+extern bool renderer_frame_stats(Renderer renderer, FrameStats* stats);
+extern bool renderer_average_frame_stats(Renderer renderer, FrameStats* stats);
+extern bool renderer_enable_stats_overlay(Renderer renderer, Display display);
+extern bool renderer_disable_stats_overlay(Renderer renderer);
+
+/* profiler.rs */
+extern bool renderer_enable_profiling(Renderer renderer, bool gpu_timing);
+extern bool renderer_disable_profiling(Renderer renderer);
+extern bool renderer_profile_begin(Renderer renderer, const char* name);
+extern bool renderer_profile_end(Renderer renderer);
+extern bool renderer_save_chrome_trace(Renderer renderer, const char* file);
+
+/* text.rs */
+extern bool renderer_load_font(Renderer renderer, Display display, const char* file, float size);
+extern bool renderer_draw_text(Renderer renderer, const char* text, float x, float y, float r, float g, float b, float a, float scale);
+extern bool renderer_draw_text_world(Renderer renderer, const char* text, float x, float y, float z, float r, float g, float b, float a, float scale);
+extern bool renderer_measure_text(Renderer renderer, const char* text, float scale, float* width, float* height);
+
+/* overlay.rs */
+extern bool renderer_add_texture(Renderer renderer, Display display, const char* name, const unsigned char* data, int length);
+extern bool renderer_draw_rect(Renderer renderer, float x, float y, float width, float height, float r, float g, float b, float a, int z);
+extern bool renderer_draw_line(Renderer renderer, float x0, float y0, float x1, float y1, float width, float r, float g, float b, float a, int z);
+extern bool renderer_draw_image(Renderer renderer, const char* texture, float x, float y, float width, float height, const float* source, float r, float g, float b, float a, int z);
+
+/* viewport.rs */
+extern bool render_viewports(Renderer renderer, Shader shader_program, Viewport* viewports, Camera* cameras, int count, Display display);
+extern bool render_stereo(Renderer renderer, Shader shader_program, Camera camera, float interpupillary_distance, float convergence, Display display);
+extern Camera camera_stereo_eye(Camera camera, float interpupillary_distance, float convergence, bool right);
+
+/* mainloop.rs */
+extern MainLoop create_main_loop(void* user_data);
+extern void free_main_loop(MainLoop ptr);
+extern bool main_loop_set_update(MainLoop main_loop, UpdateCallback callback);
+extern bool main_loop_set_render(MainLoop main_loop, RenderCallback callback);
+extern bool main_loop_set_key(MainLoop main_loop, KeyCallback callback);
+extern bool main_loop_set_mouse(MainLoop main_loop, MouseCallback callback);
+extern bool main_loop_set_resize(MainLoop main_loop, ResizeCallback callback);
+extern bool main_loop_set_max_fps(MainLoop main_loop, int max_fps);
+extern bool main_loop_run(MainLoop main_loop, Display display);
+extern bool main_loop_quit(MainLoop main_loop);
+
+/* lib.rs */
+extern bool obj2sqlite(const char* wavefront_file, const char* database_file);
+extern bool obj2bin(const char* wavefront_file, const char* binfile);
+extern bool obj2compressed(const char* wavefront_file, const char* binfile);
+extern Display create_display(int screen_width, int screen_height, const char* title);
+extern Display create_hidden_display(int screen_width, int screen_height, const char* title);
+extern void free_display(Display ptr);
 extern void thread_sleep(int ms);
 extern void thread_yield();
+extern bool window_hide(Display display);
+extern bool window_show(Display display);
 
-/* C/C++ Methods */
-extern void obj2sqlite(const char* wavefront, const char* database);
-extern void obj2bin(const char* wavefront, const char* filename);
-extern void obj2compressed(const char* wavefront, const char* filename);
+#endif /* _QUICK3D_H_ */
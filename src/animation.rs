@@ -0,0 +1,433 @@
+// Copyright (C) 2016 Chris Liebert
+
+extern crate libc;
+
+use bincode::rustc_serialize::{encode_into, decode_from};
+use bincode::SizeLimit::Infinite;
+use std::fs::File;
+use std::io::{BufWriter, BufReader};
+
+use nalgebra::Vector3;
+
+use camera::Camera;
+use error::Error;
+use ffi;
+
+/// A position and orientation of a `Camera` at a point in time
+///
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct CameraKeyframe {
+    pub time: f32,
+    pub position: [f32; 3],
+    pub orientation: [f32; 4],
+}
+
+/// The curve used to move between keyframe positions
+///
+/// `CatmullRom` passes through every keyframe. `Bezier` treats the keyframes as the control
+/// points of a single curve which starts and ends on the first and last keyframes but only
+/// approaches the ones in between.
+///
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum Interpolation {
+    Linear,
+    CatmullRom,
+    Bezier,
+}
+
+/// Remapping of time along the whole path
+///
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    /// Remap a normalized time in [0, 1]
+    ///
+    pub fn apply(&self, t: f32) -> f32 {
+        let t: f32 = t.max(0.0).min(1.0);
+        match *self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// A timed camera path for fly-throughs and repeatable benchmarks
+///
+/// Keyframes are kept sorted by time. Orientations are spherically interpolated between the
+/// keyframes surrounding the sample time. A looping path returns from the last keyframe to the
+/// first over a closing segment, so one pass takes `loop_period` seconds. When `loop_period` is
+/// not longer than the keyframes the closing segment takes the average time between keyframes.
+/// A path can be saved to and loaded from a binary file so the same path can be replayed across
+/// runs.
+///
+/// ```c
+/// /* C representation */
+/// typedef void* CameraPath;
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct CameraPath {
+    pub keyframes: Vec<CameraKeyframe>,
+    pub interpolation: Interpolation,
+    pub easing: Easing,
+    pub looping: bool,
+    pub loop_period: f32,
+}
+
+fn lerp(a: &[f32; 3], b: &[f32; 3], t: f32) -> [f32; 3] {
+    [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t, a[2] + (b[2] - a[2]) * t]
+}
+
+fn catmull_rom(p0: &[f32; 3], p1: &[f32; 3], p2: &[f32; 3], p3: &[f32; 3], t: f32) -> [f32; 3] {
+    let t2: f32 = t * t;
+    let t3: f32 = t2 * t;
+    let mut p: [f32; 3] = [0.0; 3];
+    for i in 0..3 {
+        p[i] = 0.5 * ((2.0 * p1[i]) +
+                      (-p0[i] + p2[i]) * t +
+                      (2.0 * p0[i] - 5.0 * p1[i] + 4.0 * p2[i] - p3[i]) * t2 +
+                      (-p0[i] + 3.0 * p1[i] - 3.0 * p2[i] + p3[i]) * t3);
+    }
+    p
+}
+
+/// Evaluate a Bezier curve with any number of control points using de Casteljau's algorithm
+///
+fn bezier(points: &[[f32; 3]], t: f32) -> [f32; 3] {
+    let mut points: Vec<[f32; 3]> = points.to_vec();
+    while points.len() > 1 {
+        for i in 0..points.len() - 1 {
+            points[i] = lerp(&points[i], &points[i + 1], t);
+        }
+        points.pop();
+    }
+    points[0]
+}
+
+/// Spherical linear interpolation between two unit quaternions
+///
+fn slerp(a: &[f32; 4], b: &[f32; 4], t: f32) -> [f32; 4] {
+    let mut cos_theta: f32 = a[0] * b[0] + a[1] * b[1] + a[2] * b[2] + a[3] * b[3];
+    let mut b: [f32; 4] = *b;
+    if cos_theta < 0.0 {
+        // Take the shorter way around
+        cos_theta = -cos_theta;
+        b = [-b[0], -b[1], -b[2], -b[3]];
+    }
+    let (wa, wb) = if cos_theta > 0.9995 {
+        (1.0 - t, t)
+    } else {
+        let theta: f32 = cos_theta.acos();
+        let sin_theta: f32 = theta.sin();
+        (((1.0 - t) * theta).sin() / sin_theta, (t * theta).sin() / sin_theta)
+    };
+    let q: [f32; 4] = [wa * a[0] + wb * b[0], wa * a[1] + wb * b[1], wa * a[2] + wb * b[2], wa * a[3] + wb * b[3]];
+    let length: f32 = (q[0] * q[0] + q[1] * q[1] + q[2] * q[2] + q[3] * q[3]).sqrt();
+    [q[0] / length, q[1] / length, q[2] / length, q[3] / length]
+}
+
+impl CameraPath {
+    /// Create an empty `CameraPath`
+    ///
+    pub fn new(interpolation: Interpolation) -> CameraPath {
+        CameraPath {
+            keyframes: Vec::new(),
+            interpolation: interpolation,
+            easing: Easing::Linear,
+            looping: false,
+            loop_period: 0.0,
+        }
+    }
+
+    /// Add a keyframe, keeping the keyframes sorted by time
+    ///
+    pub fn add_keyframe(&mut self, keyframe: CameraKeyframe) {
+        let index: usize = self.keyframes.iter().position(|k| k.time > keyframe.time).unwrap_or(self.keyframes.len());
+        self.keyframes.insert(index, keyframe);
+    }
+
+    /// Add a keyframe at the current position and orientation of a `Camera`
+    ///
+    pub fn add_camera(&mut self, time: f32, camera: &Camera) {
+        self.add_keyframe(CameraKeyframe {
+            time: time,
+            position: [camera.position.x, camera.position.y, camera.position.z],
+            orientation: camera.orientation,
+        });
+    }
+
+    pub fn start_time(&self) -> f32 {
+        self.keyframes.first().map(|k| k.time).unwrap_or(0.0)
+    }
+
+    pub fn end_time(&self) -> f32 {
+        self.keyframes.last().map(|k| k.time).unwrap_or(0.0)
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.end_time() - self.start_time()
+    }
+
+    /// The time taken by one pass along the path, including the closing segment when looping
+    ///
+    pub fn period(&self) -> f32 {
+        let duration: f32 = self.duration();
+        if !self.looping || self.keyframes.len() < 2 {
+            return duration;
+        }
+        match self.loop_period > duration {
+            true => self.loop_period,
+            false => duration + duration / (self.keyframes.len() - 1) as f32,
+        }
+    }
+
+    /// Check if a non-looping path has finished at time t
+    ///
+    pub fn finished(&self, t: f32) -> bool {
+        !self.looping && t >= self.end_time()
+    }
+
+    /// The keyframe index wrapped (when looping) or clamped to the ends of the path
+    ///
+    fn keyframe_at(&self, index: isize) -> &CameraKeyframe {
+        let count: isize = self.keyframes.len() as isize;
+        let index: isize = match self.looping {
+            true => ((index % count) + count) % count,
+            false => index.max(0).min(count - 1),
+        };
+        &self.keyframes[index as usize]
+    }
+
+    /// Interpolate the position and orientation at time t
+    ///
+    pub fn sample(&self, t: f32) -> Option<CameraKeyframe> {
+        if self.keyframes.is_empty() {
+            return None;
+        }
+        let duration: f32 = self.duration();
+        if self.keyframes.len() == 1 || duration <= 0.0 {
+            return Some(CameraKeyframe { time: t, .. self.keyframes[0] });
+        }
+
+        let start: f32 = self.start_time();
+        let period: f32 = self.period();
+        let mut normalized: f32 = (t - start) / period;
+        if self.looping {
+            normalized = normalized - normalized.floor();
+        }
+        let local_time: f32 = start + self.easing.apply(normalized) * period;
+
+        // Find the segment containing the eased time, the closing segment follows the last keyframe
+        let count: usize = self.keyframes.len();
+        let mut segment: usize = 0;
+        while segment + 2 < count && self.keyframes[segment + 1].time <= local_time {
+            segment += 1;
+        }
+        if self.looping && local_time >= self.end_time() {
+            segment = count - 1;
+        }
+        let k1: &CameraKeyframe = &self.keyframes[segment];
+        let k2: &CameraKeyframe = self.keyframe_at(segment as isize + 1);
+        let span: f32 = match segment + 1 == count {
+            true => period - duration,
+            false => k2.time - k1.time,
+        };
+        let u: f32 = match span > 0.0 {
+            true => ((local_time - k1.time) / span).max(0.0).min(1.0),
+            false => 0.0,
+        };
+
+        let position: [f32; 3] = match self.interpolation {
+            Interpolation::Linear => lerp(&k1.position, &k2.position, u),
+            Interpolation::CatmullRom => {
+                let k0: &CameraKeyframe = self.keyframe_at(segment as isize - 1);
+                let k3: &CameraKeyframe = self.keyframe_at(segment as isize + 2);
+                catmull_rom(&k0.position, &k1.position, &k2.position, &k3.position, u)
+            },
+            Interpolation::Bezier => {
+                let mut points: Vec<[f32; 3]> = self.keyframes.iter().map(|k| k.position).collect();
+                if self.looping {
+                    points.push(self.keyframes[0].position);
+                }
+                bezier(&points, (local_time - start) / period)
+            },
+        };
+
+        Some(CameraKeyframe {
+            time: t,
+            position: position,
+            orientation: slerp(&k1.orientation, &k2.orientation, u),
+        })
+    }
+
+    /// Move a `Camera` to the sampled position and orientation at time t
+    ///
+    pub fn apply(&self, camera: &Camera, t: f32) -> Camera {
+        match self.sample(t) {
+            Some(k) => {
+                let position: Vector3<f32> = Vector3::new(k.position[0], k.position[1], k.position[2]);
+                camera.set_position(&position).set_orientation(k.orientation)
+            },
+            None => camera.clone(),
+        }
+    }
+
+    pub fn from_binary_file(filename: String) -> Result<CameraPath, Error> {
+        let file = try!(File::open(filename.clone()));
+        let mut reader = BufReader::new(file);
+        let path: CameraPath = try!(decode_from(&mut reader, Infinite));
+        Ok(path)
+    }
+
+    pub fn to_binary_file(&self, filename: String) -> Result<(), Error> {
+        let file = try!(File::create(filename.clone()));
+        let mut writer = BufWriter::new(file);
+        try!(encode_into(&self, &mut writer, Infinite));
+        Ok(())
+    }
+}
+
+/// `extern CameraPath create_camera_path(bool catmull_rom);`
+///
+#[no_mangle]
+pub extern "C" fn create_camera_path(catmull_rom: bool) -> Option<Box<CameraPath>> {
+    ffi::guard(|| {
+        match catmull_rom {
+            true => Box::new(CameraPath::new(Interpolation::CatmullRom)),
+            false => Box::new(CameraPath::new(Interpolation::Linear)),
+        }
+    })
+}
+
+/// `extern CameraPath camera_path_from_file(const char* file);`
+///
+#[no_mangle]
+pub extern "C" fn camera_path_from_file(file: *const libc::c_char) -> Option<Box<CameraPath>> {
+    ffi::guard_result(|| -> Result<Box<CameraPath>, Error> {
+        let filename: String = try!(ffi::c_string(file));
+        let path: CameraPath = try!(CameraPath::from_binary_file(filename));
+        Ok(Box::new(path))
+    })
+}
+
+/// `extern bool camera_path_to_file(CameraPath path, const char* file);`
+///
+#[no_mangle]
+pub extern "C" fn camera_path_to_file(path: &CameraPath, file: *const libc::c_char) -> bool {
+    ffi::guard_result_status(|| {
+        let filename: String = try!(ffi::c_string(file));
+        path.to_binary_file(filename)
+    })
+}
+
+/// `extern bool camera_path_add_keyframe(CameraPath path, float time, Camera camera);`
+///
+#[no_mangle]
+pub extern "C" fn camera_path_add_keyframe(path: &mut CameraPath, time: libc::c_float, camera: &Camera) -> bool {
+    ffi::guard_status(|| {
+        path.add_camera(time as f32, camera);
+    })
+}
+
+/// `extern bool camera_path_set_looping(CameraPath path, bool looping);`
+///
+#[no_mangle]
+pub extern "C" fn camera_path_set_looping(path: &mut CameraPath, looping: bool) -> bool {
+    ffi::guard_status(|| {
+        path.looping = looping;
+    })
+}
+
+/// `extern bool camera_path_set_loop_period(CameraPath path, float period);`
+///
+/// Set the time taken by one pass of a looping path, including the return to the first keyframe.
+///
+#[no_mangle]
+pub extern "C" fn camera_path_set_loop_period(path: &mut CameraPath, period: libc::c_float) -> bool {
+    ffi::guard_status(|| {
+        path.loop_period = period as f32;
+    })
+}
+
+/// `extern bool camera_path_set_interpolation(CameraPath path, int interpolation);`
+///
+/// Interpolations are numbered 0 (linear), 1 (Catmull-Rom) and 2 (Bezier).
+///
+#[no_mangle]
+pub extern "C" fn camera_path_set_interpolation(path: &mut CameraPath, interpolation: libc::c_int) -> bool {
+    ffi::guard_result_status(|| {
+        path.interpolation = match interpolation {
+            0 => Interpolation::Linear,
+            1 => Interpolation::CatmullRom,
+            2 => Interpolation::Bezier,
+            _ => return Err(Error::InvalidArgument(format!("Unknown interpolation {}", interpolation))),
+        };
+        Ok(())
+    })
+}
+
+/// `extern bool camera_path_set_easing(CameraPath path, int easing);`
+///
+/// Easings are numbered 0 (linear), 1 (ease in), 2 (ease out) and 3 (ease in and out).
+///
+#[no_mangle]
+pub extern "C" fn camera_path_set_easing(path: &mut CameraPath, easing: libc::c_int) -> bool {
+    ffi::guard_result_status(|| {
+        path.easing = match easing {
+            0 => Easing::Linear,
+            1 => Easing::EaseIn,
+            2 => Easing::EaseOut,
+            3 => Easing::EaseInOut,
+            _ => return Err(Error::InvalidArgument(format!("Unknown easing {}", easing))),
+        };
+        Ok(())
+    })
+}
+
+/// `extern bool camera_path_finished(CameraPath path, float time);`
+///
+#[no_mangle]
+pub extern "C" fn camera_path_finished(path: &CameraPath, time: libc::c_float) -> bool {
+    ffi::guard_or(false, || {
+        path.finished(time as f32)
+    })
+}
+
+/// `extern Camera camera_path_apply(CameraPath path, Camera camera, float time);`
+///
+#[no_mangle]
+pub extern "C" fn camera_path_apply(path: &CameraPath, camera: *mut Camera, time: libc::c_float) -> Option<Box<Camera>> {
+    ffi::guard(|| {
+        let camera: Box<Camera> = unsafe { Box::from_raw(camera) };
+        let new_camera = Box::new(path.apply(&camera, time as f32));
+        drop(camera);
+        new_camera
+    })
+}
+
+/// `extern bool camera_path_apply_in_place(CameraPath path, Camera camera, float time);`
+///
+#[no_mangle]
+pub extern "C" fn camera_path_apply_in_place(path: &CameraPath, camera: &mut Camera, time: libc::c_float) -> bool {
+    ffi::guard_status(|| {
+        *camera = path.apply(camera, time as f32);
+    })
+}
+
+/// `extern void free_camera_path(CameraPath ptr);`
+///
+#[no_mangle]
+pub extern "C" fn free_camera_path(ptr: *mut CameraPath) {
+    ffi::guard_status(|| {
+        let box_ptr: Box<CameraPath> = unsafe { Box::from_raw(ptr) };
+        drop(box_ptr)
+    });
+}
@@ -0,0 +1,231 @@
+// Copyright (C) 2016 Chris Liebert
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use glium::glutin;
+use glium::DisplayBuild;
+use glium::backend::glutin_backend::GlutinFacade;
+use glium::program::Program;
+
+use camera::Camera;
+use common;
+use error::Error;
+use input::EventBuffer;
+use renderer::{Renderer, RendererError};
+use scene::Scene;
+use shader::Shader;
+
+/// Frame timing shared by the `App` runner and the FFI `MainLoop`
+///
+/// `tick` is called at the start of each frame. The time between frames is either used
+/// directly, or split into fixed steps by `fixed_steps`, and `pace` sleeps out the rest of
+/// the frame when the frame rate is limited.
+///
+pub struct FrameClock {
+    frame_start: Instant,
+    accumulator: f64,
+}
+
+impl FrameClock {
+    pub fn new() -> FrameClock {
+        FrameClock {
+            frame_start: Instant::now(),
+            accumulator: 0.0,
+        }
+    }
+
+    /// Start a new frame and return the seconds since the start of the last one
+    ///
+    pub fn tick(&mut self) -> f64 {
+        let now: Instant = Instant::now();
+        let dt: f64 = common::seconds(now.duration_since(self.frame_start));
+        self.frame_start = now;
+        dt
+    }
+
+    /// Add `dt` seconds and return how many updates of `step` seconds are due
+    ///
+    /// At most `max_steps` are returned, time which can't be caught up on is dropped so a slow
+    /// frame doesn't cause more and more updates. The fraction of a step left over, from 0 to 1,
+    /// is returned with the count so rendering can interpolate between the last two updates.
+    ///
+    pub fn fixed_steps(&mut self, dt: f64, step: f64, max_steps: u32) -> (u32, f64) {
+        self.accumulator += dt;
+        let mut steps: u32 = 0;
+        while self.accumulator >= step && steps < max_steps {
+            self.accumulator -= step;
+            steps += 1;
+        }
+        if steps == max_steps && self.accumulator >= step {
+            self.accumulator = self.accumulator % step;
+        }
+        (steps, self.accumulator / step)
+    }
+
+    /// Sleep until `1 / max_fps` seconds after the start of the frame, 0 doesn't limit the rate
+    ///
+    pub fn pace(&self, max_fps: u32) {
+        if max_fps == 0 {
+            return;
+        }
+        let frame_time: Duration = Duration::new(0, 1000000000 / max_fps);
+        let elapsed: Duration = self.frame_start.elapsed();
+        if elapsed < frame_time {
+            thread::sleep(frame_time - elapsed);
+        }
+    }
+}
+
+/// Window and timing settings for `run`
+///
+#[derive(Clone, Debug)]
+pub struct AppConfig {
+    pub title: String,
+    pub width: u32,
+    pub height: u32,
+    pub vsync: bool,
+    /// The rate of `App::update`, independent of the frame rate
+    pub updates_per_second: f64,
+    /// The most updates run before a frame is drawn, when falling behind
+    pub max_updates_per_frame: u32,
+    /// Frames are paced to this rate by sleeping, 0 runs as fast as possible
+    pub max_fps: u32,
+}
+
+impl AppConfig {
+    pub fn new(title: &str, width: u32, height: u32) -> AppConfig {
+        AppConfig {
+            title: String::from(title),
+            width: width,
+            height: height,
+            vsync: true,
+            updates_per_second: 60.0,
+            max_updates_per_frame: 5,
+            max_fps: 0,
+        }
+    }
+}
+
+/// The display, `Renderer` and `Camera` owned by the runner and lent to the `App`
+///
+pub struct Context {
+    pub display: GlutinFacade,
+    pub renderer: Renderer,
+    pub program: Program,
+    pub camera: Camera,
+    quit: bool,
+}
+
+impl Context {
+    /// Stop the runner after the current frame, `App::shutdown` is still called
+    ///
+    pub fn quit(&mut self) {
+        self.quit = true;
+    }
+
+    /// Size of the window in pixels
+    ///
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.display.get_framebuffer_dimensions()
+    }
+}
+
+/// An application driven by `run`
+///
+/// `update` is called at a fixed rate with the length of a step in seconds, so movement is the
+/// same at any frame rate. `render` is called once per frame with `alpha`, how far the frame is
+/// between the last update and the next one, for smoothing movement between updates.
+///
+pub trait App {
+    /// Create the shader program used by the default `render`
+    ///
+    fn load_shader(&mut self, display: &GlutinFacade) -> Result<Program, Error> {
+        Shader::default(display)
+    }
+
+    /// Called once the window and `Renderer` are created, before the window is shown
+    ///
+    fn init(&mut self, _context: &mut Context) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called once per frame with the window events, before the updates of the frame
+    ///
+    fn events(&mut self, _context: &mut Context, _events: &EventBuffer) {}
+
+    /// Called after the camera has been resized to the new window size, before `events`
+    ///
+    fn resized(&mut self, _context: &mut Context, _width: u32, _height: u32) {}
+
+    fn update(&mut self, context: &mut Context, dt: f64);
+
+    fn render(&mut self, context: &mut Context, _alpha: f64) -> Result<(), Error> {
+        context.renderer.render(&context.display, &context.program, &context.camera)
+    }
+
+    /// Called once when the runner stops, while the display is still open
+    ///
+    fn shutdown(&mut self, _context: &mut Context) {}
+}
+
+/// Open a window, render `scene` and drive `app` until the window is closed or `Context::quit`
+/// is called
+///
+pub fn run<A: App>(app: &mut A, config: &AppConfig, scene: Scene) -> Result<(), Error> {
+    let mut builder = glutin::WindowBuilder::new()
+        .with_depth_buffer(24)
+        .with_title(config.title.clone())
+        .with_visibility(false) // The window is shown once the app is initialized
+        .with_dimensions(config.width, config.height);
+    if config.vsync {
+        builder = builder.with_vsync();
+    }
+    let display: GlutinFacade = try!(builder.build_glium().map_err(RendererError::DisplayCreationError));
+    let renderer: Renderer = try!(Renderer::new(&display, scene));
+    let program: Program = try!(app.load_shader(&display));
+    display.release_shader_compiler();
+
+    let mut context: Context = Context {
+        camera: Camera::new(config.width as f32, config.height as f32),
+        display: display,
+        renderer: renderer,
+        program: program,
+        quit: false,
+    };
+    try!(app.init(&mut context));
+    if let Some(window) = context.display.get_window() {
+        window.show();
+    }
+
+    let step: f64 = 1.0 / config.updates_per_second;
+    let mut clock: FrameClock = FrameClock::new();
+    let mut result: Result<(), Error> = Ok(());
+    while !context.quit {
+        let dt: f64 = clock.tick();
+        let events: EventBuffer = EventBuffer::new(&context.display);
+        if events.closed() {
+            break;
+        }
+        if let Some((width, height)) = events.resized() {
+            context.camera = context.camera.resize(width as f32, height as f32);
+            app.resized(&mut context, width, height);
+        }
+        app.events(&mut context, &events);
+
+        let (steps, alpha) = clock.fixed_steps(dt, step, config.max_updates_per_frame);
+        for _ in 0..steps {
+            app.update(&mut context, step);
+        }
+        if context.quit {
+            break;
+        }
+        if let Err(e) = app.render(&mut context, alpha) {
+            result = Err(e);
+            break;
+        }
+        clock.pace(config.max_fps);
+    }
+    app.shutdown(&mut context);
+    result
+}
@@ -3,14 +3,27 @@
 extern crate libc;
 
 use nalgebra;
-use nalgebra::{Eye, Isometry3, Matrix4, PerspectiveMatrix3, ToHomogeneous, Vector3};
+use nalgebra::{Inverse, Isometry3, Matrix4, PerspectiveMatrix3, ToHomogeneous, Vector3, Vector4};
+
+use common::{matrix4_from_array, BoundingBox};
+use error::Error;
+use raycast::Ray;
+use renderer::Renderer;
 
 /// `Camera`
 ///
 /// Data for camera manipulation. Only modelview_matrix and projection_matrix are passed to the
 /// shader as uniform values.
 ///
-#[derive(Debug)]
+/// `orientation` is a unit quaternion stored as `[x, y, z, w]` that rotates the camera's local
+/// axes (right +x, up +y, forward -z) into world space, the `direction`, `right` and `up`
+/// vectors and the `pitch`, `yaw` and `roll` angles are kept in sync with it.
+///
+/// The projection is a perspective projection with a vertical field of view of `fov` radians,
+/// or an orthographic projection `ortho_zoom` world units tall above and below the center of
+/// the view when `orthographic` is set. Both use the `near` and `far` clip planes.
+///
+#[derive(Clone, Debug)]
 #[repr(C)]
 pub struct Camera {
     pub modelview_matrix: [[f32; 4]; 4],
@@ -22,9 +35,83 @@ pub struct Camera {
     pub pitch: f64,
     pub yaw: f64,
     pub roll: f64,
+    pub orientation: [f32; 4],
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+    pub aspect_ratio: f32,
+    pub orthographic: bool,
+    pub ortho_zoom: f32,
 }
 
+use std::f32::consts::FRAC_PI_4;
 use std::f64::consts::{FRAC_PI_2, PI};
+use ffi;
+
+/// Create a quaternion rotating `angle` radians around `axis`
+///
+fn quaternion_from_axis_angle(axis: &Vector3<f32>, angle: f32) -> [f32; 4] {
+    let axis: Vector3<f32> = nalgebra::normalize(axis);
+    let s: f32 = (angle * 0.5).sin();
+    [axis.x * s, axis.y * s, axis.z * s, (angle * 0.5).cos()]
+}
+
+/// The Hamilton product `a * b`, the rotation `b` followed by `a`
+///
+fn quaternion_multiply(a: &[f32; 4], b: &[f32; 4]) -> [f32; 4] {
+    [a[3] * b[0] + a[0] * b[3] + a[1] * b[2] - a[2] * b[1],
+     a[3] * b[1] - a[0] * b[2] + a[1] * b[3] + a[2] * b[0],
+     a[3] * b[2] + a[0] * b[1] - a[1] * b[0] + a[2] * b[3],
+     a[3] * b[3] - a[0] * b[0] - a[1] * b[1] - a[2] * b[2]]
+}
+
+fn quaternion_normalize(q: &[f32; 4]) -> [f32; 4] {
+    let length: f32 = (q[0] * q[0] + q[1] * q[1] + q[2] * q[2] + q[3] * q[3]).sqrt();
+    if length == 0.0 {
+        return [0.0, 0.0, 0.0, 1.0];
+    }
+    [q[0] / length, q[1] / length, q[2] / length, q[3] / length]
+}
+
+/// Rotate a vector by a unit quaternion
+///
+fn quaternion_rotate(q: &[f32; 4], v: &Vector3<f32>) -> Vector3<f32> {
+    let axis: Vector3<f32> = Vector3::new(q[0], q[1], q[2]);
+    let t: Vector3<f32> = nalgebra::cross(&axis, v) * 2.0;
+    *v + t * q[3] + nalgebra::cross(&axis, &t)
+}
+
+/// Create the quaternion for an orthonormal camera basis
+///
+fn quaternion_from_basis(right: &Vector3<f32>, up: &Vector3<f32>, direction: &Vector3<f32>) -> [f32; 4] {
+    // Rotation matrix with the columns right, up and back (-direction)
+    let (m00, m10, m20) = (right.x, right.y, right.z);
+    let (m01, m11, m21) = (up.x, up.y, up.z);
+    let (m02, m12, m22) = (-direction.x, -direction.y, -direction.z);
+    let trace: f32 = m00 + m11 + m22;
+    let q = if trace > 0.0 {
+        let s: f32 = (trace + 1.0).sqrt() * 2.0;
+        [(m21 - m12) / s, (m02 - m20) / s, (m10 - m01) / s, 0.25 * s]
+    } else if m00 > m11 && m00 > m22 {
+        let s: f32 = (1.0 + m00 - m11 - m22).sqrt() * 2.0;
+        [0.25 * s, (m01 + m10) / s, (m02 + m20) / s, (m21 - m12) / s]
+    } else if m11 > m22 {
+        let s: f32 = (1.0 + m11 - m00 - m22).sqrt() * 2.0;
+        [(m01 + m10) / s, 0.25 * s, (m12 + m21) / s, (m02 - m20) / s]
+    } else {
+        let s: f32 = (1.0 + m22 - m00 - m11).sqrt() * 2.0;
+        [(m02 + m20) / s, (m12 + m21) / s, 0.25 * s, (m10 - m01) / s]
+    };
+    quaternion_normalize(&q)
+}
+
+/// The right vector of an un-rolled camera with a horizontal angle (`pitch`)
+///
+fn level_right(horizontal: f64) -> Vector3<f32> {
+    Vector3::new((horizontal - FRAC_PI_2).sin() as f32,
+                 0.0f32,
+                 (horizontal - FRAC_PI_2).cos() as f32)
+}
 
 impl Camera {
     /// Create a new perspective `Camera` using screen_width and screen_height to calculate the
@@ -32,15 +119,9 @@ impl Camera {
     ///
     pub fn new(screen_width: f32, screen_height: f32) -> Camera {
         // Set up camera
-        let projection_matrix =
-            PerspectiveMatrix3::new(screen_width / screen_height, 45.0, 0.1, 1000.0);
-        let projection_matrix: Matrix4<f32> = projection_matrix.to_matrix();
-        let matrix: Matrix4<f32> = Eye::new_identity(4);
-        let modelview_matrix_array = *matrix.as_ref();
-
-        let camera: Camera = Camera {
-            modelview_matrix: modelview_matrix_array,
-            projection_matrix: *projection_matrix.as_ref(),
+        let mut camera: Camera = Camera {
+            modelview_matrix: [[0.0; 4]; 4],
+            projection_matrix: [[0.0; 4]; 4],
             position: Vector3::new(0.0f32, 1.0f32, 0.0f32),
             direction: Vector3::new(0.0f32, 0.0f32, 1.0f32),
             right: Vector3::new(1.0f32, 0.0f32, 0.0f32),
@@ -48,15 +129,137 @@ impl Camera {
             pitch: PI,
             yaw: 0.0,
             roll: 0.0,
+            orientation: [0.0, 0.0, 0.0, 1.0],
+            fov: FRAC_PI_4,
+            near: 0.1,
+            far: 1000.0,
+            aspect_ratio: screen_width / screen_height,
+            orthographic: false,
+            ortho_zoom: 1.0,
         };
+        camera.update_projection();
         camera.aim(0.0, 0.0)
     }
 
+    /// Recalculate `projection_matrix` from the projection settings.
+    ///
+    fn update_projection(&mut self) {
+        let projection_matrix: Matrix4<f32> = match self.orthographic {
+            true => {
+                let half_height: f32 = self.ortho_zoom;
+                let half_width: f32 = self.ortho_zoom * self.aspect_ratio;
+                let depth: f32 = self.far - self.near;
+                Matrix4::new(1.0 / half_width, 0.0, 0.0, 0.0,
+                             0.0, 1.0 / half_height, 0.0, 0.0,
+                             0.0, 0.0, -2.0 / depth, -(self.far + self.near) / depth,
+                             0.0, 0.0, 0.0, 1.0)
+            },
+            false => PerspectiveMatrix3::new(self.aspect_ratio, self.fov, self.near, self.far).to_matrix(),
+        };
+        self.projection_matrix = *projection_matrix.as_ref();
+    }
+
+    /// Create a copy of a `Camera` with a new vertical field of view in radians.
+    ///
+    /// Fails unless the field of view is between 0 and pi.
+    ///
+    pub fn set_fov(&self, fov: f32) -> Result<Camera, Error> {
+        if !(fov > 0.0 && fov < PI as f32) {
+            return Err(Error::InvalidArgument(format!("The field of view must be between 0 and pi, not {}", fov)));
+        }
+        let mut camera: Camera = self.clone();
+        camera.fov = fov;
+        camera.update_projection();
+        Ok(camera)
+    }
+
+    /// Create a copy of a `Camera` with new near and far clip plane distances.
+    ///
+    /// Fails unless `0 < near < far`.
+    ///
+    pub fn set_clip_planes(&self, near: f32, far: f32) -> Result<Camera, Error> {
+        if !(near > 0.0 && far > near && far.is_finite()) {
+            return Err(Error::InvalidArgument(format!("Invalid clip planes near {} far {}", near, far)));
+        }
+        let mut camera: Camera = self.clone();
+        camera.near = near;
+        camera.far = far;
+        camera.update_projection();
+        Ok(camera)
+    }
+
+    /// Create a copy of a `Camera` using an orthographic projection.
+    ///
+    /// `zoom` is half of the height of the view in world units, and must be positive.
+    ///
+    pub fn set_orthographic(&self, zoom: f32) -> Result<Camera, Error> {
+        if !(zoom > 0.0 && zoom.is_finite()) {
+            return Err(Error::InvalidArgument(format!("The orthographic zoom must be positive, not {}", zoom)));
+        }
+        let mut camera: Camera = self.clone();
+        camera.orthographic = true;
+        camera.ortho_zoom = zoom;
+        camera.update_projection();
+        Ok(camera)
+    }
+
+    /// Create a copy of a `Camera` using a perspective projection.
+    ///
+    pub fn set_perspective(&self) -> Camera {
+        let mut camera: Camera = self.clone();
+        camera.orthographic = false;
+        camera.update_projection();
+        camera
+    }
+
+    /// Create a copy of a `Camera` with the aspect ratio of a resized window.
+    ///
+    pub fn resize(&self, screen_width: f32, screen_height: f32) -> Camera {
+        let mut camera: Camera = self.clone();
+        if screen_width > 0.0 && screen_height > 0.0 {
+            camera.aspect_ratio = screen_width / screen_height;
+            camera.update_projection();
+        }
+        camera
+    }
+
+    /// Create a `Camera` with the same projection at a new position and orientation.
+    ///
+    /// The direction vectors, euler angles and modelview matrix are derived from the orientation.
+    ///
+    fn with_orientation(&self, position: Vector3<f32>, orientation: [f32; 4]) -> Camera {
+        let orientation: [f32; 4] = quaternion_normalize(&orientation);
+        let direction: Vector3<f32> = quaternion_rotate(&orientation, &Vector3::new(0.0f32, 0.0, -1.0));
+        let right: Vector3<f32> = quaternion_rotate(&orientation, &Vector3::new(1.0f32, 0.0, 0.0));
+        let up: Vector3<f32> = quaternion_rotate(&orientation, &Vector3::new(0.0f32, 1.0, 0.0));
+
+        let pitch: f64 = (direction.x as f64).atan2(direction.z as f64);
+        let yaw: f64 = (direction.y as f64).max(-1.0).min(1.0).asin();
+        // Roll is the angle between the right vector and the level right vector around the direction
+        let level: Vector3<f32> = level_right(pitch);
+        let roll: f64 = (nalgebra::dot(&nalgebra::cross(&level, &right), &direction) as f64)
+            .atan2(nalgebra::dot(&level, &right) as f64);
+
+        let iso3 = Isometry3::look_at_rh(&position.to_point(),
+                                         &(position + direction).to_point(),
+                                         &up);
+        let matrix: Matrix4<f32> = iso3.to_homogeneous();
+        let mut camera: Camera = self.clone();
+        camera.modelview_matrix = *matrix.as_ref();
+        camera.position = position;
+        camera.direction = direction;
+        camera.right = right;
+        camera.up = up;
+        camera.pitch = pitch;
+        camera.yaw = yaw;
+        camera.roll = roll;
+        camera.orientation = orientation;
+        camera
+    }
+
     /// Rotate a `Camera` in a relative direction perpendicular to the focal point.
     ///
     pub fn aim(&self, x: f64, y: f64) -> Camera {
-        
-        
         let factor: f64 = 0.01;
         let horizontal: f64 = self.pitch + x * factor;
         let vertical: f64 = self.yaw + y * factor;
@@ -66,28 +269,157 @@ impl Camera {
             vertical.sin() as f32,
             (vertical.cos() * horizontal.cos()) as f32);
 
-        let right: Vector3<f32> = Vector3::new(
-            (horizontal - FRAC_PI_2).sin() as f32,
-            0.0f32,
-            (horizontal - FRAC_PI_2).cos() as f32);
+        let right: Vector3<f32> = level_right(horizontal);
+        let up: Vector3<f32> = nalgebra::cross(&right, &direction);
+
+        // Apply the roll around the direction after the pitch and yaw
+        let roll: [f32; 4] = quaternion_from_axis_angle(&direction, self.roll as f32);
+        let orientation: [f32; 4] = quaternion_multiply(&roll, &quaternion_from_basis(&right, &up, &direction));
 
+        let mut camera: Camera = self.with_orientation(self.position, orientation);
+        // Keep the unwrapped angles so that aiming past the poles behaves as before
+        camera.pitch = horizontal;
+        camera.yaw = vertical;
+        camera.roll = self.roll;
+        camera
+    }
+
+    /// Rotate a `Camera` so that it faces `target` with it's up vector as close to `up` as possible.
+    ///
+    pub fn look_at(&self, target: &Vector3<f32>, up: &Vector3<f32>) -> Camera {
+        let to_target: Vector3<f32> = *target - self.position;
+        if nalgebra::norm(&to_target) == 0.0 {
+            return self.clone();
+        }
+        let direction: Vector3<f32> = nalgebra::normalize(&to_target);
+        let mut right: Vector3<f32> = nalgebra::cross(&direction, up);
+        if nalgebra::norm(&right) < 0.000001 {
+            // The direction is parallel to up, keep the current right vector
+            right = self.right;
+        }
+        let right: Vector3<f32> = nalgebra::normalize(&right);
         let up: Vector3<f32> = nalgebra::cross(&right, &direction);
-        let position: Vector3<f32> = Vector3::new(self.position[0], self.position[1], self.position[2]);
-        let iso3 = Isometry3::look_at_rh(&position.to_point(),
-                                         &(position + direction).to_point(),
-                                         &up);
-        let matrix: Matrix4<f32> = iso3.to_homogeneous();
-        
-        Camera {
-            modelview_matrix: *matrix.as_ref(),
-            projection_matrix: self.projection_matrix,
-            position: self.position,
-            direction: direction,
-            right: right,
-            up: up,
-            pitch: horizontal,
-            yaw: vertical,
-            roll: 0.0f64,
+        self.with_orientation(self.position, quaternion_from_basis(&right, &up, &direction))
+    }
+
+    /// Move a `Camera` to a new position without changing it's orientation.
+    ///
+    pub fn set_position(&self, position: &Vector3<f32>) -> Camera {
+        self.with_orientation(*position, self.orientation)
+    }
+
+    /// Create a copy of a `Camera` with a new orientation quaternion stored as `[x, y, z, w]`.
+    ///
+    pub fn set_orientation(&self, orientation: [f32; 4]) -> Camera {
+        self.with_orientation(self.position, orientation)
+    }
+
+    /// Point a `Camera` along a direction with it's up vector as close to `up` as possible.
+    ///
+    pub fn set_direction(&self, direction: &Vector3<f32>, up: &Vector3<f32>) -> Camera {
+        self.look_at(&(self.position + *direction), up)
+    }
+
+    /// Rotate a `Camera` by `angle` radians around a world space axis.
+    ///
+    pub fn rotate(&self, axis: &Vector3<f32>, angle: f32) -> Camera {
+        if nalgebra::norm(axis) == 0.0 {
+            return self.clone();
+        }
+        let rotation: [f32; 4] = quaternion_from_axis_angle(axis, angle);
+        self.with_orientation(self.position, quaternion_multiply(&rotation, &self.orientation))
+    }
+
+    /// Roll a `Camera` by `angle` radians around it's direction.
+    ///
+    pub fn roll_by(&self, angle: f32) -> Camera {
+        self.rotate(&self.direction, angle)
+    }
+
+    /// The view (modelview) matrix
+    ///
+    pub fn view_matrix(&self) -> Matrix4<f32> {
+        matrix4_from_array(&self.modelview_matrix)
+    }
+
+    /// The projection matrix
+    ///
+    pub fn projection(&self) -> Matrix4<f32> {
+        matrix4_from_array(&self.projection_matrix)
+    }
+
+    /// The projection matrix multiplied by the view matrix
+    ///
+    pub fn view_projection_matrix(&self) -> Matrix4<f32> {
+        self.projection() * self.view_matrix()
+    }
+
+    /// Create a world space `Ray` passing through the screen coordinates (x, y).
+    ///
+    /// Screen coordinates are in pixels relative to the top-left corner of the window, as
+    /// reported by `Mouse`.
+    ///
+    pub fn ray_from_screen(&self, x: i32, y: i32, screen_width: f32, screen_height: f32) -> Ray {
+        let ndc_x: f32 = 2.0 * x as f32 / screen_width - 1.0;
+        let ndc_y: f32 = 1.0 - 2.0 * y as f32 / screen_height;
+        let inverse: Matrix4<f32> = match self.view_projection_matrix().inverse() {
+            Some(m) => m,
+            None => {
+                // Degenerate projection, fall back to the view direction
+                let p = self.position;
+                let d = self.direction;
+                return Ray::new([p.x, p.y, p.z], [d.x, d.y, d.z]);
+            },
+        };
+        let near: Vector4<f32> = inverse * Vector4::new(ndc_x, ndc_y, -1.0f32, 1.0f32);
+        let far: Vector4<f32> = inverse * Vector4::new(ndc_x, ndc_y, 1.0f32, 1.0f32);
+        let near: Vector3<f32> = Vector3::new(near.x / near.w, near.y / near.w, near.z / near.w);
+        let far: Vector3<f32> = Vector3::new(far.x / far.w, far.y / far.w, far.z / far.w);
+        let direction: Vector3<f32> = far - near;
+        Ray::new([near.x, near.y, near.z], [direction.x, direction.y, direction.z])
+    }
+
+    /// The screen coordinates of a world space point, the inverse of `ray_from_screen`
+    ///
+    /// Returns `None` when the point is behind the near clip plane.
+    ///
+    pub fn screen_from_world(&self, point: [f32; 3], screen_width: f32, screen_height: f32) -> Option<[f32; 2]> {
+        let clip: Vector4<f32> = self.view_projection_matrix() * Vector4::new(point[0], point[1], point[2], 1.0f32);
+        if clip.w <= 0.0 || clip.z < -clip.w {
+            return None;
+        }
+        let ndc_x: f32 = clip.x / clip.w;
+        let ndc_y: f32 = clip.y / clip.w;
+        Some([(ndc_x + 1.0) * 0.5 * screen_width, (1.0 - ndc_y) * 0.5 * screen_height])
+    }
+
+    /// The distance from which a sphere of `radius` fits inside of the narrowest field of view.
+    ///
+    pub fn framing_distance(&self, radius: f32) -> f32 {
+        if self.orthographic {
+            // The view size doesn't depend on distance, only stay in front of the near plane
+            return radius.max(0.0001) + self.near;
+        }
+        // The projection matrix stores the cotangent of each half field of view
+        let tan_half_x: f32 = 1.0 / self.projection_matrix[0][0].abs();
+        let tan_half_y: f32 = 1.0 / self.projection_matrix[1][1].abs();
+        let half_angle: f32 = tan_half_x.min(tan_half_y).atan();
+        radius.max(0.0001) / half_angle.sin()
+    }
+
+    /// Move a `Camera` along it's current direction so that a `BoundingBox` fills the view.
+    ///
+    pub fn frame_bounds(&self, bounds: &BoundingBox) -> Camera {
+        if bounds.is_empty() {
+            return self.clone();
+        }
+        let center = bounds.center();
+        let distance: f32 = self.framing_distance(bounds.radius());
+        let camera: Camera = self.set_position(&(Vector3::new(center[0], center[1], center[2]) - self.direction * distance));
+        match self.orthographic {
+            // A single point has no size to zoom to
+            true => camera.set_orthographic(bounds.radius() / self.aspect_ratio.min(1.0)).unwrap_or(camera),
+            false => camera,
         }
     }
 
@@ -100,24 +432,11 @@ impl Camera {
     /// Move a `Camera` forward by a specified amount.
     ///
     pub fn move_forward(&self, amount: f32) -> Camera {
-        
-        let scaled_direction: Vector3<f32> = amount * self.direction;
-        let position: Vector3<f32> = self.position + scaled_direction;
-        let iso3 = Isometry3::look_at_rh(&position.to_point(),
-                                         &(position + self.direction).to_point(),
-                                         &self.up);
-        let matrix: Matrix4<f32> = iso3.to_homogeneous();
-        Camera {
-            modelview_matrix: *matrix.as_ref(),
-            projection_matrix: self.projection_matrix,
-            position: position,
-            direction: self.direction,
-            right: self.right,
-            up: self.up,
-            pitch: self.pitch,
-            yaw: self.yaw,
-            roll: self.roll,
-        }
+        let mut camera: Camera = self.set_position(&(self.position + amount * self.direction));
+        camera.pitch = self.pitch;
+        camera.yaw = self.yaw;
+        camera.roll = self.roll;
+        camera
     }
 
     /// Move a `Camera` left by a specified amount.
@@ -129,87 +448,525 @@ impl Camera {
     /// Move a `Camera` right by a specified amount.
     ///
     pub fn move_right(&self, amount: f32) -> Camera {
-        let scaled_right: Vector3<f32> = amount * self.right;
-        let position: Vector3<f32> = self.position + scaled_right;
-        let iso3 = Isometry3::look_at_rh(&position.to_point(),
-                                         &(position + self.direction).to_point(),
-                                         &self.up);
-        let matrix: Matrix4<f32> = iso3.to_homogeneous();
-        Camera {
-            modelview_matrix: *matrix.as_ref(),
-            projection_matrix: self.projection_matrix,
-            position: position,
-            direction: self.direction,
-            right: self.right,
-            up: self.up,
-            pitch: self.pitch,
-            yaw: self.yaw,
-            roll: self.roll,
-        }
+        let mut camera: Camera = self.set_position(&(self.position + amount * self.right));
+        camera.pitch = self.pitch;
+        camera.yaw = self.yaw;
+        camera.roll = self.roll;
+        camera
     }
 }
 
-/// `extern void camera_aim(Camera camera, double x, double y);`
+
+/// `extern Camera camera_aim(Camera camera, double x, double y);`
 ///
 #[no_mangle]
-pub extern "C" fn camera_aim(camera: *mut Camera, x: libc::c_double, y: libc::c_double) -> Box<Camera> {
-    let camera: Box<Camera> = unsafe { Box::from_raw(camera) };
-    let new_camera = Box::new(camera.aim(x as f64, y as f64));
-    drop(camera);
-    new_camera
+pub extern "C" fn camera_aim(camera: *mut Camera, x: libc::c_double, y: libc::c_double) -> Option<Box<Camera>> {
+    ffi::guard(|| {
+        let camera: Box<Camera> = unsafe { Box::from_raw(camera) };
+        let new_camera = Box::new(camera.aim(x as f64, y as f64));
+        drop(camera);
+        new_camera
+    })
 }
 
-/// `extern void camera_move_forward(Camera camera, float amount);`
+/// `extern Camera camera_move_forward(Camera camera, float amount);`
 ///
 #[no_mangle]
-pub extern "C" fn camera_move_forward(camera: *mut Camera, amount: libc::c_float) -> Box<Camera> {
-    let camera: Box<Camera> = unsafe { Box::from_raw(camera) };
-    let new_camera = Box::new(camera.move_forward(amount as f32));
-    drop(camera);
-    new_camera
+pub extern "C" fn camera_move_forward(camera: *mut Camera, amount: libc::c_float) -> Option<Box<Camera>> {
+    ffi::guard(|| {
+        let camera: Box<Camera> = unsafe { Box::from_raw(camera) };
+        let new_camera = Box::new(camera.move_forward(amount as f32));
+        drop(camera);
+        new_camera
+    })
 }
 
-/// `extern void camera_move_backward(Camera camera, float amount);`
+/// `extern Camera camera_move_backward(Camera camera, float amount);`
 ///
 #[no_mangle]
-pub extern "C" fn camera_move_backward(camera: *mut Camera, amount: libc::c_float) -> Box<Camera> {
-    let camera: Box<Camera> = unsafe { Box::from_raw(camera) };
-    let new_camera = Box::new(camera.move_backward(amount as f32));
-    drop(camera);
-    new_camera
+pub extern "C" fn camera_move_backward(camera: *mut Camera, amount: libc::c_float) -> Option<Box<Camera>> {
+    ffi::guard(|| {
+        let camera: Box<Camera> = unsafe { Box::from_raw(camera) };
+        let new_camera = Box::new(camera.move_backward(amount as f32));
+        drop(camera);
+        new_camera
+    })
 }
 
-/// `extern void camera_move_left(Camera camera, float amount);`
+/// `extern Camera camera_move_left(Camera camera, float amount);`
 ///
 #[no_mangle]
-pub extern "C" fn camera_move_left(camera: *mut Camera, amount: libc::c_float) -> Box<Camera> {
-    let camera: Box<Camera> = unsafe { Box::from_raw(camera) };    
-    let new_camera = Box::new(camera.move_left(amount as f32));
-    drop(camera);
-    new_camera
+pub extern "C" fn camera_move_left(camera: *mut Camera, amount: libc::c_float) -> Option<Box<Camera>> {
+    ffi::guard(|| {
+        let camera: Box<Camera> = unsafe { Box::from_raw(camera) };    
+        let new_camera = Box::new(camera.move_left(amount as f32));
+        drop(camera);
+        new_camera
+    })
 }
 
-/// `extern void camera_move_right(Camera camera, float amount);`
+/// `extern Camera camera_move_right(Camera camera, float amount);`
 ///
 #[no_mangle]
-pub extern "C" fn camera_move_right(camera: *mut Camera, amount: libc::c_float) -> Box<Camera> {
-    let camera: Box<Camera> = unsafe { Box::from_raw(camera) };   
-    let new_camera = Box::new(camera.move_right(amount as f32));
-    drop(camera);
-    new_camera
+pub extern "C" fn camera_move_right(camera: *mut Camera, amount: libc::c_float) -> Option<Box<Camera>> {
+    ffi::guard(|| {
+        let camera: Box<Camera> = unsafe { Box::from_raw(camera) };   
+        let new_camera = Box::new(camera.move_right(amount as f32));
+        drop(camera);
+        new_camera
+    })
+}
+
+/// `extern Camera camera_look_at(Camera camera, float target_x, float target_y, float target_z, float up_x, float up_y, float up_z);`
+///
+#[no_mangle]
+pub extern "C" fn camera_look_at(camera: *mut Camera,
+                                 target_x: libc::c_float,
+                                 target_y: libc::c_float,
+                                 target_z: libc::c_float,
+                                 up_x: libc::c_float,
+                                 up_y: libc::c_float,
+                                 up_z: libc::c_float)
+                                 -> Option<Box<Camera>> {
+    ffi::guard(|| {
+        let camera: Box<Camera> = unsafe { Box::from_raw(camera) };
+        let target: Vector3<f32> = Vector3::new(target_x as f32, target_y as f32, target_z as f32);
+        let up: Vector3<f32> = Vector3::new(up_x as f32, up_y as f32, up_z as f32);
+        let new_camera = Box::new(camera.look_at(&target, &up));
+        drop(camera);
+        new_camera
+    })
+}
+
+/// `extern Camera camera_set_position(Camera camera, float x, float y, float z);`
+///
+#[no_mangle]
+pub extern "C" fn camera_set_position(camera: *mut Camera,
+                                      x: libc::c_float,
+                                      y: libc::c_float,
+                                      z: libc::c_float)
+                                      -> Option<Box<Camera>> {
+    ffi::guard(|| {
+        let camera: Box<Camera> = unsafe { Box::from_raw(camera) };
+        let new_camera = Box::new(camera.set_position(&Vector3::new(x as f32, y as f32, z as f32)));
+        drop(camera);
+        new_camera
+    })
+}
+
+/// `extern Camera camera_rotate(Camera camera, float axis_x, float axis_y, float axis_z, float angle);`
+///
+#[no_mangle]
+pub extern "C" fn camera_rotate(camera: *mut Camera,
+                                axis_x: libc::c_float,
+                                axis_y: libc::c_float,
+                                axis_z: libc::c_float,
+                                angle: libc::c_float)
+                                -> Option<Box<Camera>> {
+    ffi::guard(|| {
+        let camera: Box<Camera> = unsafe { Box::from_raw(camera) };
+        let axis: Vector3<f32> = Vector3::new(axis_x as f32, axis_y as f32, axis_z as f32);
+        let new_camera = Box::new(camera.rotate(&axis, angle as f32));
+        drop(camera);
+        new_camera
+    })
+}
+
+/// `extern Camera camera_roll(Camera camera, float angle);`
+///
+#[no_mangle]
+pub extern "C" fn camera_roll(camera: *mut Camera, angle: libc::c_float) -> Option<Box<Camera>> {
+    ffi::guard(|| {
+        let camera: Box<Camera> = unsafe { Box::from_raw(camera) };
+        let new_camera = Box::new(camera.roll_by(angle as f32));
+        drop(camera);
+        new_camera
+    })
+}
+
+/// `extern Camera camera_set_fov(Camera camera, float fov);`
+///
+/// An invalid value returns the camera unchanged and sets `quick3d_last_error`.
+///
+#[no_mangle]
+pub extern "C" fn camera_set_fov(camera: *mut Camera, fov: libc::c_float) -> Option<Box<Camera>> {
+    ffi::guard(|| {
+        let camera: Box<Camera> = unsafe { Box::from_raw(camera) };
+        match camera.set_fov(fov as f32) {
+            Ok(new_camera) => Box::new(new_camera),
+            Err(e) => {
+                ffi::set_last_error(&e.to_string());
+                camera
+            },
+        }
+    })
+}
+
+/// `extern Camera camera_set_clip_planes(Camera camera, float near, float far);`
+///
+/// An invalid value returns the camera unchanged and sets `quick3d_last_error`.
+///
+#[no_mangle]
+pub extern "C" fn camera_set_clip_planes(camera: *mut Camera, near: libc::c_float, far: libc::c_float) -> Option<Box<Camera>> {
+    ffi::guard(|| {
+        let camera: Box<Camera> = unsafe { Box::from_raw(camera) };
+        match camera.set_clip_planes(near as f32, far as f32) {
+            Ok(new_camera) => Box::new(new_camera),
+            Err(e) => {
+                ffi::set_last_error(&e.to_string());
+                camera
+            },
+        }
+    })
+}
+
+/// `extern Camera camera_set_orthographic(Camera camera, float zoom);`
+///
+/// An invalid value returns the camera unchanged and sets `quick3d_last_error`.
+///
+#[no_mangle]
+pub extern "C" fn camera_set_orthographic(camera: *mut Camera, zoom: libc::c_float) -> Option<Box<Camera>> {
+    ffi::guard(|| {
+        let camera: Box<Camera> = unsafe { Box::from_raw(camera) };
+        match camera.set_orthographic(zoom as f32) {
+            Ok(new_camera) => Box::new(new_camera),
+            Err(e) => {
+                ffi::set_last_error(&e.to_string());
+                camera
+            },
+        }
+    })
+}
+
+/// `extern Camera camera_set_perspective(Camera camera);`
+///
+#[no_mangle]
+pub extern "C" fn camera_set_perspective(camera: *mut Camera) -> Option<Box<Camera>> {
+    ffi::guard(|| {
+        let camera: Box<Camera> = unsafe { Box::from_raw(camera) };
+        let new_camera = Box::new(camera.set_perspective());
+        drop(camera);
+        new_camera
+    })
+}
+
+/// `extern Camera camera_resize(Camera camera, float screen_width, float screen_height);`
+///
+#[no_mangle]
+pub extern "C" fn camera_resize(camera: *mut Camera, screen_width: libc::c_float, screen_height: libc::c_float) -> Option<Box<Camera>> {
+    ffi::guard(|| {
+        let camera: Box<Camera> = unsafe { Box::from_raw(camera) };
+        let new_camera = Box::new(camera.resize(screen_width as f32, screen_height as f32));
+        drop(camera);
+        new_camera
+    })
+}
+
+/// `extern Camera camera_frame_scene(Camera camera, Renderer renderer);`
+///
+#[no_mangle]
+pub extern "C" fn camera_frame_scene(camera: *mut Camera, renderer: &Renderer) -> Option<Box<Camera>> {
+    ffi::guard(|| {
+        let camera: Box<Camera> = unsafe { Box::from_raw(camera) };
+        let new_camera = Box::new(camera.frame_bounds(&renderer.scene.world_bounding_box()));
+        drop(camera);
+        new_camera
+    })
 }
 
 /// `extern Camera create_camera(float screen_width, float screen_height);`
 ///
 #[no_mangle]
-pub extern "C" fn create_camera(screen_width: f32, screen_height: f32) -> Box<Camera> {
-    Box::new(Camera::new(screen_width, screen_height))
+pub extern "C" fn create_camera(screen_width: f32, screen_height: f32) -> Option<Box<Camera>> {
+    ffi::guard(|| {
+        Box::new(Camera::new(screen_width, screen_height))
+    })
 }
 
-/// `extern void free_camera(Camera memory);`
+/// `extern void free_camera(Camera ptr);`
 ///
 #[no_mangle]
 pub extern "C" fn free_camera(ptr: *mut Camera) {
-    let box_ptr: Box<Camera> = unsafe { Box::from_raw(ptr) };
-    drop(box_ptr)
-}
\ No newline at end of file
+    ffi::guard_status(|| {
+        let box_ptr: Box<Camera> = unsafe { Box::from_raw(ptr) };
+        drop(box_ptr)
+    });
+}
+
+/// Write the components of a vector to a float array
+///
+fn write_floats(values: &[f32], out: *mut libc::c_float) {
+    if out.is_null() {
+        return;
+    }
+    for (i, value) in values.iter().enumerate() {
+        unsafe { *out.offset(i as isize) = *value as libc::c_float };
+    }
+}
+
+/// Write a column-major matrix to an array of 16 floats
+///
+fn write_matrix(matrix: &[[f32; 4]; 4], out: *mut libc::c_float) {
+    for column in 0..4 {
+        if !out.is_null() {
+            write_floats(&matrix[column], unsafe { out.offset(column as isize * 4) });
+        }
+    }
+}
+
+/// The following functions modify a `Camera` in place so that bindings can keep using the
+/// same handle instead of replacing it with the `Camera` returned by the functions above.
+///
+
+/// `extern bool camera_aim_in_place(Camera camera, double x, double y);`
+///
+#[no_mangle]
+pub extern "C" fn camera_aim_in_place(camera: &mut Camera, x: libc::c_double, y: libc::c_double) -> bool {
+    ffi::guard_status(|| {
+        *camera = camera.aim(x as f64, y as f64);
+    })
+}
+
+/// `extern bool camera_move_forward_in_place(Camera camera, float amount);`
+///
+#[no_mangle]
+pub extern "C" fn camera_move_forward_in_place(camera: &mut Camera, amount: libc::c_float) -> bool {
+    ffi::guard_status(|| {
+        *camera = camera.move_forward(amount as f32);
+    })
+}
+
+/// `extern bool camera_move_backward_in_place(Camera camera, float amount);`
+///
+#[no_mangle]
+pub extern "C" fn camera_move_backward_in_place(camera: &mut Camera, amount: libc::c_float) -> bool {
+    ffi::guard_status(|| {
+        *camera = camera.move_backward(amount as f32);
+    })
+}
+
+/// `extern bool camera_move_left_in_place(Camera camera, float amount);`
+///
+#[no_mangle]
+pub extern "C" fn camera_move_left_in_place(camera: &mut Camera, amount: libc::c_float) -> bool {
+    ffi::guard_status(|| {
+        *camera = camera.move_left(amount as f32);
+    })
+}
+
+/// `extern bool camera_move_right_in_place(Camera camera, float amount);`
+///
+#[no_mangle]
+pub extern "C" fn camera_move_right_in_place(camera: &mut Camera, amount: libc::c_float) -> bool {
+    ffi::guard_status(|| {
+        *camera = camera.move_right(amount as f32);
+    })
+}
+
+/// `extern bool camera_look_at_in_place(Camera camera, float target_x, float target_y, float target_z, float up_x, float up_y, float up_z);`
+///
+#[no_mangle]
+pub extern "C" fn camera_look_at_in_place(camera: &mut Camera,
+                                          target_x: libc::c_float,
+                                          target_y: libc::c_float,
+                                          target_z: libc::c_float,
+                                          up_x: libc::c_float,
+                                          up_y: libc::c_float,
+                                          up_z: libc::c_float) -> bool {
+    ffi::guard_status(|| {
+        let target: Vector3<f32> = Vector3::new(target_x as f32, target_y as f32, target_z as f32);
+        let up: Vector3<f32> = Vector3::new(up_x as f32, up_y as f32, up_z as f32);
+        *camera = camera.look_at(&target, &up);
+    })
+}
+
+/// `extern bool camera_rotate_in_place(Camera camera, float axis_x, float axis_y, float axis_z, float angle);`
+///
+#[no_mangle]
+pub extern "C" fn camera_rotate_in_place(camera: &mut Camera,
+                                         axis_x: libc::c_float,
+                                         axis_y: libc::c_float,
+                                         axis_z: libc::c_float,
+                                         angle: libc::c_float) -> bool {
+    ffi::guard_status(|| {
+        let axis: Vector3<f32> = Vector3::new(axis_x as f32, axis_y as f32, axis_z as f32);
+        *camera = camera.rotate(&axis, angle as f32);
+    })
+}
+
+/// `extern bool camera_roll_in_place(Camera camera, float angle);`
+///
+#[no_mangle]
+pub extern "C" fn camera_roll_in_place(camera: &mut Camera, angle: libc::c_float) -> bool {
+    ffi::guard_status(|| {
+        *camera = camera.roll_by(angle as f32);
+    })
+}
+
+/// `extern bool camera_frame_scene_in_place(Camera camera, Renderer renderer);`
+///
+#[no_mangle]
+pub extern "C" fn camera_frame_scene_in_place(camera: &mut Camera, renderer: &Renderer) -> bool {
+    ffi::guard_status(|| {
+        *camera = camera.frame_bounds(&renderer.scene.world_bounding_box());
+    })
+}
+
+/// `extern bool camera_resize_in_place(Camera camera, float screen_width, float screen_height);`
+///
+#[no_mangle]
+pub extern "C" fn camera_resize_in_place(camera: &mut Camera, screen_width: libc::c_float, screen_height: libc::c_float) -> bool {
+    ffi::guard_status(|| {
+        *camera = camera.resize(screen_width as f32, screen_height as f32);
+    })
+}
+
+/// `extern bool camera_set_fov_in_place(Camera camera, float fov);`
+///
+#[no_mangle]
+pub extern "C" fn camera_set_fov_in_place(camera: &mut Camera, fov: libc::c_float) -> bool {
+    ffi::guard_result_status(|| {
+        *camera = try!(camera.set_fov(fov as f32));
+        Ok(())
+    })
+}
+
+/// `extern bool camera_set_clip_planes_in_place(Camera camera, float near, float far);`
+///
+#[no_mangle]
+pub extern "C" fn camera_set_clip_planes_in_place(camera: &mut Camera, near: libc::c_float, far: libc::c_float) -> bool {
+    ffi::guard_result_status(|| {
+        *camera = try!(camera.set_clip_planes(near as f32, far as f32));
+        Ok(())
+    })
+}
+
+/// `extern bool camera_set_orthographic_in_place(Camera camera, float zoom);`
+///
+#[no_mangle]
+pub extern "C" fn camera_set_orthographic_in_place(camera: &mut Camera, zoom: libc::c_float) -> bool {
+    ffi::guard_result_status(|| {
+        *camera = try!(camera.set_orthographic(zoom as f32));
+        Ok(())
+    })
+}
+
+/// `extern bool camera_set_perspective_in_place(Camera camera);`
+///
+#[no_mangle]
+pub extern "C" fn camera_set_perspective_in_place(camera: &mut Camera) -> bool {
+    ffi::guard_status(|| {
+        *camera = camera.set_perspective();
+    })
+}
+
+/// `extern bool camera_set_position_in_place(Camera camera, float x, float y, float z);`
+///
+#[no_mangle]
+pub extern "C" fn camera_set_position_in_place(camera: &mut Camera, x: libc::c_float, y: libc::c_float, z: libc::c_float) -> bool {
+    ffi::guard_status(|| {
+        *camera = camera.set_position(&Vector3::new(x as f32, y as f32, z as f32));
+    })
+}
+
+/// `extern bool camera_set_direction_in_place(Camera camera, float x, float y, float z);`
+///
+/// The up vector is kept as close to the positive y axis as possible.
+///
+#[no_mangle]
+pub extern "C" fn camera_set_direction_in_place(camera: &mut Camera, x: libc::c_float, y: libc::c_float, z: libc::c_float) -> bool {
+    ffi::guard_status(|| {
+        let direction: Vector3<f32> = Vector3::new(x as f32, y as f32, z as f32);
+        *camera = camera.set_direction(&direction, &Vector3::new(0.0f32, 1.0, 0.0));
+    })
+}
+
+/// `extern bool camera_set_orientation_in_place(Camera camera, float x, float y, float z, float w);`
+///
+#[no_mangle]
+pub extern "C" fn camera_set_orientation_in_place(camera: &mut Camera,
+                                                  x: libc::c_float,
+                                                  y: libc::c_float,
+                                                  z: libc::c_float,
+                                                  w: libc::c_float) -> bool {
+    ffi::guard_status(|| {
+        *camera = camera.set_orientation([x as f32, y as f32, z as f32, w as f32]);
+    })
+}
+
+/// `extern bool camera_get_position(Camera camera, float out[3]);`
+///
+/// Writes 3 floats: x, y, z.
+///
+#[no_mangle]
+pub extern "C" fn camera_get_position(camera: &Camera, out: *mut [libc::c_float; 3]) -> bool {
+    ffi::guard_status(|| {
+        write_floats(&[camera.position.x, camera.position.y, camera.position.z], out as *mut libc::c_float);
+    })
+}
+
+/// `extern bool camera_get_direction(Camera camera, float out[3]);`
+///
+/// Writes 3 floats: x, y, z.
+///
+#[no_mangle]
+pub extern "C" fn camera_get_direction(camera: &Camera, out: *mut [libc::c_float; 3]) -> bool {
+    ffi::guard_status(|| {
+        write_floats(&[camera.direction.x, camera.direction.y, camera.direction.z], out as *mut libc::c_float);
+    })
+}
+
+/// `extern bool camera_get_right(Camera camera, float out[3]);`
+///
+/// Writes 3 floats: x, y, z.
+///
+#[no_mangle]
+pub extern "C" fn camera_get_right(camera: &Camera, out: *mut [libc::c_float; 3]) -> bool {
+    ffi::guard_status(|| {
+        write_floats(&[camera.right.x, camera.right.y, camera.right.z], out as *mut libc::c_float);
+    })
+}
+
+/// `extern bool camera_get_up(Camera camera, float out[3]);`
+///
+/// Writes 3 floats: x, y, z.
+///
+#[no_mangle]
+pub extern "C" fn camera_get_up(camera: &Camera, out: *mut [libc::c_float; 3]) -> bool {
+    ffi::guard_status(|| {
+        write_floats(&[camera.up.x, camera.up.y, camera.up.z], out as *mut libc::c_float);
+    })
+}
+
+/// `extern bool camera_get_orientation(Camera camera, float out[4]);`
+///
+/// Writes 4 floats, the quaternion as x, y, z, w.
+///
+#[no_mangle]
+pub extern "C" fn camera_get_orientation(camera: &Camera, out: *mut [libc::c_float; 4]) -> bool {
+    ffi::guard_status(|| {
+        write_floats(&camera.orientation, out as *mut libc::c_float);
+    })
+}
+
+/// `extern bool camera_get_modelview_matrix(Camera camera, float out[16]);`
+///
+/// Writes 16 floats in column-major order.
+///
+#[no_mangle]
+pub extern "C" fn camera_get_modelview_matrix(camera: &Camera, out: *mut [libc::c_float; 16]) -> bool {
+    ffi::guard_status(|| {
+        write_matrix(&camera.modelview_matrix, out as *mut libc::c_float);
+    })
+}
+
+/// `extern bool camera_get_projection_matrix(Camera camera, float out[16]);`
+///
+/// Writes 16 floats in column-major order.
+///
+#[no_mangle]
+pub extern "C" fn camera_get_projection_matrix(camera: &Camera, out: *mut [libc::c_float; 16]) -> bool {
+    ffi::guard_status(|| {
+        write_matrix(&camera.projection_matrix, out as *mut libc::c_float);
+    })
+}
@@ -1,13 +1,42 @@
 // Copyright(C) 2016 Chris Liebert
 
 use std::cell::RefCell;
-use nalgebra::Matrix4;
+use std::f32;
+use std::time::Duration;
+use nalgebra::{Eye, Matrix4, Vector4};
+
+/// Convert a column-major array, as passed to glium uniforms, into a `Matrix4`
+///
+pub fn matrix4_from_array(m: &[[f32; 4]; 4]) -> Matrix4<f32> {
+    Matrix4::new(m[0][0], m[1][0], m[2][0], m[3][0],
+                 m[0][1], m[1][1], m[2][1], m[3][1],
+                 m[0][2], m[1][2], m[2][2], m[3][2],
+                 m[0][3], m[1][3], m[2][3], m[3][3])
+}
+
+/// Convert a `Duration` to seconds
+///
+pub fn seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + duration.subsec_nanos() as f64 / 1000000000.0
+}
+
+/// Convert a `Duration` to milliseconds
+///
+pub fn milliseconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1000.0 + duration.subsec_nanos() as f64 / 1000000.0
+}
+
+/// Convert a `Duration` to microseconds
+///
+pub fn microseconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1000000.0 + duration.subsec_nanos() as f64 / 1000.0
+}
 
 /// A representation of a binary image and it's name
 ///
 /// An `ImageBlob` represents a row in the `texture` table of an SQL database.
 ///
-#[derive(PartialEq, RustcEncodable, RustcDecodable)]
+#[derive(Clone, PartialEq, RustcEncodable, RustcDecodable)]
 pub struct ImageBlob {
     pub name: String,
     pub image: Vec<u8>,
@@ -29,6 +58,183 @@ pub struct Mesh {
     pub matrix: RefCell<Matrix4<f32>>,
 }
 
+/// An axis-aligned bounding box
+///
+/// A `BoundingBox` is empty when any component of `min` is greater than `max`.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl BoundingBox {
+    /// Create an empty `BoundingBox` that can be grown with `extend`
+    ///
+    pub fn empty() -> BoundingBox {
+        BoundingBox {
+            min: [f32::INFINITY; 3],
+            max: [f32::NEG_INFINITY; 3],
+        }
+    }
+
+    /// Create the smallest `BoundingBox` containing every `Vertex8f32`
+    ///
+    pub fn from_vertices(vertices: &[Vertex8f32]) -> BoundingBox {
+        let mut bounds = BoundingBox::empty();
+        for vertex in vertices {
+            bounds.extend(&vertex.position);
+        }
+        bounds
+    }
+
+    pub fn is_empty(&self) -> bool {
+        (0..3).any(|i| self.min[i] > self.max[i])
+    }
+
+    /// Grow the `BoundingBox` to contain a point
+    ///
+    pub fn extend(&mut self, point: &[f32; 3]) {
+        for i in 0..3 {
+            self.min[i] = self.min[i].min(point[i]);
+            self.max[i] = self.max[i].max(point[i]);
+        }
+    }
+
+    /// The smallest `BoundingBox` containing both `self` and `other`
+    ///
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        if self.is_empty() {
+            return *other;
+        }
+        if other.is_empty() {
+            return *self;
+        }
+        let mut bounds = *self;
+        bounds.extend(&other.min);
+        bounds.extend(&other.max);
+        bounds
+    }
+
+    pub fn center(&self) -> [f32; 3] {
+        [(self.min[0] + self.max[0]) * 0.5,
+         (self.min[1] + self.max[1]) * 0.5,
+         (self.min[2] + self.max[2]) * 0.5]
+    }
+
+    pub fn size(&self) -> [f32; 3] {
+        [self.max[0] - self.min[0], self.max[1] - self.min[1], self.max[2] - self.min[2]]
+    }
+
+    /// The radius of the sphere centered on the `BoundingBox` that touches it's corners
+    ///
+    pub fn radius(&self) -> f32 {
+        let size = self.size();
+        0.5 * (size[0] * size[0] + size[1] * size[1] + size[2] * size[2]).sqrt()
+    }
+
+    /// The 8 corners of the `BoundingBox`
+    ///
+    pub fn corners(&self) -> [[f32; 3]; 8] {
+        let mut corners = [[0.0f32; 3]; 8];
+        for i in 0..8 {
+            corners[i] = [if i & 1 == 0 { self.min[0] } else { self.max[0] },
+                          if i & 2 == 0 { self.min[1] } else { self.max[1] },
+                          if i & 4 == 0 { self.min[2] } else { self.max[2] }];
+        }
+        corners
+    }
+
+    /// The `BoundingBox` containing `self` after it is moved by a matrix
+    ///
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> BoundingBox {
+        let mut bounds = BoundingBox::empty();
+        if self.is_empty() {
+            return bounds;
+        }
+        for corner in self.corners().iter() {
+            let p: Vector4<f32> = *matrix * Vector4::new(corner[0], corner[1], corner[2], 1.0f32);
+            bounds.extend(&[p.x, p.y, p.z]);
+        }
+        bounds
+    }
+}
+
+impl Mesh {
+    /// Create a `Mesh` with an identity matrix, the bounding sphere is computed from the vertices
+    ///
+    pub fn new(name: &str, vertices: Vec<Vertex8f32>, material_index: usize) -> Mesh {
+        let identity: Matrix4<f32> = Eye::new_identity(4);
+        let mut mesh = Mesh {
+            name: String::from(name),
+            vertices: vertices,
+            material_index: material_index,
+            radius: 0.0,
+            center: [0.0; 3],
+            matrix: RefCell::new(identity),
+        };
+        mesh.compute_bounds();
+        mesh
+    }
+
+    /// Recalculate `center` and `radius` from the vertices
+    ///
+    pub fn compute_bounds(&mut self) {
+        let bounds = self.local_bounding_box();
+        if bounds.is_empty() {
+            self.center = [0.0; 3];
+            self.radius = 0.0;
+            return;
+        }
+        let center = bounds.center();
+        let mut radius_squared: f32 = 0.0;
+        for vertex in &self.vertices {
+            let p = vertex.position;
+            let dx = p[0] - center[0];
+            let dy = p[1] - center[1];
+            let dz = p[2] - center[2];
+            radius_squared = radius_squared.max(dx * dx + dy * dy + dz * dz);
+        }
+        self.center = center;
+        self.radius = radius_squared.sqrt();
+    }
+
+    /// Replace the vertices and recalculate the bounding sphere
+    ///
+    pub fn set_vertices(&mut self, vertices: Vec<Vertex8f32>) {
+        self.vertices = vertices;
+        self.compute_bounds();
+    }
+
+    /// The `BoundingBox` of the vertices in model space
+    ///
+    pub fn local_bounding_box(&self) -> BoundingBox {
+        BoundingBox::from_vertices(&self.vertices)
+    }
+
+    /// The `BoundingBox` of the `Mesh` moved by it's current matrix
+    ///
+    pub fn world_bounding_box(&self) -> BoundingBox {
+        self.local_bounding_box().transform(&*self.matrix.borrow())
+    }
+
+    /// The center and radius of the bounding sphere moved by the current matrix
+    ///
+    /// The radius is scaled by the largest axis scale of the matrix.
+    ///
+    pub fn world_bounding_sphere(&self) -> ([f32; 3], f32) {
+        let matrix: Matrix4<f32> = *self.matrix.borrow();
+        let center: Vector4<f32> = matrix * Vector4::new(self.center[0], self.center[1], self.center[2], 1.0f32);
+        let m = matrix.as_ref();
+        let mut scale: f32 = 0.0;
+        for column in 0..3 {
+            let c = m[column];
+            scale = scale.max((c[0] * c[0] + c[1] * c[1] + c[2] * c[2]).sqrt());
+        }
+        ([center.x, center.y, center.z], self.radius * scale)
+    }
+}
+
 /// `Material`
 ///
 /// Material properties from that can be passed as uniforms
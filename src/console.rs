@@ -0,0 +1,581 @@
+// Copyright (C) 2016 Chris Liebert
+
+extern crate libc;
+
+use std;
+use std::collections::BTreeMap;
+use std::ffi::CString;
+use std::fmt;
+use std::rc::Rc;
+
+use input::ConsoleInput;
+use ffi;
+
+/// The typed value of a console variable
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum CVarValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+impl CVarValue {
+    /// Parse a string as a value of the same type as self
+    ///
+    pub fn parse_as(&self, value: &str) -> Result<CVarValue, String> {
+        match self {
+            &CVarValue::Bool(_) => {
+                match value.to_lowercase().as_str() {
+                    "1" | "true" | "on" | "yes" => Ok(CVarValue::Bool(true)),
+                    "0" | "false" | "off" | "no" => Ok(CVarValue::Bool(false)),
+                    _ => Err(format!("Expected a boolean, found {}", value)),
+                }
+            },
+            &CVarValue::Int(_) => {
+                value.parse::<i64>()
+                    .map(CVarValue::Int)
+                    .map_err(|_| format!("Expected an integer, found {}", value))
+            },
+            &CVarValue::Float(_) => {
+                value.parse::<f64>()
+                    .map(CVarValue::Float)
+                    .map_err(|_| format!("Expected a number, found {}", value))
+            },
+            &CVarValue::Str(_) => Ok(CVarValue::Str(String::from(value))),
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            &CVarValue::Bool(_) => "bool",
+            &CVarValue::Int(_) => "int",
+            &CVarValue::Float(_) => "float",
+            &CVarValue::Str(_) => "string",
+        }
+    }
+}
+
+impl fmt::Display for CVarValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &CVarValue::Bool(b) => write!(f, "{}", b),
+            &CVarValue::Int(i) => write!(f, "{}", i),
+            &CVarValue::Float(x) => write!(f, "{}", x),
+            &CVarValue::Str(ref s) => write!(f, "\"{}\"", s),
+        }
+    }
+}
+
+/// A named console variable which other systems can read each frame
+///
+#[derive(Clone, Debug)]
+pub struct CVar {
+    pub name: String,
+    pub value: CVarValue,
+    pub default: CVarValue,
+    pub help: String,
+}
+
+/// A console command
+///
+/// The callback receives the words following the command name and returns the text to print
+/// or an error message.
+///
+pub struct Command {
+    pub name: String,
+    pub help: String,
+    pub callback: Box<Fn(&mut Console, &[String]) -> Result<String, String>>,
+}
+
+/// An interpreter for console commands and variables
+///
+/// A line is split into statements on `;`, each statement is split into words on whitespace
+/// where double quotes group words. The first word names a command or a console variable,
+/// a variable name alone prints the value of the variable and followed by a value sets it.
+///
+/// ```text
+/// help
+/// set camera_speed 0.05; camera_speed
+/// echo "Hello, world"
+/// ```
+///
+/// ```c
+/// /* C representation */
+/// typedef void* Console;
+/// ```
+///
+pub struct Console {
+    commands: BTreeMap<String, Rc<Command>>,
+    cvars: BTreeMap<String, CVar>,
+}
+
+/// Split a statement into words, double quotes group words and `\"` escapes a quote
+///
+pub fn tokenize(statement: &str) -> Result<Vec<String>, String> {
+    let mut words: Vec<String> = Vec::new();
+    let mut word: String = String::new();
+    let mut in_word: bool = false;
+    let mut quoted: bool = false;
+    let mut chars = statement.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_word = true;
+            },
+            '\\' if quoted => {
+                match chars.next() {
+                    Some(escaped) => word.push(escaped),
+                    None => return Err(String::from("Unexpected end of line after \\")),
+                }
+            },
+            c if c.is_whitespace() && !quoted => {
+                if in_word {
+                    words.push(word);
+                    word = String::new();
+                    in_word = false;
+                }
+            },
+            c => {
+                word.push(c);
+                in_word = true;
+            },
+        }
+    }
+    if quoted {
+        return Err(String::from("Unterminated quote"));
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// Split a line into statements on `;` outside of quotes
+///
+fn split_statements(line: &str) -> Vec<String> {
+    let mut statements: Vec<String> = Vec::new();
+    let mut statement: String = String::new();
+    let mut quoted: bool = false;
+    let mut escaped: bool = false;
+    for c in line.chars() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' && quoted {
+            escaped = true;
+        } else if c == '"' {
+            quoted = !quoted;
+        } else if (c == ';' || c == '\n') && !quoted {
+            statements.push(statement);
+            statement = String::new();
+            continue;
+        }
+        statement.push(c);
+    }
+    statements.push(statement);
+    statements
+}
+
+impl Console {
+    /// Create a `Console` with the built-in commands `help`, `set`, `get`, `reset`, `cvars`,
+    /// `commands` and `echo`
+    ///
+    pub fn new() -> Console {
+        let mut console: Console = Console {
+            commands: BTreeMap::new(),
+            cvars: BTreeMap::new(),
+        };
+        console.register_command("help", "help [name]: describe a command or variable, or list everything", |console, args| {
+            match args.get(0) {
+                Some(name) => console.help(name),
+                None => Ok(console.help_all()),
+            }
+        });
+        console.register_command("set", "set <name> <value>: set a console variable", |console, args| {
+            if args.len() != 2 {
+                return Err(String::from("Usage: set <name> <value>"));
+            }
+            console.set_cvar(&args[0], &args[1]).map(|_| String::new())
+        });
+        console.register_command("get", "get <name>: print a console variable", |console, args| {
+            match args.get(0) {
+                Some(name) => console.cvar(name).map(|c| format!("{} = {}", c.name, c.value)).ok_or(format!("Unknown variable {}", name)),
+                None => Err(String::from("Usage: get <name>")),
+            }
+        });
+        console.register_command("reset", "reset <name>: restore the default value of a console variable", |console, args| {
+            match args.get(0) {
+                Some(name) => console.reset_cvar(name).map(|_| String::new()),
+                None => Err(String::from("Usage: reset <name>")),
+            }
+        });
+        console.register_command("cvars", "cvars: list the console variables and their values", |console, _| {
+            let lines: Vec<String> = console.cvars.values().map(|c| format!("{} = {}", c.name, c.value)).collect();
+            Ok(lines.join("\n"))
+        });
+        console.register_command("commands", "commands: list the console commands", |console, _| {
+            Ok(console.commands.keys().cloned().collect::<Vec<String>>().join("\n"))
+        });
+        console.register_command("echo", "echo [text]: print text", |_, args| Ok(args.join(" ")));
+        console
+    }
+
+    /// Register a command, replacing any command with the same name
+    ///
+    pub fn register_command<F>(&mut self, name: &str, help: &str, callback: F)
+        where F: Fn(&mut Console, &[String]) -> Result<String, String> + 'static
+    {
+        self.commands.insert(String::from(name), Rc::new(Command {
+            name: String::from(name),
+            help: String::from(help),
+            callback: Box::new(callback),
+        }));
+    }
+
+    pub fn unregister_command(&mut self, name: &str) {
+        self.commands.remove(name);
+    }
+
+    /// Register a console variable, an existing variable of the same name keeps it's value if
+    /// the value has the same type
+    ///
+    pub fn register_cvar(&mut self, name: &str, default: CVarValue, help: &str) {
+        let value: CVarValue = match self.cvars.get(name) {
+            Some(existing) if existing.value.type_name() == default.type_name() => existing.value.clone(),
+            _ => default.clone(),
+        };
+        self.cvars.insert(String::from(name), CVar {
+            name: String::from(name),
+            value: value,
+            default: default,
+            help: String::from(help),
+        });
+    }
+
+    pub fn cvar(&self, name: &str) -> Option<&CVar> {
+        self.cvars.get(name)
+    }
+
+    /// Set a console variable from a string, which must parse as the type of the variable
+    ///
+    pub fn set_cvar(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match self.cvars.get_mut(name) {
+            Some(cvar) => {
+                cvar.value = try!(cvar.value.parse_as(value));
+                Ok(())
+            },
+            None => Err(format!("Unknown variable {}", name)),
+        }
+    }
+
+    pub fn reset_cvar(&mut self, name: &str) -> Result<(), String> {
+        match self.cvars.get_mut(name) {
+            Some(cvar) => {
+                cvar.value = cvar.default.clone();
+                Ok(())
+            },
+            None => Err(format!("Unknown variable {}", name)),
+        }
+    }
+
+    /// The value of a boolean variable, or false if there is no such variable
+    ///
+    pub fn get_bool(&self, name: &str) -> bool {
+        match self.cvars.get(name).map(|c| &c.value) {
+            Some(&CVarValue::Bool(b)) => b,
+            Some(&CVarValue::Int(i)) => i != 0,
+            _ => false,
+        }
+    }
+
+    /// The value of an integer variable, or 0 if there is no such variable
+    ///
+    pub fn get_int(&self, name: &str) -> i64 {
+        match self.cvars.get(name).map(|c| &c.value) {
+            Some(&CVarValue::Int(i)) => i,
+            Some(&CVarValue::Float(x)) => x as i64,
+            Some(&CVarValue::Bool(b)) => b as i64,
+            _ => 0,
+        }
+    }
+
+    /// The value of a numeric variable, or 0 if there is no such variable
+    ///
+    pub fn get_float(&self, name: &str) -> f64 {
+        match self.cvars.get(name).map(|c| &c.value) {
+            Some(&CVarValue::Float(x)) => x,
+            Some(&CVarValue::Int(i)) => i as f64,
+            _ => 0.0,
+        }
+    }
+
+    /// The value of a variable as a string, string variables are not quoted
+    ///
+    pub fn get_string(&self, name: &str) -> Option<String> {
+        match self.cvars.get(name).map(|c| &c.value) {
+            Some(&CVarValue::Str(ref s)) => Some(s.clone()),
+            Some(value) => Some(format!("{}", value)),
+            None => None,
+        }
+    }
+
+    fn help(&self, name: &str) -> Result<String, String> {
+        if let Some(command) = self.commands.get(name) {
+            return Ok(command.help.clone());
+        }
+        match self.cvars.get(name) {
+            Some(cvar) => Ok(format!("{} ({}, default {}): {}", cvar.name, cvar.value.type_name(), cvar.default, cvar.help)),
+            None => Err(format!("Unknown command or variable {}", name)),
+        }
+    }
+
+    fn help_all(&self) -> String {
+        let mut lines: Vec<String> = self.commands.values().map(|c| c.help.clone()).collect();
+        for cvar in self.cvars.values() {
+            lines.push(format!("{} ({}): {}", cvar.name, cvar.value.type_name(), cvar.help));
+        }
+        lines.join("\n")
+    }
+
+    /// The sorted names of the commands and variables starting with prefix
+    ///
+    pub fn complete(&self, prefix: &str) -> Vec<String> {
+        let mut names: Vec<String> = self.commands.keys()
+            .chain(self.cvars.keys())
+            .filter(|name| name.starts_with(prefix))
+            .cloned()
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Run a single statement which has been split into words
+    ///
+    pub fn execute_words(&mut self, words: &[String]) -> Result<String, String> {
+        let name: &String = match words.first() {
+            Some(name) => name,
+            None => return Ok(String::new()),
+        };
+        let command: Option<Rc<Command>> = self.commands.get(name).cloned();
+        if let Some(command) = command {
+            return (command.callback)(self, &words[1..]);
+        }
+        if self.cvars.contains_key(name) {
+            return match words.len() {
+                1 => Ok(format!("{} = {}", name, self.cvars[name].value)),
+                2 => self.set_cvar(name, &words[1]).map(|_| String::new()),
+                _ => Err(format!("Usage: {} [value]", name)),
+            };
+        }
+        Err(format!("Unknown command {}", name))
+    }
+
+    /// Run every statement of a line, stopping at the first error
+    ///
+    /// The output of the statements is joined by newlines.
+    ///
+    pub fn execute(&mut self, line: &str) -> Result<String, String> {
+        let mut output: Vec<String> = Vec::new();
+        for statement in split_statements(line) {
+            let words: Vec<String> = try!(tokenize(&statement));
+            let result: String = try!(self.execute_words(&words));
+            if !result.is_empty() {
+                output.push(result);
+            }
+        }
+        Ok(output.join("\n"))
+    }
+
+    /// Run the lines read by a `ConsoleInput` since the last call, printing the output
+    ///
+    /// Returns the number of lines which failed, the error of the last one is kept as the last
+    /// error for `quick3d_last_error`.
+    ///
+    pub fn process_input(&mut self, input: &ConsoleInput) -> usize {
+        let mut errors: usize = 0;
+        for line in input.read().lines() {
+            match self.execute(line) {
+                Ok(ref output) if output.is_empty() => (),
+                Ok(output) => println!("{}", output),
+                Err(e) => {
+                    ffi::set_last_error(&format!("{}: {}", line, e));
+                    errors += 1;
+                },
+            }
+        }
+        errors
+    }
+}
+
+/// `extern Console create_console();`
+///
+#[no_mangle]
+pub extern "C" fn create_console() -> Option<Box<Console>> {
+    ffi::guard(|| {
+        Box::new(Console::new())
+    })
+}
+
+/// `extern void free_console(Console ptr);`
+///
+#[no_mangle]
+pub extern "C" fn free_console(ptr: *mut Console) {
+    ffi::guard_status(|| {
+        let box_ptr: Box<Console> = unsafe { Box::from_raw(ptr) };
+        drop(box_ptr)
+    });
+}
+
+/// Run a line of console commands and print the output, returns false if a command failed
+///
+/// `extern bool console_execute(Console console, const char* line);`
+///
+#[no_mangle]
+pub extern "C" fn console_execute(console: &mut Console, line: *const libc::c_char) -> bool {
+    ffi::guard_result_or(false, || {
+        match console.execute(&try!(ffi::c_string(line))) {
+            Ok(output) => {
+                if !output.is_empty() {
+                    println!("{}", output);
+                }
+                Ok(true)
+            },
+            Err(e) => {
+                ffi::set_last_error(&e);
+                Ok(false)
+            },
+        }
+    })
+}
+
+/// Run the lines read by a `ConsoleInput`, returns the number of lines which failed
+///
+/// The error of the last line which failed is available from `quick3d_last_error`.
+///
+/// `extern int console_process_input(Console console, ConsoleInput input);`
+///
+#[no_mangle]
+pub extern "C" fn console_process_input(console: &mut Console, input: &ConsoleInput) -> libc::c_int {
+    ffi::guard_or(-1, || {
+        console.process_input(input) as libc::c_int
+    })
+}
+
+/// Get the names starting with prefix separated by newlines, the string must be freed with
+/// `free_string`
+///
+/// `extern char* console_complete(Console console, const char* prefix);`
+///
+#[no_mangle]
+pub extern "C" fn console_complete(console: &Console, prefix: *const libc::c_char) -> *mut libc::c_char {
+    ffi::guard_result_or(std::ptr::null_mut(), || {
+        match CString::new(console.complete(&try!(ffi::c_string(prefix))).join("\n")) {
+            Ok(s) => Ok(s.into_raw()),
+            Err(_) => Ok(std::ptr::null_mut()),
+        }
+    })
+}
+
+/// `extern bool console_register_bool(Console console, const char* name, bool value, const char* help);`
+///
+#[no_mangle]
+pub extern "C" fn console_register_bool(console: &mut Console, name: *const libc::c_char, value: bool, help: *const libc::c_char) -> bool {
+    ffi::guard_result_status(|| {
+        console.register_cvar(&try!(ffi::c_string(name)), CVarValue::Bool(value), &try!(ffi::c_string(help)));
+        Ok(())
+    })
+}
+
+/// `extern bool console_register_int(Console console, const char* name, int value, const char* help);`
+///
+#[no_mangle]
+pub extern "C" fn console_register_int(console: &mut Console, name: *const libc::c_char, value: libc::c_int, help: *const libc::c_char) -> bool {
+    ffi::guard_result_status(|| {
+        console.register_cvar(&try!(ffi::c_string(name)), CVarValue::Int(value as i64), &try!(ffi::c_string(help)));
+        Ok(())
+    })
+}
+
+/// `extern bool console_register_float(Console console, const char* name, double value, const char* help);`
+///
+#[no_mangle]
+pub extern "C" fn console_register_float(console: &mut Console, name: *const libc::c_char, value: libc::c_double, help: *const libc::c_char) -> bool {
+    ffi::guard_result_status(|| {
+        console.register_cvar(&try!(ffi::c_string(name)), CVarValue::Float(value as f64), &try!(ffi::c_string(help)));
+        Ok(())
+    })
+}
+
+/// `extern bool console_register_string(Console console, const char* name, const char* value, const char* help);`
+///
+#[no_mangle]
+pub extern "C" fn console_register_string(console: &mut Console, name: *const libc::c_char, value: *const libc::c_char, help: *const libc::c_char) -> bool {
+    ffi::guard_result_status(|| {
+        console.register_cvar(&try!(ffi::c_string(name)), CVarValue::Str(try!(ffi::c_string(value))), &try!(ffi::c_string(help)));
+        Ok(())
+    })
+}
+
+/// Set a console variable from a string, returns false if the variable does not exist or the
+/// value is invalid
+///
+/// `extern bool console_set(Console console, const char* name, const char* value);`
+///
+#[no_mangle]
+pub extern "C" fn console_set(console: &mut Console, name: *const libc::c_char, value: *const libc::c_char) -> bool {
+    ffi::guard_result_or(false, || {
+        match console.set_cvar(&try!(ffi::c_string(name)), &try!(ffi::c_string(value))) {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                ffi::set_last_error(&e);
+                Ok(false)
+            },
+        }
+    })
+}
+
+/// `extern bool console_get_bool(Console console, const char* name);`
+///
+#[no_mangle]
+pub extern "C" fn console_get_bool(console: &Console, name: *const libc::c_char) -> bool {
+    ffi::guard_result_or(false, || {
+        Ok(console.get_bool(&try!(ffi::c_string(name))))
+    })
+}
+
+/// `extern int console_get_int(Console console, const char* name);`
+///
+#[no_mangle]
+pub extern "C" fn console_get_int(console: &Console, name: *const libc::c_char) -> libc::c_int {
+    ffi::guard_result_or(-1, || {
+        Ok(console.get_int(&try!(ffi::c_string(name))) as libc::c_int)
+    })
+}
+
+/// `extern double console_get_float(Console console, const char* name);`
+///
+#[no_mangle]
+pub extern "C" fn console_get_float(console: &Console, name: *const libc::c_char) -> libc::c_double {
+    ffi::guard_result_or(0.0, || {
+        Ok(console.get_float(&try!(ffi::c_string(name))) as libc::c_double)
+    })
+}
+
+/// Get a console variable as a string, the string must be freed with `free_string`
+///
+/// Returns null if there is no such variable.
+///
+/// `extern char* console_get_string(Console console, const char* name);`
+///
+#[no_mangle]
+pub extern "C" fn console_get_string(console: &Console, name: *const libc::c_char) -> *mut libc::c_char {
+    ffi::guard_result_or(std::ptr::null_mut(), || {
+        match console.get_string(&try!(ffi::c_string(name))).map(CString::new) {
+            Some(Ok(s)) => Ok(s.into_raw()),
+            _ => Ok(std::ptr::null_mut()),
+        }
+    })
+}
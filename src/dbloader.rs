@@ -7,11 +7,11 @@ extern crate libc;
 extern crate rusqlite;
 
 #[cfg(feature = "sqlite")]
-use std::ffi::CStr;
-#[cfg(feature = "sqlite")]
-use std::io::Error;
+use std::io;
+use error::Error;
 #[cfg(feature = "sqlite")]
 use scene::Scene;
+use ffi;
 #[cfg(feature = "sqlite")]use shader::Shader;
 #[cfg(feature = "sqlite")]use std::path::Path;
 #[cfg(feature = "sqlite")]
@@ -23,7 +23,7 @@ pub struct DBLoader {
 #[cfg(feature = "sqlite")]
 #[derive(Debug)]
 pub enum DBLoaderError {
-    IoError(Error),
+    IoError(io::Error),
     DBError(self::rusqlite::Error),
 }
 
@@ -46,18 +46,17 @@ pub struct SceneNodeTableRow {
 #[cfg(feature = "sqlite")]
 impl DBLoader {
     /// Create a new DBLoader object
-    pub fn new(filename: &str) -> Result<DBLoader, DBLoaderError> {
-        use std::io::Error;
-        use std::io::ErrorKind;
+    pub fn new(filename: &str) -> Result<DBLoader, Error> {
         use std::path::Path;
         if !Path::new(filename.clone()).exists() {
-            return Err(DBLoaderError::IoError(Error::new(ErrorKind::NotFound, format!("Unable to load {}", filename) )));
+            let error = io::Error::new(io::ErrorKind::NotFound, format!("Unable to load {}", filename));
+            return Err(Error::DBLoader(DBLoaderError::IoError(error)));
         }
         Ok(DBLoader { filename: String::from(filename) })
     }
 
     /// Load the contents of an SQLite datbase into a `Scene` data structure.
-    pub fn load_scene(&self) -> Result<Scene, DBLoaderError> {
+    pub fn load_scene(&self) -> Result<Scene, Error> {
         use self::rusqlite::Connection;
         use nalgebra::{Eye, Matrix4};
         use std::cell::RefCell;
@@ -205,7 +204,7 @@ impl DBLoader {
     }
 
     /// Load a shader from an SQLite database
-    pub fn load_shader(&self, name: &str, glsl_version_string: &str) -> Result<Shader, DBLoaderError> {
+    pub fn load_shader(&self, name: &str, glsl_version_string: &str) -> Result<Shader, Error> {
         let conn = try!(
             Connection::open(Path::new(&self.filename))
                 .map_err(DBLoaderError::DBError)
@@ -250,40 +249,43 @@ impl DBLoader {
     }
 }
 
-/// `extern void free_db_loader(DBLoader dbloader);`
+/// `extern void free_db_loader(DBLoader ptr);`
 ///
 #[cfg(feature = "sqlite")]
 #[no_mangle]
 pub extern "C" fn free_db_loader(ptr: *mut DBLoader) {
-    let box_ptr: Box<DBLoader> = unsafe { Box::from_raw(ptr) };
-    drop(box_ptr)
+    ffi::guard_status(|| {
+        let box_ptr: Box<DBLoader> = unsafe { Box::from_raw(ptr) };
+        drop(box_ptr)
+    });
 }
 
-/// `extern void free_db_loader(DBLoader dbloader);`
+/// `extern void free_db_loader(DBLoader ptr);`
 ///
 #[cfg(not(feature = "sqlite"))]
 #[no_mangle]
 pub extern "C" fn free_db_loader(_ptr: *mut libc::c_void) {
-	panic!("The SQLite feature is not enabled");
+    ffi::guard_result_status(|| Err(ffi::sqlite_disabled()));
 }
 
 /// `extern DBLoader create_db_loader(const char* filename);`
 ///
 #[cfg(feature = "sqlite")]
 #[no_mangle]
-pub extern "C" fn create_db_loader(filename_cstr: *const libc::c_char) -> Box<DBLoader> {
-    unsafe {
-        let filename: String = CStr::from_ptr(filename_cstr).to_string_lossy().into_owned();
-        let dbloader: DBLoader = DBLoader::new(&filename).expect("Unable to create database loader");
-        println!("Loaded {}", &filename);
-        Box::new(dbloader)
-    }
+pub extern "C" fn create_db_loader(filename_cstr: *const libc::c_char) -> Option<Box<DBLoader>> {
+    ffi::guard_result(|| -> Result<Box<DBLoader>, Error> {
+        let filename: String = try!(ffi::c_string(filename_cstr));
+        let dbloader: DBLoader = try!(DBLoader::new(&filename));
+        Ok(Box::new(dbloader))
+    })
 }
 
 /// `extern DBLoader create_db_loader(const char* filename);`
 ///
 #[cfg(not(feature = "sqlite"))]
 #[no_mangle]
-pub extern "C" fn create_db_loader(_filename_cstr: *const libc::c_char) -> Box<libc::c_void> {
-    panic!("The SQLite feature is not enabled");
+pub extern "C" fn create_db_loader(_filename_cstr: *const libc::c_char) -> Option<Box<libc::c_void>> {
+    ffi::guard_result(|| -> Result<Box<libc::c_void>, Error> {
+        Err(ffi::sqlite_disabled())
+    })
 }
\ No newline at end of file
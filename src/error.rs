@@ -0,0 +1,264 @@
+// Copyright (C) 2016 Chris Liebert
+
+use std;
+use std::error;
+use std::fmt;
+use std::io;
+
+use bincode::rustc_serialize::{EncodingError, DecodingError};
+
+#[cfg(feature = "sqlite")]
+use dbloader::DBLoaderError;
+use inputmap::InputMapError;
+use renderer::RendererError;
+use shader::ShaderError;
+
+/// Any error returned by quick3d
+///
+/// The variant tells which part of the library failed, the error it wraps is available from
+/// `cause` for more detail.
+///
+#[derive(Debug)]
+pub enum Error {
+    #[cfg(feature = "sqlite")]
+    DBLoader(DBLoaderError),
+    Decoding(DecodingError),
+    Encoding(EncodingError),
+    InputMap(InputMapError),
+    InvalidArgument(String),
+    Io(io::Error),
+    Renderer(RendererError),
+    Shader(ShaderError),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            #[cfg(feature = "sqlite")]
+            Error::DBLoader(ref e) => write!(f, "Database error: {}", e),
+            Error::Decoding(ref e) => write!(f, "Unable to decode binary data: {}", e),
+            Error::Encoding(ref e) => write!(f, "Unable to encode binary data: {}", e),
+            Error::InputMap(ref e) => write!(f, "Input map error: {}", e),
+            Error::InvalidArgument(ref message) => write!(f, "Invalid argument: {}", message),
+            Error::Io(ref e) => write!(f, "IO error: {}", e),
+            Error::Renderer(ref e) => write!(f, "Renderer error: {}", e),
+            Error::Shader(ref e) => write!(f, "Shader error: {}", e),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            #[cfg(feature = "sqlite")]
+            Error::DBLoader(_) => "database error",
+            Error::Decoding(_) => "unable to decode binary data",
+            Error::Encoding(_) => "unable to encode binary data",
+            Error::InputMap(_) => "input map error",
+            Error::InvalidArgument(_) => "invalid argument",
+            Error::Io(_) => "IO error",
+            Error::Renderer(_) => "renderer error",
+            Error::Shader(_) => "shader error",
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            #[cfg(feature = "sqlite")]
+            Error::DBLoader(ref e) => Some(e),
+            Error::Decoding(ref e) => Some(e),
+            Error::Encoding(ref e) => Some(e),
+            Error::InputMap(ref e) => Some(e),
+            Error::InvalidArgument(_) => None,
+            Error::Io(ref e) => Some(e),
+            Error::Renderer(ref e) => Some(e),
+            Error::Shader(ref e) => Some(e),
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<DBLoaderError> for Error {
+    fn from(e: DBLoaderError) -> Error {
+        Error::DBLoader(e)
+    }
+}
+
+impl From<DecodingError> for Error {
+    fn from(e: DecodingError) -> Error {
+        Error::Decoding(e)
+    }
+}
+
+impl From<EncodingError> for Error {
+    fn from(e: EncodingError) -> Error {
+        Error::Encoding(e)
+    }
+}
+
+impl From<InputMapError> for Error {
+    fn from(e: InputMapError) -> Error {
+        Error::InputMap(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl From<RendererError> for Error {
+    fn from(e: RendererError) -> Error {
+        Error::Renderer(e)
+    }
+}
+
+impl From<ShaderError> for Error {
+    fn from(e: ShaderError) -> Error {
+        Error::Shader(e)
+    }
+}
+
+impl fmt::Display for RendererError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RendererError::DisplayCreationError(ref e) => write!(f, "Unable to create display: {}", e),
+            RendererError::DrawError(ref e) => write!(f, "Unable to draw: {}", e),
+            RendererError::EmptySceneError => write!(f, "The scene does not contain any meshes"),
+            RendererError::FontNotLoadedError => write!(f, "No font has been loaded for drawing text"),
+            RendererError::FrameBufferValidationError(ref e) => write!(f, "Invalid frame buffer: {}", e),
+            RendererError::ImageLoadingError(ref e) => write!(f, "Unable to load image: {}", e),
+            RendererError::InvalidFontError => write!(f, "The font data is not a valid TrueType font"),
+            RendererError::ProgramCreationError(ref e) => write!(f, "Unable to create program: {}", e),
+            RendererError::RenderBufferCreationError(ref e) => write!(f, "Unable to create render buffer: {}", e),
+            RendererError::SwapBuffersError(ref e) => write!(f, "Unable to swap buffers: {}", e),
+            RendererError::TextureCreationError(ref e) => write!(f, "Unable to create texture: {}", e),
+            RendererError::VertexBufferCreationError(ref e) => write!(f, "Unable to create vertex buffer: {}", e),
+        }
+    }
+}
+
+impl error::Error for RendererError {
+    fn description(&self) -> &str {
+        match *self {
+            RendererError::DisplayCreationError(_) => "unable to create display",
+            RendererError::DrawError(_) => "unable to draw",
+            RendererError::EmptySceneError => "the scene does not contain any meshes",
+            RendererError::FontNotLoadedError => "no font has been loaded",
+            RendererError::FrameBufferValidationError(_) => "invalid frame buffer",
+            RendererError::ImageLoadingError(_) => "unable to load image",
+            RendererError::InvalidFontError => "invalid font",
+            RendererError::ProgramCreationError(_) => "unable to create program",
+            RendererError::RenderBufferCreationError(_) => "unable to create render buffer",
+            RendererError::SwapBuffersError(_) => "unable to swap buffers",
+            RendererError::TextureCreationError(_) => "unable to create texture",
+            RendererError::VertexBufferCreationError(_) => "unable to create vertex buffer",
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            RendererError::DisplayCreationError(ref e) => Some(e),
+            RendererError::DrawError(ref e) => Some(e),
+            RendererError::EmptySceneError => None,
+            RendererError::FontNotLoadedError => None,
+            RendererError::FrameBufferValidationError(ref e) => Some(e),
+            RendererError::ImageLoadingError(ref e) => Some(e),
+            RendererError::InvalidFontError => None,
+            RendererError::ProgramCreationError(ref e) => Some(e),
+            RendererError::RenderBufferCreationError(ref e) => Some(e),
+            RendererError::SwapBuffersError(ref e) => Some(e),
+            RendererError::TextureCreationError(ref e) => Some(e),
+            RendererError::VertexBufferCreationError(ref e) => Some(e),
+        }
+    }
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ShaderError::IoError(ref e) => write!(f, "Unable to read shader: {}", e),
+            ShaderError::ProgramChooserCreationError(ref e) => write!(f, "Unable to create program: {}", e),
+            ShaderError::ProgramCreationError(ref e) => write!(f, "Unable to create program: {}", e),
+            ShaderError::InvalidGLSLVersionStringError(ref e) => write!(f, "Invalid GLSL version: {}", e),
+            ShaderError::UnsupportedGLSLVersionError(ref e) => write!(f, "Unsupported GLSL version: {}", e),
+        }
+    }
+}
+
+impl error::Error for ShaderError {
+    fn description(&self) -> &str {
+        match *self {
+            ShaderError::IoError(_) => "unable to read shader",
+            ShaderError::ProgramChooserCreationError(_) => "unable to create program",
+            ShaderError::ProgramCreationError(_) => "unable to create program",
+            ShaderError::InvalidGLSLVersionStringError(_) => "invalid GLSL version",
+            ShaderError::UnsupportedGLSLVersionError(_) => "unsupported GLSL version",
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            ShaderError::IoError(ref e) => Some(e),
+            ShaderError::ProgramChooserCreationError(ref e) => Some(e),
+            ShaderError::ProgramCreationError(ref e) => Some(e),
+            ShaderError::InvalidGLSLVersionStringError(ref e) => Some(e),
+            ShaderError::UnsupportedGLSLVersionError(ref e) => Some(e),
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl fmt::Display for DBLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DBLoaderError::IoError(ref e) => write!(f, "{}", e),
+            DBLoaderError::DBError(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl error::Error for DBLoaderError {
+    fn description(&self) -> &str {
+        match *self {
+            DBLoaderError::IoError(_) => "unable to open database",
+            DBLoaderError::DBError(_) => "SQLite error",
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            DBLoaderError::IoError(ref e) => Some(e),
+            DBLoaderError::DBError(ref e) => Some(e),
+        }
+    }
+}
+
+impl fmt::Display for InputMapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            InputMapError::IoError(ref e) => write!(f, "{}", e),
+            InputMapError::ParseError(line, ref message) => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl error::Error for InputMapError {
+    fn description(&self) -> &str {
+        match *self {
+            InputMapError::IoError(_) => "unable to read input map",
+            InputMapError::ParseError(_, _) => "unable to parse input map",
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            InputMapError::IoError(ref e) => Some(e),
+            InputMapError::ParseError(_, _) => None,
+        }
+    }
+}
@@ -0,0 +1,137 @@
+// Copyright (C) 2016 Chris Liebert
+
+extern crate libc;
+
+use std;
+use std::any::Any;
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::io;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+
+use error::Error;
+
+thread_local!(static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None));
+
+/// Record an error message for `quick3d_last_error`
+///
+pub fn set_last_error(message: &str) {
+    let message: CString = CString::new(message.replace("\0", ""))
+        .expect("Unable to convert error message");
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+pub fn clear_last_error() {
+    LAST_ERROR.with(|e| *e.borrow_mut() = None);
+}
+
+/// The error recorded by the last call on this thread, if it failed
+///
+pub fn last_error() -> Option<String> {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map(|m| m.to_string_lossy().into_owned()))
+}
+
+/// Copy a string passed to an exported function
+///
+/// Returns an `InvalidArgument` error if the pointer is null.
+///
+pub fn c_string(string: *const libc::c_char) -> Result<String, Error> {
+    if string.is_null() {
+        return Err(Error::InvalidArgument(String::from("Unexpected null string")));
+    }
+    Ok(unsafe { CStr::from_ptr(string).to_string_lossy().into_owned() })
+}
+
+/// The error returned by the functions which need the sqlite feature when it is disabled
+///
+#[cfg(not(feature = "sqlite"))]
+pub fn sqlite_disabled() -> Error {
+    Error::Io(io::Error::new(io::ErrorKind::Other, "The SQLite feature is not enabled"))
+}
+
+fn panic_message(payload: &Box<Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        String::from(*message)
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        String::from("Unknown error")
+    }
+}
+
+/// Run the body of an exported function, stopping a panic from unwinding into the caller
+///
+/// The last error is cleared before the call, and set to the panic message if it panics.
+///
+pub fn guard<T, F: FnOnce() -> T>(f: F) -> Option<T> {
+    clear_last_error();
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(value) => Some(value),
+        Err(payload) => {
+            set_last_error(&panic_message(&payload));
+            None
+        }
+    }
+}
+
+/// Like `guard`, returning `default` if the function panics
+///
+pub fn guard_or<T, F: FnOnce() -> T>(default: T, f: F) -> T {
+    guard(f).unwrap_or(default)
+}
+
+/// Like `guard` for functions without a result, returning whether it succeeded
+///
+pub fn guard_status<F: FnOnce()>(f: F) -> bool {
+    guard(f).is_some()
+}
+
+/// Like `guard` for functions returning a `Result`, recording the error for `quick3d_last_error`
+///
+/// Returns `None` if the function returned an error or panicked.
+///
+pub fn guard_result<T, F: FnOnce() -> Result<T, Error>>(f: F) -> Option<T> {
+    match guard(f) {
+        Some(Ok(value)) => Some(value),
+        Some(Err(e)) => {
+            set_last_error(&e.to_string());
+            None
+        }
+        None => None,
+    }
+}
+
+/// Like `guard_result`, returning `default` if the function fails
+///
+pub fn guard_result_or<T, F: FnOnce() -> Result<T, Error>>(default: T, f: F) -> T {
+    guard_result(f).unwrap_or(default)
+}
+
+/// Like `guard_result` for functions without a result, returning whether it succeeded
+///
+pub fn guard_result_status<F: FnOnce() -> Result<(), Error>>(f: F) -> bool {
+    guard_result(f).is_some()
+}
+
+/// Get the error message of the last call made on this thread
+///
+/// Returns null if the last call succeeded. The string is owned by quick3d and is valid until
+/// the next call made on the same thread.
+///
+/// `extern const char* quick3d_last_error();`
+///
+#[no_mangle]
+pub extern "C" fn quick3d_last_error() -> *const libc::c_char {
+    LAST_ERROR.with(|e| match *e.borrow() {
+        Some(ref message) => message.as_ptr(),
+        None => std::ptr::null(),
+    })
+}
+
+/// `extern void quick3d_clear_error();`
+///
+#[no_mangle]
+pub extern "C" fn quick3d_clear_error() {
+    clear_last_error();
+}
@@ -3,11 +3,19 @@
 extern crate libc;
 
 use std;
+use std::collections::HashSet;
 use std::ffi::CString;
+use std::io;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
-use glium::glutin::{ElementState, Event, MouseButton, VirtualKeyCode};
+use glium::glutin::{ElementState, Event, MouseButton, MouseScrollDelta, Touch, TouchPhase, VirtualKeyCode};
 use glium::backend::glutin_backend::GlutinFacade;
+use ffi;
+
+/// The approximate height of a line in pixels, used to convert pixel scrolling to lines
+///
+pub const PIXELS_PER_LINE: f32 = 20.0;
 
 #[derive(Debug)]
 pub struct EventBuffer(Vec<Event>);
@@ -183,6 +191,163 @@ impl KeyCode {
            &KeyCode::YEN => VirtualKeyCode::Yen, 
        }
    }
+
+   /// Look up a `KeyCode` by it's name in the C enum, ignoring case
+   ///
+   pub fn from_name(name: &str) -> Option<KeyCode> {
+       match name.to_uppercase().as_str() {
+           "KEY1" => Some(KeyCode::KEY1),
+           "KEY2" => Some(KeyCode::KEY2),
+           "KEY3" => Some(KeyCode::KEY3),
+           "KEY4" => Some(KeyCode::KEY4),
+           "KEY5" => Some(KeyCode::KEY5),
+           "KEY6" => Some(KeyCode::KEY6),
+           "KEY7" => Some(KeyCode::KEY7),
+           "KEY8" => Some(KeyCode::KEY8),
+           "KEY9" => Some(KeyCode::KEY9),
+           "KEY0" => Some(KeyCode::KEY0),
+           "A" => Some(KeyCode::A),
+           "B" => Some(KeyCode::B),
+           "C" => Some(KeyCode::C),
+           "D" => Some(KeyCode::D),
+           "E" => Some(KeyCode::E),
+           "F" => Some(KeyCode::F),
+           "G" => Some(KeyCode::G),
+           "H" => Some(KeyCode::H),
+           "I" => Some(KeyCode::I),
+           "J" => Some(KeyCode::J),
+           "K" => Some(KeyCode::K),
+           "L" => Some(KeyCode::L),
+           "M" => Some(KeyCode::M),
+           "N" => Some(KeyCode::N),
+           "O" => Some(KeyCode::O),
+           "P" => Some(KeyCode::P),
+           "Q" => Some(KeyCode::Q),
+           "R" => Some(KeyCode::R),
+           "S" => Some(KeyCode::S),
+           "T" => Some(KeyCode::T),
+           "U" => Some(KeyCode::U),
+           "V" => Some(KeyCode::V),
+           "W" => Some(KeyCode::W),
+           "X" => Some(KeyCode::X),
+           "Y" => Some(KeyCode::Y),
+           "Z" => Some(KeyCode::Z),
+           "ESCAPE" => Some(KeyCode::ESCAPE),
+           "F1" => Some(KeyCode::F1),
+           "F2" => Some(KeyCode::F2),
+           "F3" => Some(KeyCode::F3),
+           "F4" => Some(KeyCode::F4),
+           "F5" => Some(KeyCode::F5),
+           "F6" => Some(KeyCode::F6),
+           "F7" => Some(KeyCode::F7),
+           "F8" => Some(KeyCode::F8),
+           "F9" => Some(KeyCode::F9),
+           "F10" => Some(KeyCode::F10),
+           "F11" => Some(KeyCode::F11),
+           "F12" => Some(KeyCode::F12),
+           "F13" => Some(KeyCode::F13),
+           "F14" => Some(KeyCode::F14),
+           "F15" => Some(KeyCode::F15),
+           "SNAPSHOT" => Some(KeyCode::SNAPSHOT),
+           "SCROLL" => Some(KeyCode::SCROLL),
+           "PAUSE" => Some(KeyCode::PAUSE),
+           "INSERT" => Some(KeyCode::INSERT),
+           "HOME" => Some(KeyCode::HOME),
+           "DELETE" => Some(KeyCode::DELETE),
+           "END" => Some(KeyCode::END),
+           "PAGEDOWN" => Some(KeyCode::PAGEDOWN),
+           "PAGEUP" => Some(KeyCode::PAGEUP),
+           "LEFT" => Some(KeyCode::LEFT),
+           "UP" => Some(KeyCode::UP),
+           "RIGHT" => Some(KeyCode::RIGHT),
+           "DOWN" => Some(KeyCode::DOWN),
+           "BACK" => Some(KeyCode::BACK),
+           "RETURN" => Some(KeyCode::RETURN),
+           "SPACE" => Some(KeyCode::SPACE),
+           "NUMLOCK" => Some(KeyCode::NUMLOCK),
+           "NUMPAD0" => Some(KeyCode::NUMPAD0),
+           "NUMPAD1" => Some(KeyCode::NUMPAD1),
+           "NUMPAD2" => Some(KeyCode::NUMPAD2),
+           "NUMPAD3" => Some(KeyCode::NUMPAD3),
+           "NUMPAD4" => Some(KeyCode::NUMPAD4),
+           "NUMPAD5" => Some(KeyCode::NUMPAD5),
+           "NUMPAD6" => Some(KeyCode::NUMPAD6),
+           "NUMPAD7" => Some(KeyCode::NUMPAD7),
+           "NUMPAD8" => Some(KeyCode::NUMPAD8),
+           "NUMPAD9" => Some(KeyCode::NUMPAD9),
+           "ABNTC1" => Some(KeyCode::ABNTC1),
+           "ABNTC2" => Some(KeyCode::ABNTC2),
+           "ADD" => Some(KeyCode::ADD),
+           "APOSTROPHE" => Some(KeyCode::APOSTROPHE),
+           "APPS" => Some(KeyCode::APPS),
+           "AT" => Some(KeyCode::AT),
+           "AX" => Some(KeyCode::AX),
+           "BACKSLASH" => Some(KeyCode::BACKSLASH),
+           "CALCULATOR" => Some(KeyCode::CALCULATOR),
+           "CAPITAL" => Some(KeyCode::CAPITAL),
+           "COLON" => Some(KeyCode::COLON),
+           "COMMA" => Some(KeyCode::COMMA),
+           "CONVERT" => Some(KeyCode::CONVERT),
+           "DECIMAL" => Some(KeyCode::DECIMAL),
+           "DIVIDE" => Some(KeyCode::DIVIDE),
+           "EQUALS" => Some(KeyCode::EQUALS),
+           "GRAVE" => Some(KeyCode::GRAVE),
+           "KANA" => Some(KeyCode::KANA),
+           "KANJI" => Some(KeyCode::KANJI),
+           "LALT" => Some(KeyCode::LALT),
+           "LBRACKET" => Some(KeyCode::LBRACKET),
+           "LCONTROL" => Some(KeyCode::LCONTROL),
+           "LMENU" => Some(KeyCode::LMENU),
+           "LSHIFT" => Some(KeyCode::LSHIFT),
+           "LWIN" => Some(KeyCode::LWIN),
+           "MAIL" => Some(KeyCode::MAIL),
+           "MEDIASELECT" => Some(KeyCode::MEDIASELECT),
+           "MEDIASTOP" => Some(KeyCode::MEDIASTOP),
+           "MINUS" => Some(KeyCode::MINUS),
+           "MULTIPLY" => Some(KeyCode::MULTIPLY),
+           "MUTE" => Some(KeyCode::MUTE),
+           "MYCOMPUTER" => Some(KeyCode::MYCOMPUTER),
+           "NAVIGATEFORWARD" => Some(KeyCode::NAVIGATEFORWARD),
+           "NAVIGATEBACKWARD" => Some(KeyCode::NAVIGATEBACKWARD),
+           "NEXTTRACK" => Some(KeyCode::NEXTTRACK),
+           "NOCONVERT" => Some(KeyCode::NOCONVERT),
+           "NUMPADCOMMA" => Some(KeyCode::NUMPADCOMMA),
+           "NUMPADENTER" => Some(KeyCode::NUMPADENTER),
+           "NUMPADEQUALS" => Some(KeyCode::NUMPADEQUALS),
+           "OEM102" => Some(KeyCode::OEM102),
+           "PERIOD" => Some(KeyCode::PERIOD),
+           "PLAYPAUSE" => Some(KeyCode::PLAYPAUSE),
+           "POWER" => Some(KeyCode::POWER),
+           "PREVTRACK" => Some(KeyCode::PREVTRACK),
+           "RALT" => Some(KeyCode::RALT),
+           "RBRACKET" => Some(KeyCode::RBRACKET),
+           "RCONTROL" => Some(KeyCode::RCONTROL),
+           "RMENU" => Some(KeyCode::RMENU),
+           "RSHIFT" => Some(KeyCode::RSHIFT),
+           "RWIN" => Some(KeyCode::RWIN),
+           "SEMICOLON" => Some(KeyCode::SEMICOLON),
+           "SLASH" => Some(KeyCode::SLASH),
+           "SLEEP" => Some(KeyCode::SLEEP),
+           "STOP" => Some(KeyCode::STOP),
+           "SUBTRACT" => Some(KeyCode::SUBTRACT),
+           "SYSRQ" => Some(KeyCode::SYSRQ),
+           "TAB" => Some(KeyCode::TAB),
+           "UNDERLINE" => Some(KeyCode::UNDERLINE),
+           "UNLABELED" => Some(KeyCode::UNLABELED),
+           "VOLUMEDOWN" => Some(KeyCode::VOLUMEDOWN),
+           "VOLUMEUP" => Some(KeyCode::VOLUMEUP),
+           "WAKE" => Some(KeyCode::WAKE),
+           "WEBBACK" => Some(KeyCode::WEBBACK),
+           "WEBFAVORITES" => Some(KeyCode::WEBFAVORITES),
+           "WEBFORWARD" => Some(KeyCode::WEBFORWARD),
+           "WEBHOME" => Some(KeyCode::WEBHOME),
+           "WEBREFRESH" => Some(KeyCode::WEBREFRESH),
+           "WEBSEARCH" => Some(KeyCode::WEBSEARCH),
+           "WEBSTOP" => Some(KeyCode::WEBSTOP),
+           "YEN" => Some(KeyCode::YEN),
+           _ => None,
+       }
+   }
 }
 
 #[repr(C)]
@@ -191,6 +356,239 @@ pub struct Mouse {
     pub y: i32,
 }
 
+/// The kind of a window `Event`, as seen across the FFI
+///
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub enum EventKind {
+    EVENT_RESIZED, EVENT_MOVED, EVENT_CLOSED, EVENT_DROPPED_FILE, EVENT_RECEIVED_CHARACTER,
+    EVENT_FOCUSED, EVENT_KEYBOARD_INPUT, EVENT_MOUSE_MOVED, EVENT_MOUSE_ENTERED, EVENT_MOUSE_LEFT,
+    EVENT_MOUSE_WHEEL, EVENT_MOUSE_INPUT, EVENT_TOUCHPAD_PRESSURE, EVENT_AWAKENED, EVENT_REFRESH,
+    EVENT_SUSPENDED, EVENT_TOUCH,
+}
+
+/// A flattened copy of a window `Event` for iterating over an `EventBuffer` across the FFI
+///
+/// Only the fields used by the kind of event are set, the others are zero (or -1 for `keycode`):
+///
+/// * `EVENT_RESIZED`: `x` and `y` are the new width and height
+/// * `EVENT_MOVED`, `EVENT_MOUSE_MOVED`: `x` and `y` are the position
+/// * `EVENT_DROPPED_FILE`: `index` is the index for `events_dropped_file`
+/// * `EVENT_RECEIVED_CHARACTER`: `character` is the unicode code point
+/// * `EVENT_FOCUSED`, `EVENT_SUSPENDED`: `pressed` is the new state
+/// * `EVENT_KEYBOARD_INPUT`: `pressed`, `scancode` and `keycode`, a `KeyCode` or -1
+/// * `EVENT_MOUSE_WHEEL`: `fx` and `fy` are the delta, `phase` is set and `pressed` is true when
+///   the delta is in pixels rather than lines
+/// * `EVENT_MOUSE_INPUT`: `pressed` and `button` (0 left, 1 right, 2 middle)
+/// * `EVENT_TOUCHPAD_PRESSURE`: `fx` is the pressure and `id` the stage
+/// * `EVENT_TOUCH`: `fx` and `fy` are the location, `phase` and `id` are set
+///
+/// Touch phases are 0 (started), 1 (moved), 2 (ended) and 3 (cancelled).
+///
+/// ```c
+/// /* C representation */
+/// typedef struct EventData {
+///     EventKind kind;
+///     int x, y;
+///     double fx, fy;
+///     bool pressed;
+///     int keycode, scancode, button, phase, index;
+///     unsigned int character;
+///     long long id;
+/// } EventData;
+/// ```
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct EventData {
+    pub kind: EventKind,
+    pub x: i32,
+    pub y: i32,
+    pub fx: f64,
+    pub fy: f64,
+    pub pressed: bool,
+    pub keycode: i32,
+    pub scancode: i32,
+    pub button: i32,
+    pub phase: i32,
+    pub index: i32,
+    pub character: u32,
+    pub id: i64,
+}
+
+/// The index of a `KeyCode` in the C enum for a `VirtualKeyCode`
+///
+pub fn keycode_index(keycode: VirtualKeyCode) -> Option<i32> {
+    // The names of the C enum are the uppercase names of the glutin keys
+    KeyCode::from_name(&format!("{:?}", keycode)).map(|k| k as i32)
+}
+
+/// Every `VirtualKeyCode` in declaration order, so `VIRTUAL_KEY_CODES[key as usize] == key`
+///
+/// Unlike the C enum this includes keys such as `Compose` which have no `KeyCode`.
+///
+pub const VIRTUAL_KEY_CODES: [VirtualKeyCode; 150] = [
+    VirtualKeyCode::Key1, VirtualKeyCode::Key2, VirtualKeyCode::Key3, VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5, VirtualKeyCode::Key6, VirtualKeyCode::Key7, VirtualKeyCode::Key8,
+    VirtualKeyCode::Key9, VirtualKeyCode::Key0, VirtualKeyCode::A, VirtualKeyCode::B,
+    VirtualKeyCode::C, VirtualKeyCode::D, VirtualKeyCode::E, VirtualKeyCode::F, VirtualKeyCode::G,
+    VirtualKeyCode::H, VirtualKeyCode::I, VirtualKeyCode::J, VirtualKeyCode::K, VirtualKeyCode::L,
+    VirtualKeyCode::M, VirtualKeyCode::N, VirtualKeyCode::O, VirtualKeyCode::P, VirtualKeyCode::Q,
+    VirtualKeyCode::R, VirtualKeyCode::S, VirtualKeyCode::T, VirtualKeyCode::U, VirtualKeyCode::V,
+    VirtualKeyCode::W, VirtualKeyCode::X, VirtualKeyCode::Y, VirtualKeyCode::Z,
+    VirtualKeyCode::Escape, VirtualKeyCode::F1, VirtualKeyCode::F2, VirtualKeyCode::F3,
+    VirtualKeyCode::F4, VirtualKeyCode::F5, VirtualKeyCode::F6, VirtualKeyCode::F7,
+    VirtualKeyCode::F8, VirtualKeyCode::F9, VirtualKeyCode::F10, VirtualKeyCode::F11,
+    VirtualKeyCode::F12, VirtualKeyCode::F13, VirtualKeyCode::F14, VirtualKeyCode::F15,
+    VirtualKeyCode::Snapshot, VirtualKeyCode::Scroll, VirtualKeyCode::Pause,
+    VirtualKeyCode::Insert, VirtualKeyCode::Home, VirtualKeyCode::Delete, VirtualKeyCode::End,
+    VirtualKeyCode::PageDown, VirtualKeyCode::PageUp, VirtualKeyCode::Left, VirtualKeyCode::Up,
+    VirtualKeyCode::Right, VirtualKeyCode::Down, VirtualKeyCode::Back, VirtualKeyCode::Return,
+    VirtualKeyCode::Space, VirtualKeyCode::Compose, VirtualKeyCode::Numlock,
+    VirtualKeyCode::Numpad0, VirtualKeyCode::Numpad1, VirtualKeyCode::Numpad2,
+    VirtualKeyCode::Numpad3, VirtualKeyCode::Numpad4, VirtualKeyCode::Numpad5,
+    VirtualKeyCode::Numpad6, VirtualKeyCode::Numpad7, VirtualKeyCode::Numpad8,
+    VirtualKeyCode::Numpad9, VirtualKeyCode::AbntC1, VirtualKeyCode::AbntC2, VirtualKeyCode::Add,
+    VirtualKeyCode::Apostrophe, VirtualKeyCode::Apps, VirtualKeyCode::At, VirtualKeyCode::Ax,
+    VirtualKeyCode::Backslash, VirtualKeyCode::Calculator, VirtualKeyCode::Capital,
+    VirtualKeyCode::Colon, VirtualKeyCode::Comma, VirtualKeyCode::Convert, VirtualKeyCode::Decimal,
+    VirtualKeyCode::Divide, VirtualKeyCode::Equals, VirtualKeyCode::Grave, VirtualKeyCode::Kana,
+    VirtualKeyCode::Kanji, VirtualKeyCode::LAlt, VirtualKeyCode::LBracket,
+    VirtualKeyCode::LControl, VirtualKeyCode::LMenu, VirtualKeyCode::LShift, VirtualKeyCode::LWin,
+    VirtualKeyCode::Mail, VirtualKeyCode::MediaSelect, VirtualKeyCode::MediaStop,
+    VirtualKeyCode::Minus, VirtualKeyCode::Multiply, VirtualKeyCode::Mute,
+    VirtualKeyCode::MyComputer, VirtualKeyCode::NavigateForward, VirtualKeyCode::NavigateBackward,
+    VirtualKeyCode::NextTrack, VirtualKeyCode::NoConvert, VirtualKeyCode::NumpadComma,
+    VirtualKeyCode::NumpadEnter, VirtualKeyCode::NumpadEquals, VirtualKeyCode::OEM102,
+    VirtualKeyCode::Period, VirtualKeyCode::PlayPause, VirtualKeyCode::Power,
+    VirtualKeyCode::PrevTrack, VirtualKeyCode::RAlt, VirtualKeyCode::RBracket,
+    VirtualKeyCode::RControl, VirtualKeyCode::RMenu, VirtualKeyCode::RShift, VirtualKeyCode::RWin,
+    VirtualKeyCode::Semicolon, VirtualKeyCode::Slash, VirtualKeyCode::Sleep, VirtualKeyCode::Stop,
+    VirtualKeyCode::Subtract, VirtualKeyCode::Sysrq, VirtualKeyCode::Tab,
+    VirtualKeyCode::Underline, VirtualKeyCode::Unlabeled, VirtualKeyCode::VolumeDown,
+    VirtualKeyCode::VolumeUp, VirtualKeyCode::Wake, VirtualKeyCode::WebBack,
+    VirtualKeyCode::WebFavorites, VirtualKeyCode::WebForward, VirtualKeyCode::WebHome,
+    VirtualKeyCode::WebRefresh, VirtualKeyCode::WebSearch, VirtualKeyCode::WebStop,
+    VirtualKeyCode::Yen,
+];
+
+/// Find a `VirtualKeyCode` from it's discriminant, the inverse of `key as u32`
+///
+pub fn virtual_key_code_from_index(index: u32) -> Option<VirtualKeyCode> {
+    VIRTUAL_KEY_CODES.get(index as usize).cloned()
+}
+
+/// The mouse button numbering used across the FFI, the inverse of `mouse_button_from_index`
+///
+pub fn mouse_button_index(button: MouseButton) -> i32 {
+    match button {
+        MouseButton::Left => 0,
+        MouseButton::Right => 1,
+        MouseButton::Middle => 2,
+        MouseButton::Other(n) => n as i32,
+    }
+}
+
+pub fn touch_phase_index(phase: TouchPhase) -> i32 {
+    match phase {
+        TouchPhase::Started => 0,
+        TouchPhase::Moved => 1,
+        TouchPhase::Ended => 2,
+        TouchPhase::Cancelled => 3,
+    }
+}
+
+pub fn touch_phase_from_index(index: i32) -> TouchPhase {
+    match index {
+        0 => TouchPhase::Started,
+        1 => TouchPhase::Moved,
+        2 => TouchPhase::Ended,
+        _ => TouchPhase::Cancelled,
+    }
+}
+
+impl EventData {
+    fn new(kind: EventKind) -> EventData {
+        EventData {
+            kind: kind,
+            x: 0,
+            y: 0,
+            fx: 0.0,
+            fy: 0.0,
+            pressed: false,
+            keycode: -1,
+            scancode: 0,
+            button: 0,
+            phase: 0,
+            index: 0,
+            character: 0,
+            id: 0,
+        }
+    }
+
+    /// Flatten an `Event`, `dropped_index` is the number of `DroppedFile` events before it
+    ///
+    pub fn from_event(event: &Event, dropped_index: i32) -> EventData {
+        match event {
+            &Event::Resized(w, h) => EventData { x: w as i32, y: h as i32, .. EventData::new(EventKind::EVENT_RESIZED) },
+            &Event::Moved(x, y) => EventData { x: x, y: y, .. EventData::new(EventKind::EVENT_MOVED) },
+            &Event::Closed => EventData::new(EventKind::EVENT_CLOSED),
+            &Event::DroppedFile(_) => EventData { index: dropped_index, .. EventData::new(EventKind::EVENT_DROPPED_FILE) },
+            &Event::ReceivedCharacter(c) => EventData { character: c as u32, .. EventData::new(EventKind::EVENT_RECEIVED_CHARACTER) },
+            &Event::Focused(focused) => EventData { pressed: focused, .. EventData::new(EventKind::EVENT_FOCUSED) },
+            &Event::KeyboardInput(state, scancode, keycode) => {
+                EventData {
+                    pressed: state == ElementState::Pressed,
+                    scancode: scancode as i32,
+                    keycode: keycode.and_then(keycode_index).unwrap_or(-1),
+                    .. EventData::new(EventKind::EVENT_KEYBOARD_INPUT)
+                }
+            },
+            &Event::MouseMoved(x, y) => EventData { x: x, y: y, .. EventData::new(EventKind::EVENT_MOUSE_MOVED) },
+            &Event::MouseEntered => EventData::new(EventKind::EVENT_MOUSE_ENTERED),
+            &Event::MouseLeft => EventData::new(EventKind::EVENT_MOUSE_LEFT),
+            &Event::MouseWheel(delta, phase) => {
+                let (fx, fy, pixels) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (x, y, false),
+                    MouseScrollDelta::PixelDelta(x, y) => (x, y, true),
+                };
+                EventData {
+                    fx: fx as f64,
+                    fy: fy as f64,
+                    pressed: pixels,
+                    phase: touch_phase_index(phase),
+                    .. EventData::new(EventKind::EVENT_MOUSE_WHEEL)
+                }
+            },
+            &Event::MouseInput(state, button) => {
+                EventData {
+                    pressed: state == ElementState::Pressed,
+                    button: mouse_button_index(button),
+                    .. EventData::new(EventKind::EVENT_MOUSE_INPUT)
+                }
+            },
+            &Event::TouchpadPressure(pressure, stage) => {
+                EventData { fx: pressure as f64, id: stage as i64, .. EventData::new(EventKind::EVENT_TOUCHPAD_PRESSURE) }
+            },
+            &Event::Awakened => EventData::new(EventKind::EVENT_AWAKENED),
+            &Event::Refresh => EventData::new(EventKind::EVENT_REFRESH),
+            &Event::Suspended(suspended) => EventData { pressed: suspended, .. EventData::new(EventKind::EVENT_SUSPENDED) },
+            &Event::Touch(ref touch) => {
+                EventData {
+                    x: touch.location.0 as i32,
+                    y: touch.location.1 as i32,
+                    fx: touch.location.0,
+                    fy: touch.location.1,
+                    phase: touch_phase_index(touch.phase),
+                    id: touch.id as i64,
+                    .. EventData::new(EventKind::EVENT_TOUCH)
+                }
+            },
+        }
+    }
+}
+
 impl EventBuffer {
     pub fn closed(&self) -> bool {
        self.0.iter().any(|e| match e {
@@ -202,11 +600,36 @@ impl EventBuffer {
     pub fn empty(&self) -> bool {
         0 == self.0.len()
     }
+
+    /// The events polled from the display
+    ///
+    pub fn events(&self) -> &[Event] {
+        self.0.as_slice()
+    }
+
+    /// The most recent window size if the window was resized
+    ///
+    pub fn resized(&self) -> Option<(u32, u32)> {
+        let mut size: Option<(u32, u32)> = None;
+        for e in self.0.as_slice() {
+            match e {
+                &Event::Resized(w, h) => { size = Some((w, h)); },
+                _ => (),
+            }
+        }
+        size
+    }
     
     pub fn new(display: &GlutinFacade) -> EventBuffer {
         let events: Vec<Event> = display.poll_events().collect();
         EventBuffer(events)
     }
+
+    /// Create an `EventBuffer` from events which did not come from a display, such as a replay
+    ///
+    pub fn from_events(events: Vec<Event>) -> EventBuffer {
+        EventBuffer(events)
+    }
     
     pub fn key_pressed(&self, keycode: u8) -> bool {
        self.0.iter().any(|e| match e {
@@ -231,7 +654,7 @@ impl EventBuffer {
     
     pub fn released(&self, keycode: VirtualKeyCode) -> bool {
        self.0.iter().any(|e| match e {
-           &Event::KeyboardInput(ElementState::Pressed, _, virtual_keycode) => (virtual_keycode == Some(keycode)),
+           &Event::KeyboardInput(ElementState::Released, _, virtual_keycode) => (virtual_keycode == Some(keycode)),
             _ => false,
        })
     }
@@ -257,7 +680,7 @@ impl EventBuffer {
     
     pub fn mouse_pressed_right(&self) -> bool {
         self.0.iter().any(|e| match e {
-           &Event::MouseInput(ElementState::Pressed, MouseButton::Left) => true,
+           &Event::MouseInput(ElementState::Pressed, MouseButton::Right) => true,
             _ => false,
        })
     }
@@ -271,19 +694,266 @@ impl EventBuffer {
     
     pub fn mouse_released_right(&self) -> bool {
         self.0.iter().any(|e| match e {
-           &Event::MouseInput(ElementState::Released, MouseButton::Left) => true,
+           &Event::MouseInput(ElementState::Released, MouseButton::Right) => true,
             _ => false,
        })
     }
+
+    /// The most recent focus change
+    ///
+    pub fn focused(&self) -> Option<bool> {
+        let mut focused: Option<bool> = None;
+        for e in self.0.as_slice() {
+            match e {
+                &Event::Focused(f) => { focused = Some(f); },
+                _ => (),
+            }
+        }
+        focused
+    }
+
+    /// The most recent window position if the window was moved
+    ///
+    pub fn moved(&self) -> Option<(i32, i32)> {
+        let mut position: Option<(i32, i32)> = None;
+        for e in self.0.as_slice() {
+            match e {
+                &Event::Moved(x, y) => { position = Some((x, y)); },
+                _ => (),
+            }
+        }
+        position
+    }
+
+    /// The characters typed, in order
+    ///
+    pub fn text(&self) -> String {
+        self.0.iter().filter_map(|e| match e {
+            &Event::ReceivedCharacter(c) => Some(c),
+            _ => None,
+        }).collect()
+    }
+
+    /// The paths of the files dropped onto the window
+    ///
+    pub fn dropped_files(&self) -> Vec<PathBuf> {
+        self.0.iter().filter_map(|e| match e {
+            &Event::DroppedFile(ref path) => Some(path.clone()),
+            _ => None,
+        }).collect()
+    }
+
+    /// The sum of the mouse wheel deltas in lines
+    ///
+    pub fn scroll(&self) -> (f32, f32) {
+        self.0.iter().fold((0.0, 0.0), |(sx, sy), e| match e {
+            &Event::MouseWheel(MouseScrollDelta::LineDelta(x, y), _) => (sx + x, sy + y),
+            &Event::MouseWheel(MouseScrollDelta::PixelDelta(x, y), _) => (sx + x / PIXELS_PER_LINE, sy + y / PIXELS_PER_LINE),
+            _ => (sx, sy),
+        })
+    }
+
+    pub fn touches(&self) -> Vec<Touch> {
+        self.0.iter().filter_map(|e| match e {
+            &Event::Touch(ref touch) => Some(touch.clone()),
+            _ => None,
+        }).collect()
+    }
+
+    /// Flatten every event for iteration across the FFI
+    ///
+    pub fn event_data(&self) -> Vec<EventData> {
+        let mut dropped_index: i32 = 0;
+        self.0.iter().map(|e| {
+            let data: EventData = EventData::from_event(e, dropped_index);
+            if data.kind == EventKind::EVENT_DROPPED_FILE {
+                dropped_index += 1;
+            }
+            data
+        }).collect()
+    }
+
+    /// Flatten the event at `index` without converting the others
+    ///
+    pub fn event_data_at(&self, index: usize) -> Option<EventData> {
+        self.0.get(index).map(|event| {
+            // Only a dropped file needs to count the dropped files before it
+            let dropped_index: usize = match event {
+                &Event::DroppedFile(_) => self.0[..index].iter().filter(|e| match *e {
+                    &Event::DroppedFile(_) => true,
+                    _ => false,
+                }).count(),
+                _ => 0,
+            };
+            EventData::from_event(event, dropped_index as i32)
+        })
+    }
+}
+
+/// Convert the mouse button numbering used across the FFI (0 left, 1 right, 2 middle, others above)
+///
+pub fn mouse_button_from_index(button: libc::c_int) -> MouseButton {
+    match button {
+        0 => MouseButton::Left,
+        1 => MouseButton::Right,
+        2 => MouseButton::Middle,
+        n => MouseButton::Other(n as u8),
+    }
+}
+
+/// Input state accumulated across frames
+///
+/// Call `update` once per frame with the polled `EventBuffer`. Keys and buttons are down from
+/// the frame they were pressed until the frame they are released, pressed and released are only
+/// true on the frame the state changed (key repeat is ignored). Mouse movement and scrolling are
+/// summed over the events of the current frame.
+///
+/// ```c
+/// /* C representation */
+/// typedef void* InputState;
+/// ```
+///
+#[derive(Clone, Debug)]
+pub struct InputState {
+    keys_down: HashSet<VirtualKeyCode>,
+    keys_pressed: HashSet<VirtualKeyCode>,
+    keys_released: HashSet<VirtualKeyCode>,
+    buttons_down: HashSet<MouseButton>,
+    buttons_pressed: HashSet<MouseButton>,
+    buttons_released: HashSet<MouseButton>,
+    pub mouse_position: Option<(i32, i32)>,
+    pub mouse_delta: (i32, i32),
+    pub scroll: (f32, f32),
+    pub focused: bool,
+}
+
+impl InputState {
+    pub fn new() -> InputState {
+        InputState {
+            keys_down: HashSet::new(),
+            keys_pressed: HashSet::new(),
+            keys_released: HashSet::new(),
+            buttons_down: HashSet::new(),
+            buttons_pressed: HashSet::new(),
+            buttons_released: HashSet::new(),
+            mouse_position: None,
+            mouse_delta: (0, 0),
+            scroll: (0.0, 0.0),
+            focused: true,
+        }
+    }
+
+    /// Advance to the next frame, applying a batch of events
+    ///
+    pub fn update(&mut self, events: &EventBuffer) {
+        self.update_events(events.events());
+    }
+
+    pub fn update_events(&mut self, events: &[Event]) {
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+        self.mouse_delta = (0, 0);
+        self.scroll = (0.0, 0.0);
+        for event in events {
+            self.apply(event);
+        }
+    }
+
+    fn apply(&mut self, event: &Event) {
+        match event {
+            &Event::KeyboardInput(ElementState::Pressed, _, Some(keycode)) => {
+                if self.keys_down.insert(keycode) {
+                    self.keys_pressed.insert(keycode);
+                }
+            },
+            &Event::KeyboardInput(ElementState::Released, _, Some(keycode)) => {
+                if self.keys_down.remove(&keycode) {
+                    self.keys_released.insert(keycode);
+                }
+            },
+            &Event::MouseInput(ElementState::Pressed, button) => {
+                if self.buttons_down.insert(button) {
+                    self.buttons_pressed.insert(button);
+                }
+            },
+            &Event::MouseInput(ElementState::Released, button) => {
+                if self.buttons_down.remove(&button) {
+                    self.buttons_released.insert(button);
+                }
+            },
+            &Event::MouseMoved(x, y) => {
+                if let Some((last_x, last_y)) = self.mouse_position {
+                    self.mouse_delta.0 += x - last_x;
+                    self.mouse_delta.1 += y - last_y;
+                }
+                self.mouse_position = Some((x, y));
+            },
+            &Event::MouseLeft => {
+                // The next position is not relative to the last one seen
+                self.mouse_position = None;
+            },
+            &Event::MouseWheel(MouseScrollDelta::LineDelta(x, y), _) => {
+                self.scroll.0 += x;
+                self.scroll.1 += y;
+            },
+            &Event::MouseWheel(MouseScrollDelta::PixelDelta(x, y), _) => {
+                self.scroll.0 += x / PIXELS_PER_LINE;
+                self.scroll.1 += y / PIXELS_PER_LINE;
+            },
+            &Event::Focused(focused) => {
+                self.focused = focused;
+                if !focused {
+                    // Anything released while the window is unfocused is never reported
+                    self.release_all();
+                }
+            },
+            _ => (),
+        }
+    }
+
+    /// Release every key and button that is down
+    ///
+    pub fn release_all(&mut self) {
+        self.keys_released.extend(self.keys_down.drain());
+        self.buttons_released.extend(self.buttons_down.drain());
+    }
+
+    pub fn key_down(&self, keycode: VirtualKeyCode) -> bool {
+        self.keys_down.contains(&keycode)
+    }
+
+    pub fn key_pressed(&self, keycode: VirtualKeyCode) -> bool {
+        self.keys_pressed.contains(&keycode)
+    }
+
+    pub fn key_released(&self, keycode: VirtualKeyCode) -> bool {
+        self.keys_released.contains(&keycode)
+    }
+
+    pub fn button_down(&self, button: MouseButton) -> bool {
+        self.buttons_down.contains(&button)
+    }
+
+    pub fn button_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+
+    pub fn button_released(&self, button: MouseButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
 }
 
 /// Check for user close event
 ///
-/// `extern bool display_closed(void* events);`
+/// `extern bool display_closed(EventBuffer buffer);`
 ///
 #[no_mangle]
 pub extern "C" fn display_closed(buffer: &EventBuffer) -> bool {
-    buffer.closed()
+    ffi::guard_or(false, || {
+        buffer.closed()
+    })
 }
 
 /// Poll the next batch of events
@@ -291,109 +961,413 @@ pub extern "C" fn display_closed(buffer: &EventBuffer) -> bool {
 /// `extern EventBuffer get_events(Display display);`
 ///
 #[no_mangle]
-pub extern "C" fn get_events(display: &GlutinFacade) -> Box<EventBuffer> {
-    Box::new(EventBuffer::new(display))
+pub extern "C" fn get_events(display: &GlutinFacade) -> Option<Box<EventBuffer>> {
+    ffi::guard(|| {
+        Box::new(EventBuffer::new(display))
+    })
 }
 
 /// Checks if the event buffer is empty
 ///
-/// `extern EventBuffer events_empty(EventBuffer display);`
+/// `extern bool events_empty(EventBuffer buffer);`
 ///
 #[no_mangle]
 pub extern "C" fn events_empty(buffer: &EventBuffer) -> bool {
-    buffer.empty()
+    ffi::guard_or(false, || {
+        buffer.empty()
+    })
+}
+
+/// Check if the window was resized, the new size is written to width and height
+///
+/// `extern bool events_resized(EventBuffer buffer, int* width, int* height);`
+///
+#[no_mangle]
+pub extern "C" fn events_resized(buffer: &EventBuffer, width: *mut libc::int32_t, height: *mut libc::int32_t) -> bool {
+    ffi::guard_or(false, || {
+        match buffer.resized() {
+            Some((w, h)) => {
+                unsafe {
+                    if !width.is_null() { *width = w as libc::int32_t; }
+                    if !height.is_null() { *height = h as libc::int32_t; }
+                }
+                true
+            },
+            None => false,
+        }
+    })
+}
+
+/// Get the number of events in the buffer
+///
+/// `extern int events_count(EventBuffer buffer);`
+///
+#[no_mangle]
+pub extern "C" fn events_count(buffer: &EventBuffer) -> libc::c_int {
+    ffi::guard_or(-1, || {
+        buffer.events().len() as libc::c_int
+    })
+}
+
+/// Copy the event at index into data, returns false if the index is out of range
+///
+/// `extern bool events_get(EventBuffer buffer, int index, EventData* data);`
+///
+#[no_mangle]
+pub extern "C" fn events_get(buffer: &EventBuffer, index: libc::c_int, data: *mut EventData) -> bool {
+    ffi::guard_or(false, || {
+        if index < 0 || data.is_null() {
+            return false;
+        }
+        match buffer.event_data_at(index as usize) {
+            Some(event) => {
+                unsafe { *data = event };
+                true
+            },
+            None => false,
+        }
+    })
+}
+
+/// Check if the window gained or lost focus, the new state is written to focused
+///
+/// `extern bool events_focused(EventBuffer buffer, bool* focused);`
+///
+#[no_mangle]
+pub extern "C" fn events_focused(buffer: &EventBuffer, focused: *mut bool) -> bool {
+    ffi::guard_or(false, || {
+        match buffer.focused() {
+            Some(f) => {
+                if !focused.is_null() {
+                    unsafe { *focused = f };
+                }
+                true
+            },
+            None => false,
+        }
+    })
+}
+
+/// Get the scrolling in lines
+///
+/// `extern bool events_scroll(EventBuffer buffer, float* x, float* y);`
+///
+#[no_mangle]
+pub extern "C" fn events_scroll(buffer: &EventBuffer, x: *mut libc::c_float, y: *mut libc::c_float) -> bool {
+    ffi::guard_status(|| {
+        let (sx, sy) = buffer.scroll();
+        unsafe {
+            if !x.is_null() { *x = sx as libc::c_float; }
+            if !y.is_null() { *y = sy as libc::c_float; }
+        }
+    })
+}
+
+/// Get the text typed, as UTF-8, the string must be freed with `free_string`
+///
+/// `extern char* events_text(EventBuffer buffer);`
+///
+#[no_mangle]
+pub extern "C" fn events_text(buffer: &EventBuffer) -> *mut libc::c_char {
+    ffi::guard_or(std::ptr::null_mut(), || {
+        // A typed null character can not be represented in a C string
+        match CString::new(buffer.text().replace('\0', "")) {
+            Ok(s) => s.into_raw(),
+            Err(e) => panic!("Unable to read text input: {:?}", e),
+        }
+    })
+}
+
+/// `extern int events_dropped_file_count(EventBuffer buffer);`
+///
+#[no_mangle]
+pub extern "C" fn events_dropped_file_count(buffer: &EventBuffer) -> libc::c_int {
+    ffi::guard_or(-1, || {
+        buffer.dropped_files().len() as libc::c_int
+    })
+}
+
+/// Get the path of a dropped file, the string must be freed with `free_string`
+///
+/// Returns null if the index is out of range.
+///
+/// `extern char* events_dropped_file(EventBuffer buffer, int index);`
+///
+#[no_mangle]
+pub extern "C" fn events_dropped_file(buffer: &EventBuffer, index: libc::c_int) -> *mut libc::c_char {
+    ffi::guard_or(std::ptr::null_mut(), || {
+        if index < 0 {
+            return std::ptr::null_mut();
+        }
+        match buffer.dropped_files().get(index as usize) {
+            Some(path) => {
+                match CString::new(path.to_string_lossy().into_owned()) {
+                    Ok(s) => s.into_raw(),
+                    Err(_) => std::ptr::null_mut(),
+                }
+            },
+            None => std::ptr::null_mut(),
+        }
+    })
+}
+
+/// Free a string returned by quick3d
+///
+/// `extern void free_string(char* ptr);`
+///
+#[no_mangle]
+pub extern "C" fn free_string(ptr: *mut libc::c_char) {
+    ffi::guard_status(|| {
+        if !ptr.is_null() {
+            drop(unsafe { CString::from_raw(ptr) });
+        }
+    });
 }
 
 /// Free an event buffer
 ///
-/// `extern void free_events(EventBuffer queue);`
+/// `extern void free_events(EventBuffer ptr);`
 ///
 #[no_mangle]
 pub extern "C" fn free_events(ptr: *mut EventBuffer) {
-    let box_ptr: Box<EventBuffer> = unsafe { Box::from_raw(ptr) };
-    drop(box_ptr);
+    ffi::guard_status(|| {
+        let box_ptr: Box<EventBuffer> = unsafe { Box::from_raw(ptr) };
+        drop(box_ptr);
+    });
 }
 
 /// Free a mouse struct
 ///
-/// `extern void free_mouse(Mouse* mouse);`
+/// `extern void free_mouse(Mouse* ptr);`
 ///
 #[no_mangle]
 pub extern "C" fn free_mouse(ptr: *mut Mouse) {
-    let box_ptr: Box<Mouse> = unsafe { Box::from_raw(ptr) };
-    drop(box_ptr);
+    ffi::guard_status(|| {
+        let box_ptr: Box<Mouse> = unsafe { Box::from_raw(ptr) };
+        drop(box_ptr);
+    });
 }
 
 /// Print an event queue
 ///
-/// `extern void print_events(EventBuffer queue);`
+/// `extern bool print_events(EventBuffer ptr);`
 ///
 #[no_mangle]
-pub extern "C" fn print_events(ptr: &EventBuffer) {
-    println!("{:?}", ptr);
+pub extern "C" fn print_events(ptr: &EventBuffer) -> bool {
+    ffi::guard_status(|| {
+        println!("{:?}", ptr);
+    })
 }
 
 /// Check for user key press input events
 ///
-/// `extern bool key_pressed(void* events, int keycode);`
+/// `extern bool key_pressed(EventBuffer buffer, KeyCode keycode);`
 ///
 #[no_mangle]
 pub extern "C" fn key_pressed(buffer: &EventBuffer, keycode: KeyCode) -> bool {
-    buffer.pressed(keycode.get_enum())
+    ffi::guard_or(false, || {
+        buffer.pressed(keycode.get_enum())
+    })
 }
 
 /// Check for user key release input events
 ///
-/// `extern bool key_released(void* events, int keycode);`
+/// `extern bool key_released(EventBuffer buffer, KeyCode keycode);`
 ///
 #[no_mangle]
 pub extern "C" fn key_released(buffer: &EventBuffer, keycode: KeyCode) -> bool {
-   buffer.released(keycode.get_enum())
+    ffi::guard_or(false, || {
+       buffer.released(keycode.get_enum())
+    })
 }
 
 /// Check if the mouse has moved
 ///
-/// `extern Mouse* mouse_moved(void* events);`
+/// `extern Mouse* mouse_moved(EventBuffer buffer);`
 ///
 #[no_mangle]
-pub extern "C" fn mouse_moved(buffer: &EventBuffer) -> Box<Mouse> {
-   Box::new(buffer.mouse_moved())
+pub extern "C" fn mouse_moved(buffer: &EventBuffer) -> Option<Box<Mouse>> {
+    ffi::guard(|| {
+       Box::new(buffer.mouse_moved())
+    })
 }
 
 /// Check for mouse left button was pressed
 ///
-/// `extern bool mouse_pressed_left(void* events, int keycode);`
+/// `extern bool mouse_pressed_left(EventBuffer buffer);`
 ///
 #[no_mangle]
 pub extern "C" fn mouse_pressed_left(buffer: &EventBuffer) -> bool {
-    buffer.mouse_pressed_left()
+    ffi::guard_or(false, || {
+        buffer.mouse_pressed_left()
+    })
 }
 
 /// Check for mouse right button was pressed
 ///
-/// `extern bool mouse_pressed_right(void* events, int keycode);`
+/// `extern bool mouse_pressed_right(EventBuffer buffer);`
 ///
 #[no_mangle]
 pub extern "C" fn mouse_pressed_right(buffer: &EventBuffer) -> bool {
-    buffer.mouse_pressed_right()
+    ffi::guard_or(false, || {
+        buffer.mouse_pressed_right()
+    })
 }
 
 /// Check for mouse left button was released
 ///
-/// `extern bool mouse_released_left(void* events, int keycode);`
+/// `extern bool mouse_released_left(EventBuffer buffer);`
 ///
 #[no_mangle]
 pub extern "C" fn mouse_released_left(buffer: &EventBuffer) -> bool {
-    buffer.mouse_released_left()
+    ffi::guard_or(false, || {
+        buffer.mouse_released_left()
+    })
 }
 
 /// Check for mouse right button was released
 ///
-/// `extern bool mouse_released_right(void* events, int keycode);`
+/// `extern bool mouse_released_right(EventBuffer buffer);`
 ///
 #[no_mangle]
 pub extern "C" fn mouse_released_right(buffer: &EventBuffer) -> bool {
-    buffer.mouse_released_right()
+    ffi::guard_or(false, || {
+        buffer.mouse_released_right()
+    })
+}
+
+/// `extern InputState create_input_state();`
+///
+#[no_mangle]
+pub extern "C" fn create_input_state() -> Option<Box<InputState>> {
+    ffi::guard(|| {
+        Box::new(InputState::new())
+    })
+}
+
+/// `extern void free_input_state(InputState ptr);`
+///
+#[no_mangle]
+pub extern "C" fn free_input_state(ptr: *mut InputState) {
+    ffi::guard_status(|| {
+        let box_ptr: Box<InputState> = unsafe { Box::from_raw(ptr) };
+        drop(box_ptr);
+    });
+}
+
+/// Advance the input state to the next frame
+///
+/// `extern bool input_state_update(InputState state, EventBuffer buffer);`
+///
+#[no_mangle]
+pub extern "C" fn input_state_update(state: &mut InputState, buffer: &EventBuffer) -> bool {
+    ffi::guard_status(|| {
+        state.update(buffer);
+    })
+}
+
+/// `extern bool input_key_down(InputState state, KeyCode keycode);`
+///
+#[no_mangle]
+pub extern "C" fn input_key_down(state: &InputState, keycode: KeyCode) -> bool {
+    ffi::guard_or(false, || {
+        state.key_down(keycode.get_enum())
+    })
+}
+
+/// `extern bool input_key_pressed(InputState state, KeyCode keycode);`
+///
+#[no_mangle]
+pub extern "C" fn input_key_pressed(state: &InputState, keycode: KeyCode) -> bool {
+    ffi::guard_or(false, || {
+        state.key_pressed(keycode.get_enum())
+    })
+}
+
+/// `extern bool input_key_released(InputState state, KeyCode keycode);`
+///
+#[no_mangle]
+pub extern "C" fn input_key_released(state: &InputState, keycode: KeyCode) -> bool {
+    ffi::guard_or(false, || {
+        state.key_released(keycode.get_enum())
+    })
+}
+
+/// Buttons are numbered 0 (left), 1 (right), 2 (middle) and above for other buttons
+///
+/// `extern bool input_button_down(InputState state, int button);`
+///
+#[no_mangle]
+pub extern "C" fn input_button_down(state: &InputState, button: libc::c_int) -> bool {
+    ffi::guard_or(false, || {
+        state.button_down(mouse_button_from_index(button))
+    })
+}
+
+/// `extern bool input_button_pressed(InputState state, int button);`
+///
+#[no_mangle]
+pub extern "C" fn input_button_pressed(state: &InputState, button: libc::c_int) -> bool {
+    ffi::guard_or(false, || {
+        state.button_pressed(mouse_button_from_index(button))
+    })
+}
+
+/// `extern bool input_button_released(InputState state, int button);`
+///
+#[no_mangle]
+pub extern "C" fn input_button_released(state: &InputState, button: libc::c_int) -> bool {
+    ffi::guard_or(false, || {
+        state.button_released(mouse_button_from_index(button))
+    })
+}
+
+/// Get the last known mouse position, returns false if the mouse position is unknown
+///
+/// `extern bool input_mouse_position(InputState state, int* x, int* y);`
+///
+#[no_mangle]
+pub extern "C" fn input_mouse_position(state: &InputState, x: *mut libc::int32_t, y: *mut libc::int32_t) -> bool {
+    ffi::guard_or(false, || {
+        match state.mouse_position {
+            Some((mx, my)) => {
+                unsafe {
+                    if !x.is_null() { *x = mx as libc::int32_t; }
+                    if !y.is_null() { *y = my as libc::int32_t; }
+                }
+                true
+            },
+            None => false,
+        }
+    })
+}
+
+/// Get the mouse movement of the current frame in pixels
+///
+/// `extern bool input_mouse_delta(InputState state, int* dx, int* dy);`
+///
+#[no_mangle]
+pub extern "C" fn input_mouse_delta(state: &InputState, dx: *mut libc::int32_t, dy: *mut libc::int32_t) -> bool {
+    ffi::guard_status(|| {
+        unsafe {
+            if !dx.is_null() { *dx = state.mouse_delta.0 as libc::int32_t; }
+            if !dy.is_null() { *dy = state.mouse_delta.1 as libc::int32_t; }
+        }
+    })
+}
+
+/// Get the scrolling of the current frame in lines
+///
+/// `extern bool input_scroll(InputState state, float* x, float* y);`
+///
+#[no_mangle]
+pub extern "C" fn input_scroll(state: &InputState, x: *mut libc::c_float, y: *mut libc::c_float) -> bool {
+    ffi::guard_status(|| {
+        unsafe {
+            if !x.is_null() { *x = state.scroll.0 as libc::c_float; }
+            if !y.is_null() { *y = state.scroll.1 as libc::c_float; }
+        }
+    })
 }
 
 /// Structure for querying stdin console input
@@ -409,84 +1383,96 @@ pub struct ConsoleInput {
     pub finished: Arc<Mutex<bool>>,
 }
 
+impl ConsoleInput {
+    /// Take the lines read since the last call
+    ///
+    pub fn read(&self) -> String {
+        let arc = self.buffer.clone();
+        let mut mutex = arc.lock().expect("Unable to aquire console input lock");
+        std::mem::replace(&mut *mutex, String::new())
+    }
+}
+
 /// `extern ConsoleInput create_console_reader();`
 ///
 #[no_mangle]
-pub extern "C" fn create_console_reader() -> Box<ConsoleInput> {
-    use std::thread;
-    let buffer_arc: Arc<Mutex<String>> = Arc::new(Mutex::new(String::new()));
-    let buffer_arc_copy = buffer_arc.clone();
-    let finished_arc: Arc<Mutex<bool>> = Arc::new(Mutex::new(false));
-    let finished_arc_copy = finished_arc.clone();
-    {
-        // Initialize the finished value
-        let finished = finished_arc.clone();
-        let mut finished_lock = finished.lock().expect("Unable to aquire lock for console reader");
-        *finished_lock = false;
-    }
+pub extern "C" fn create_console_reader() -> Option<Box<ConsoleInput>> {
+    ffi::guard(|| {
+        use std::thread;
+        let buffer_arc: Arc<Mutex<String>> = Arc::new(Mutex::new(String::new()));
+        let buffer_arc_copy = buffer_arc.clone();
+        let finished_arc: Arc<Mutex<bool>> = Arc::new(Mutex::new(false));
+        let finished_arc_copy = finished_arc.clone();
+        {
+            // Initialize the finished value
+            let finished = finished_arc.clone();
+            let mut finished_lock = finished.lock().expect("Unable to aquire lock for console reader");
+            *finished_lock = false;
+        }
     
-    let child = thread::spawn(move || {
-        println!("Enter command, or return to close console");
-        'console: loop {
-            let mut buffer = String::new();
-            match std::io::stdin().read_line(&mut buffer) {
-                Ok(_) => {
-                    buffer = buffer
-                        .replace("\r", "")
-                        .replace("\n", " ");
-                    if 1 == buffer.len() { break 'console };
-                    let arc = buffer_arc.clone();
-                    let mut writer = arc.lock().expect("Unable to aquire console buffer write lock");
-                    let mut new_string: String = (*writer).clone();
-                    new_string.push_str(&buffer);
-                    *writer = new_string;
-                    std::thread::yield_now();
-                },
-                Err(e) => println!("Error: {:?}", e),
+        let child = thread::spawn(move || {
+            println!("Enter command, or return to close console");
+            'console: loop {
+                let mut buffer = String::new();
+                match std::io::stdin().read_line(&mut buffer) {
+                    Ok(_) => {
+                        // Lines are kept separate so a `Console` can run them one at a time
+                        buffer = buffer.replace("\r", "");
+                        if 1 == buffer.len() { break 'console };
+                        let arc = buffer_arc.clone();
+                        let mut writer = arc.lock().expect("Unable to aquire console buffer write lock");
+                        let mut new_string: String = (*writer).clone();
+                        new_string.push_str(&buffer);
+                        *writer = new_string;
+                        std::thread::yield_now();
+                    },
+                    Err(e) => println!("Error: {:?}", e),
+                }
             }
-        }
-        println!("Console closed");
-        let finished = finished_arc.clone();
-        let mut finished_lock = finished.lock().expect("Unable to aquire console buffer lock");
-        *finished_lock = true;
-        return 0;
-    });
+            println!("Console closed");
+            let finished = finished_arc.clone();
+            let mut finished_lock = finished.lock().expect("Unable to aquire console buffer lock");
+            *finished_lock = true;
+            return 0;
+        });
     
-    std::thread::yield_now();
-    Box::new(ConsoleInput{thread_handle: child, buffer: buffer_arc_copy, finished: finished_arc_copy})
+        std::thread::yield_now();
+        Box::new(ConsoleInput{thread_handle: child, buffer: buffer_arc_copy, finished: finished_arc_copy})
+    })
 }
 
 /// `extern bool console_is_closed(ConsoleInput console);`
 ///
 #[no_mangle]
 pub extern "C" fn console_is_closed(console: &ConsoleInput) -> bool {
-    let arc = console.finished.clone();
-    let mutex = arc.lock().expect("Unable to aquire console input lock");
-    mutex.clone()
+    ffi::guard_or(false, || {
+        let arc = console.finished.clone();
+        let mutex = arc.lock().expect("Unable to aquire console input lock");
+        mutex.clone()
+    })
 }
 
 /// `extern char* read_console_buffer(ConsoleInput console);`
 ///
+/// The string must be freed with `free_string`.
+///
 #[no_mangle]
 pub extern "C" fn read_console_buffer(console: &ConsoleInput) -> *mut libc::c_char {
-    let retval: String;
-    let arc = console.buffer.clone();
-    let mut mutex = arc.lock().expect("Unable to aquire console input lock");
-    retval = (*mutex).clone();
-    *mutex = String::new();
-    match CString::new(retval) {
-        Ok(s) => s.into_raw(),
-        Err(e) => panic!("Unable to read console buffer: {:?}", e),
-    }
+    ffi::guard_result_or(std::ptr::null_mut(), || {
+        let s: CString = try!(CString::new(console.read()).map_err(io::Error::from));
+        Ok(s.into_raw())
+    })
 }
 
-/// `extern void wait_console_quit(ConsoleInput console);`
+/// `extern void wait_console_quit(ConsoleInput handle);`
 ///
 #[no_mangle]
 pub extern "C" fn wait_console_quit(handle: *mut ConsoleInput) {
-    let child: Box<ConsoleInput> = unsafe { Box::from_raw(handle) };
-    match child.thread_handle.join() {
-        Ok(c) => drop(c),
-        Err(e) => println!("Console thread did not return: {:?}", e),
-    }
+    ffi::guard_status(|| {
+        let child: Box<ConsoleInput> = unsafe { Box::from_raw(handle) };
+        match child.thread_handle.join() {
+            Ok(c) => drop(c),
+            Err(e) => println!("Console thread did not return: {:?}", e),
+        }
+    });
 }
@@ -0,0 +1,376 @@
+// Copyright (C) 2016 Chris Liebert
+
+extern crate libc;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use glium::glutin::{Event, MouseButton, VirtualKeyCode};
+
+use error::Error;
+use input::{EventBuffer, InputState, KeyCode, mouse_button_from_index};
+use ffi;
+
+/// A physical input which can trigger an action
+///
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Binding {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+}
+
+/// A physical input producing a continuous value
+///
+/// `Keys` is -1, 0 or 1 depending on which of the negative and positive keys are held.
+/// Mouse axes are the movement (in pixels) or vertical wheel lines of the current frame.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AxisBinding {
+    Keys(VirtualKeyCode, VirtualKeyCode),
+    MouseX,
+    MouseY,
+    MouseWheel,
+}
+
+#[derive(Debug)]
+pub enum InputMapError {
+    IoError(::std::io::Error),
+    ParseError(usize, String),
+}
+
+/// Named actions and axes bound to keys, mouse buttons and mouse axes
+///
+/// Call `update` once per frame with the polled `EventBuffer`, then query the actions by
+/// name. The bindings are looked up in an `InputState`, so an action is pressed or released
+/// only on the frame one of it's bindings changed and is held while any binding is down.
+///
+/// Bindings can be loaded from a text file with one binding per line, `#` starts a comment.
+/// Key names are those of the C `KeyCode` enum and mouse buttons are `MOUSE_LEFT`,
+/// `MOUSE_RIGHT` and `MOUSE_MIDDLE`:
+///
+/// ```text
+/// action jump SPACE
+/// action aim MOUSE_LEFT
+/// axis move_forward S W
+/// axis look_x MOUSE_X 0.01
+/// axis zoom MOUSE_WHEEL
+/// ```
+///
+/// ```c
+/// /* C representation */
+/// typedef void* InputMap;
+/// ```
+///
+#[derive(Clone, Debug)]
+pub struct InputMap {
+    pub actions: HashMap<String, Vec<Binding>>,
+    pub axes: HashMap<String, Vec<(AxisBinding, f32)>>,
+    pub state: InputState,
+}
+
+/// Parse a key or mouse button name
+///
+fn parse_binding(name: &str) -> Option<Binding> {
+    match name.to_uppercase().as_str() {
+        "MOUSE_LEFT" => Some(Binding::Mouse(MouseButton::Left)),
+        "MOUSE_RIGHT" => Some(Binding::Mouse(MouseButton::Right)),
+        "MOUSE_MIDDLE" => Some(Binding::Mouse(MouseButton::Middle)),
+        _ => KeyCode::from_name(name).map(|k| Binding::Key(k.get_enum())),
+    }
+}
+
+impl InputMap {
+    pub fn new() -> InputMap {
+        InputMap {
+            actions: HashMap::new(),
+            axes: HashMap::new(),
+            state: InputState::new(),
+        }
+    }
+
+    /// Load bindings from a configuration file
+    ///
+    pub fn from_file(filename: &str) -> Result<InputMap, Error> {
+        let file: File = try!(File::open(filename).map_err(InputMapError::IoError));
+        let mut map: InputMap = InputMap::new();
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line: String = try!(line.map_err(InputMapError::IoError));
+            try!(map.parse_line(&line).map_err(|e| InputMapError::ParseError(i + 1, e)));
+        }
+        Ok(map)
+    }
+
+    /// Add the binding described by a single line of a configuration file
+    ///
+    pub fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        let line: &str = match line.find('#') {
+            Some(i) => &line[..i],
+            None => line,
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words.is_empty() {
+            return Ok(());
+        }
+        match (words[0], words.len()) {
+            ("action", 3) => {
+                match parse_binding(words[2]) {
+                    Some(binding) => {
+                        self.bind_action(words[1], binding);
+                        Ok(())
+                    },
+                    None => Err(format!("Unknown key or button {}", words[2])),
+                }
+            },
+            ("axis", 3) | ("axis", 4) | ("axis", 5) => {
+                let (binding, scale_index) = match words[2].to_uppercase().as_str() {
+                    "MOUSE_X" => (AxisBinding::MouseX, 3),
+                    "MOUSE_Y" => (AxisBinding::MouseY, 3),
+                    "MOUSE_WHEEL" => (AxisBinding::MouseWheel, 3),
+                    _ => {
+                        if words.len() < 4 {
+                            return Err(String::from("A key axis needs a negative and a positive key"));
+                        }
+                        match (parse_binding(words[2]), parse_binding(words[3])) {
+                            (Some(Binding::Key(negative)), Some(Binding::Key(positive))) => {
+                                (AxisBinding::Keys(negative, positive), 4)
+                            },
+                            _ => return Err(format!("Unknown keys {} {}", words[2], words[3])),
+                        }
+                    },
+                };
+                if words.len() > scale_index + 1 {
+                    return Err(String::from("Too many values for axis"));
+                }
+                let scale: f32 = match words.get(scale_index) {
+                    Some(s) => try!(s.parse::<f32>().map_err(|e| format!("Invalid scale {}: {:?}", s, e))),
+                    None => 1.0,
+                };
+                self.bind_axis(words[1], binding, scale);
+                Ok(())
+            },
+            _ => Err(format!("Unable to parse binding: {}", line.trim())),
+        }
+    }
+
+    pub fn bind_action(&mut self, action: &str, binding: Binding) {
+        self.actions.entry(String::from(action)).or_insert(Vec::new()).push(binding);
+    }
+
+    pub fn bind_axis(&mut self, axis: &str, binding: AxisBinding, scale: f32) {
+        self.axes.entry(String::from(axis)).or_insert(Vec::new()).push((binding, scale));
+    }
+
+    /// Remove all bindings of an action or axis
+    ///
+    pub fn unbind(&mut self, name: &str) {
+        self.actions.remove(name);
+        self.axes.remove(name);
+    }
+
+    /// Advance to the next frame, applying a batch of events
+    ///
+    pub fn update(&mut self, events: &EventBuffer) {
+        self.state.update(events);
+    }
+
+    pub fn update_events(&mut self, events: &[Event]) {
+        self.state.update_events(events);
+    }
+
+    fn any_binding<K, M>(&self, action: &str, key: K, mouse: M) -> bool
+        where K: Fn(&InputState, VirtualKeyCode) -> bool,
+              M: Fn(&InputState, MouseButton) -> bool
+    {
+        match self.actions.get(action) {
+            Some(bindings) => bindings.iter().any(|b| match b {
+                &Binding::Key(keycode) => key(&self.state, keycode),
+                &Binding::Mouse(button) => mouse(&self.state, button),
+            }),
+            None => false,
+        }
+    }
+
+    /// Check if the action was pressed this frame
+    ///
+    pub fn action_pressed(&self, action: &str) -> bool {
+        self.any_binding(action, InputState::key_pressed, InputState::button_pressed)
+    }
+
+    /// Check if the action is currently held down
+    ///
+    pub fn action_held(&self, action: &str) -> bool {
+        self.any_binding(action, InputState::key_down, InputState::button_down)
+    }
+
+    /// Check if the action was released this frame
+    ///
+    pub fn action_released(&self, action: &str) -> bool {
+        self.any_binding(action, InputState::key_released, InputState::button_released)
+    }
+
+    /// The sum of the scaled values of all bindings of an axis
+    ///
+    pub fn axis(&self, axis: &str) -> f32 {
+        let bindings = match self.axes.get(axis) {
+            Some(b) => b,
+            None => return 0.0,
+        };
+        bindings.iter().fold(0.0, |sum, &(binding, scale)| {
+            let value: f32 = match binding {
+                AxisBinding::Keys(negative, positive) => {
+                    let mut value: f32 = 0.0;
+                    if self.state.key_down(negative) { value -= 1.0; }
+                    if self.state.key_down(positive) { value += 1.0; }
+                    value
+                },
+                AxisBinding::MouseX => self.state.mouse_delta.0 as f32,
+                AxisBinding::MouseY => self.state.mouse_delta.1 as f32,
+                AxisBinding::MouseWheel => self.state.scroll.1,
+            };
+            sum + value * scale
+        })
+    }
+}
+
+/// `extern InputMap create_input_map();`
+///
+#[no_mangle]
+pub extern "C" fn create_input_map() -> Option<Box<InputMap>> {
+    ffi::guard(|| {
+        Box::new(InputMap::new())
+    })
+}
+
+/// `extern InputMap input_map_from_file(const char* filename);`
+///
+#[no_mangle]
+pub extern "C" fn input_map_from_file(filename: *const libc::c_char) -> Option<Box<InputMap>> {
+    ffi::guard_result(|| -> Result<Box<InputMap>, Error> {
+        let map: InputMap = try!(InputMap::from_file(&try!(ffi::c_string(filename))));
+        Ok(Box::new(map))
+    })
+}
+
+/// `extern void free_input_map(InputMap ptr);`
+///
+#[no_mangle]
+pub extern "C" fn free_input_map(ptr: *mut InputMap) {
+    ffi::guard_status(|| {
+        let box_ptr: Box<InputMap> = unsafe { Box::from_raw(ptr) };
+        drop(box_ptr)
+    });
+}
+
+/// `extern bool input_map_bind_key(InputMap map, const char* action, KeyCode key);`
+///
+#[no_mangle]
+pub extern "C" fn input_map_bind_key(map: &mut InputMap, action: *const libc::c_char, key: KeyCode) -> bool {
+    ffi::guard_result_status(|| {
+        map.bind_action(&try!(ffi::c_string(action)), Binding::Key(key.get_enum()));
+        Ok(())
+    })
+}
+
+/// `extern bool input_map_bind_mouse_button(InputMap map, const char* action, int button);`
+///
+/// Buttons are numbered 0 (left), 1 (right) and 2 (middle).
+///
+#[no_mangle]
+pub extern "C" fn input_map_bind_mouse_button(map: &mut InputMap, action: *const libc::c_char, button: libc::c_int) -> bool {
+    ffi::guard_result_status(|| {
+        map.bind_action(&try!(ffi::c_string(action)), Binding::Mouse(mouse_button_from_index(button)));
+        Ok(())
+    })
+}
+
+/// `extern bool input_map_bind_axis_keys(InputMap map, const char* axis, KeyCode negative, KeyCode positive, float scale);`
+///
+#[no_mangle]
+pub extern "C" fn input_map_bind_axis_keys(map: &mut InputMap,
+                                           axis: *const libc::c_char,
+                                           negative: KeyCode,
+                                           positive: KeyCode,
+                                           scale: libc::c_float) -> bool {
+    ffi::guard_result_status(|| {
+        map.bind_axis(&try!(ffi::c_string(axis)), AxisBinding::Keys(negative.get_enum(), positive.get_enum()), scale as f32);
+        Ok(())
+    })
+}
+
+/// `extern bool input_map_bind_mouse_axis(InputMap map, const char* axis, int mouse_axis, float scale);`
+///
+/// Mouse axes are numbered 0 (x), 1 (y) and 2 (wheel).
+///
+#[no_mangle]
+pub extern "C" fn input_map_bind_mouse_axis(map: &mut InputMap, axis: *const libc::c_char, mouse_axis: libc::c_int, scale: libc::c_float) -> bool {
+    ffi::guard_result_status(|| {
+        let binding: AxisBinding = match mouse_axis {
+            0 => AxisBinding::MouseX,
+            1 => AxisBinding::MouseY,
+            _ => AxisBinding::MouseWheel,
+        };
+        map.bind_axis(&try!(ffi::c_string(axis)), binding, scale as f32);
+        Ok(())
+    })
+}
+
+/// `extern bool input_map_parse_line(InputMap map, const char* line);`
+///
+#[no_mangle]
+pub extern "C" fn input_map_parse_line(map: &mut InputMap, line: *const libc::c_char) -> bool {
+    ffi::guard_result_or(false, || {
+        match map.parse_line(&try!(ffi::c_string(line))) {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                ffi::set_last_error(&e);
+                Ok(false)
+            },
+        }
+    })
+}
+
+/// `extern bool input_map_update(InputMap map, EventBuffer events);`
+///
+#[no_mangle]
+pub extern "C" fn input_map_update(map: &mut InputMap, events: &EventBuffer) -> bool {
+    ffi::guard_status(|| {
+        map.update(events);
+    })
+}
+
+/// `extern bool action_pressed(InputMap map, const char* action);`
+///
+#[no_mangle]
+pub extern "C" fn action_pressed(map: &InputMap, action: *const libc::c_char) -> bool {
+    ffi::guard_result_or(false, || {
+        Ok(map.action_pressed(&try!(ffi::c_string(action))))
+    })
+}
+
+/// `extern bool action_held(InputMap map, const char* action);`
+///
+#[no_mangle]
+pub extern "C" fn action_held(map: &InputMap, action: *const libc::c_char) -> bool {
+    ffi::guard_result_or(false, || {
+        Ok(map.action_held(&try!(ffi::c_string(action))))
+    })
+}
+
+/// `extern bool action_released(InputMap map, const char* action);`
+///
+#[no_mangle]
+pub extern "C" fn action_released(map: &InputMap, action: *const libc::c_char) -> bool {
+    ffi::guard_result_or(false, || {
+        Ok(map.action_released(&try!(ffi::c_string(action))))
+    })
+}
+
+/// `extern float axis_value(InputMap map, const char* axis);`
+///
+#[no_mangle]
+pub extern "C" fn axis_value(map: &InputMap, axis: *const libc::c_char) -> libc::c_float {
+    ffi::guard_result_or(0.0, || {
+        Ok(map.axis(&try!(ffi::c_string(axis))) as libc::c_float)
+    })
+}
@@ -11,17 +11,37 @@ extern crate flate2;
 extern crate nalgebra;
 extern crate libc;
 extern crate frustum_query;
+extern crate rusttype;
 
 pub mod common;
+pub mod console;
 pub mod dbloader;
+pub mod error;
+pub mod ffi;
 pub mod input;
+pub mod inputmap;
+pub mod recording;
+pub mod remote;
 pub mod camera;
+pub mod animation;
+pub mod orbit;
 pub mod scene;
 pub mod shader;
 #[macro_use]
 pub mod renderer;
+pub mod raycast;
+pub mod picking;
+pub mod stats;
+pub mod profiler;
+pub mod text;
+pub mod overlay;
+pub mod viewport;
+pub mod app;
+pub mod mainloop;
 
-use std::ffi::CStr;
+#[cfg(feature = "sqlite")]
+use std::ffi::CString;
+use std::io;
 
 use glium::glutin;
 use glium::DisplayBuild;
@@ -29,6 +49,8 @@ use glium::backend::glutin_backend::GlutinFacade;
 
 #[cfg(feature = "sqlite")]
 use dbloader::DBLoader;
+use error::Error;
+use renderer::RendererError;
 
 #[cfg(feature = "sqlite")]
 use scene::Scene;
@@ -45,105 +67,92 @@ extern "C" {
 /// that can be accessed from C and SWIG
 ///
 
-/// `extern void obj2sqlite(const char* wavefront, const char* database);`
+/// `extern bool obj2sqlite(const char* wavefront_file, const char* database_file);`
 ///
 /// If the obj2sqlite feature is enabled, a bundled c++ library is used.
 ///
 #[cfg(feature = "sqlite")]
 #[no_mangle]
-pub fn obj2sqlite(wavefront_file: *const libc::c_char, database_file: *const libc::c_char) {
-    unsafe { wavefrontToSQLite(wavefront_file, database_file) };
+pub extern "C" fn obj2sqlite(wavefront_file: *const libc::c_char, database_file: *const libc::c_char) -> bool {
+    ffi::guard_result_status(|| {
+        try!(ffi::c_string(wavefront_file));
+        try!(ffi::c_string(database_file));
+        unsafe { wavefrontToSQLite(wavefront_file, database_file) };
+        Ok(())
+    })
 }
 
-/// `extern void obj2sqlite(const char* wavefront, const char* database);`
+/// `extern bool obj2sqlite(const char* wavefront_file, const char* database_file);`
 ///
 /// When the sqlite feature is disabled, the method is still availible 
 /// so that the FFI wrappers will still build without modification the user
 /// is notified that the feature is disabled.
 #[cfg(not(feature = "sqlite"))]
 #[no_mangle]
-pub fn obj2sqlite(wavefront_file: *const libc::c_char, database_file: *const libc::c_char) {
-    let filename1: String = unsafe{ CStr::from_ptr(wavefront_file).to_string_lossy().into_owned() };
-    let filename2: String = unsafe{ CStr::from_ptr(database_file).to_string_lossy().into_owned() };
-    panic!("Unable to convert {} to {}, the SQLite feature is not enabled.", filename1, filename2);
+pub extern "C" fn obj2sqlite(_wavefront_file: *const libc::c_char, _database_file: *const libc::c_char) -> bool {
+    ffi::guard_result_status(|| Err(ffi::sqlite_disabled()))
+}
+
+/// Convert a Wavefront file into a SQLite database next to it, then load the `Scene` from the database
+///
+#[cfg(feature = "sqlite")]
+fn load_wavefront(wavefront_file: *const libc::c_char) -> Result<Scene, Error> {
+    let mut database_file: String = try!(ffi::c_string(wavefront_file));
+    database_file.push_str(".db");
+    let sqlite_file: CString = try!(CString::new(database_file.clone()).map_err(io::Error::from));
+    unsafe { wavefrontToSQLite(wavefront_file, sqlite_file.as_ptr()) };
+    let dbloader: DBLoader = try!(DBLoader::new(&database_file));
+    dbloader.load_scene()
 }
 
-/// `extern void obj2bin(const char* wavefront, const char* database);`
+/// `extern bool obj2bin(const char* wavefront_file, const char* binfile);`
 ///
-/// This will print an error if the sqlite feature is disabled
+/// This will set the last error if the sqlite feature is disabled
 ///
 #[cfg(feature = "sqlite")]
 #[no_mangle]
-pub fn obj2bin(wavefront_file: *const libc::c_char, binfile: *const libc::c_char) {
-    use std::ffi::CString;
-    let filename: String = unsafe{ CStr::from_ptr(wavefront_file.clone()).to_string_lossy().into_owned() };
-    let binfile_str: String = unsafe{ CStr::from_ptr(binfile.clone()).to_string_lossy().into_owned() };
-    let mut database_file: String = filename.clone();
-    database_file.push_str(&String::from(".db"));
-    let sqlite_file = CString::new(database_file.clone()).expect("unable to parse file name");
-    obj2sqlite(wavefront_file, sqlite_file.clone().into_raw());
-    let scene: Scene = match DBLoader::new(&(sqlite_file.into_string()
-        .expect("Unable to parse file name")))
-        .expect("Unable to create database loader").load_scene() {
-        Ok(s) => s,
-        Err(e) => panic!("Unable to load scene from SQLite: {:?}", e),
-    };
-    match scene.to_binary_file(binfile_str.clone()) {
-        Ok(()) => println!("Saved {}", binfile_str),
-        Err(e) => panic!("Unable to save binary file {}: {:?}", binfile_str, e),
-    };
+pub extern "C" fn obj2bin(wavefront_file: *const libc::c_char, binfile: *const libc::c_char) -> bool {
+    ffi::guard_result_status(|| {
+        let binfile_str: String = try!(ffi::c_string(binfile));
+        let scene: Scene = try!(load_wavefront(wavefront_file));
+        scene.to_binary_file(binfile_str)
+    })
 }
 
-/// `extern void obj2bin(const char* wavefront, const char* database);`
+/// `extern bool obj2bin(const char* wavefront_file, const char* binfile);`
 ///
 /// When the sqlite feature is disabled, the method is still availible 
 /// so that the FFI wrappers will still build without modification the user
 /// is notified that the feature is disabled.
 #[cfg(not(feature = "sqlite"))]
 #[no_mangle]
-pub fn obj2bin(wavefront_file: *const libc::c_char, database_file: *const libc::c_char) {
-    let filename1: String = unsafe{ CStr::from_ptr(wavefront_file).to_string_lossy().into_owned() };
-    let filename2: String = unsafe{ CStr::from_ptr(database_file).to_string_lossy().into_owned() };
-    panic!("Unable to convert {} to {}, the SQLite feature is not enabled.", filename1, filename2);
+pub extern "C" fn obj2bin(_wavefront_file: *const libc::c_char, _database_file: *const libc::c_char) -> bool {
+    ffi::guard_result_status(|| Err(ffi::sqlite_disabled()))
 }
 
-/// `extern void obj2compressed(const char* wavefront, const char* database);`
+/// `extern bool obj2compressed(const char* wavefront_file, const char* binfile);`
 ///
-/// This will print an error if the obj2sqlite feature is disabled
+/// This will set the last error if the obj2sqlite feature is disabled
 ///
 #[cfg(feature = "sqlite")]
 #[no_mangle]
-pub fn obj2compressed(wavefront_file: *const libc::c_char, binfile: *const libc::c_char) {
-    use std::ffi::CString;
-	let filename: String = unsafe{ CStr::from_ptr(wavefront_file.clone()).to_string_lossy().into_owned() };
-    let binfile_str: String = unsafe{ CStr::from_ptr(binfile.clone()).to_string_lossy().into_owned() };
-    let mut database_file: String = filename.clone();
-    database_file.push_str(&String::from(".db"));
-    let sqlite_file = CString::new(database_file.clone()).expect("unable to parse file name");
-    obj2sqlite(wavefront_file, sqlite_file.clone().into_raw());
-    let scene: Scene = match DBLoader::new(&(sqlite_file.into_string()
-        .expect("Unable to parse file name")))
-        .expect("Unable to create database loader").load_scene() {
-        Ok(s) => s,
-        Err(e) => panic!("Unable to load scene from SQLite: {:?}", e),
-    };
-    match scene.to_compressed_binary_file(binfile_str.clone()) {
-        Ok(()) => println!("Saved {}", binfile_str),
-        Err(e) => panic!("Unable to save compressed binary file {}: {:?}", binfile_str, e),
-    };
+pub extern "C" fn obj2compressed(wavefront_file: *const libc::c_char, binfile: *const libc::c_char) -> bool {
+    ffi::guard_result_status(|| {
+        let binfile_str: String = try!(ffi::c_string(binfile));
+        let scene: Scene = try!(load_wavefront(wavefront_file));
+        scene.to_compressed_binary_file(binfile_str)
+    })
 }
 
-/// `extern void obj2compressed(const char* wavefront, const char* database);`
+/// `extern bool obj2compressed(const char* wavefront_file, const char* binfile);`
 ///
 /// When the sqlite feature is disabled, the method is still availible 
 /// so that the FFI wrappers will still build without modification the user
 /// is notified that the feature is disabled.
 #[cfg(not(feature = "sqlite"))]
 #[no_mangle]
-pub fn obj2compressed(wavefront_file: *const libc::c_char, database_file: *const libc::c_char) {
-    let filename1: String = unsafe{ CStr::from_ptr(wavefront_file).to_string_lossy().into_owned() };
-    let filename2: String = unsafe{ CStr::from_ptr(database_file).to_string_lossy().into_owned() };
-    panic!("Unable to convert {} to {}, the SQLite feature is not enabled.", filename1, filename2);
+pub extern "C" fn obj2compressed(_wavefront_file: *const libc::c_char, _database_file: *const libc::c_char) -> bool {
+    ffi::guard_result_status(|| Err(ffi::sqlite_disabled()))
 }
 
 /// `extern Display create_display(int screen_width, int screen_height, const char* title);`
@@ -152,23 +161,24 @@ pub fn obj2compressed(wavefront_file: *const libc::c_char, database_file: *const
 pub extern "C" fn create_display(screen_width: libc::int32_t,
                                  screen_height: libc::int32_t,
                                  title: *const libc::c_char)
-                                 -> Box<GlutinFacade> {
-    let w: u32 = screen_width as u32;
-    let h: u32 = screen_height as u32;
-    let window_title: String;
-
-    unsafe {
-        window_title = CStr::from_ptr(title).to_string_lossy().into_owned();
-        let display: GlutinFacade = glutin::WindowBuilder::new()
-            .with_gl_debug_flag(true)
-            .with_title(window_title)
-            .with_visibility(true)
-            .with_dimensions(w, h)
-            .with_multitouch()
-						.build_glium_unchecked()
-            .expect("Unable to create display");
-        return Box::new(display);
-    }
+                                 -> Option<Box<GlutinFacade>> {
+    ffi::guard_result(|| -> Result<Box<GlutinFacade>, Error> {
+        let w: u32 = screen_width as u32;
+        let h: u32 = screen_height as u32;
+        let window_title: String = try!(ffi::c_string(title));
+        let display: GlutinFacade = try!(
+            unsafe {
+                glutin::WindowBuilder::new()
+                    .with_gl_debug_flag(true)
+                    .with_title(window_title)
+                    .with_visibility(true)
+                    .with_dimensions(w, h)
+                    .with_multitouch()
+                    .build_glium_unchecked()
+            }.map_err(RendererError::DisplayCreationError)
+        );
+        Ok(Box::new(display))
+    })
 }
 
 /// `extern Display create_hidden_display(int screen_width, int screen_height, const char* title);`
@@ -177,31 +187,32 @@ pub extern "C" fn create_display(screen_width: libc::int32_t,
 pub extern "C" fn create_hidden_display(screen_width: libc::int32_t,
                                  screen_height: libc::int32_t,
                                  title: *const libc::c_char)
-                                 -> Box<GlutinFacade> {
-    let w: u32 = screen_width as u32;
-    let h: u32 = screen_height as u32;
-    let window_title: String;
-
-    unsafe {
-
-        window_title = CStr::from_ptr(title).to_string_lossy().into_owned();
-        let display: GlutinFacade = glutin::WindowBuilder::new()
-            .with_gl_debug_flag(true)
-            .with_title(window_title)
-            .with_visibility(false)
-            .with_dimensions(w, h)
-            .build_glium()
-            .expect("Unable to create display");
-        return Box::new(display);
-    }
+                                 -> Option<Box<GlutinFacade>> {
+    ffi::guard_result(|| -> Result<Box<GlutinFacade>, Error> {
+        let w: u32 = screen_width as u32;
+        let h: u32 = screen_height as u32;
+        let window_title: String = try!(ffi::c_string(title));
+        let display: GlutinFacade = try!(
+            glutin::WindowBuilder::new()
+                .with_gl_debug_flag(true)
+                .with_title(window_title)
+                .with_visibility(false)
+                .with_dimensions(w, h)
+                .build_glium()
+                .map_err(RendererError::DisplayCreationError)
+        );
+        Ok(Box::new(display))
+    })
 }
 
-/// `extern void free_display(Display memory);`
+/// `extern void free_display(Display ptr);`
 ///
 #[no_mangle]
 pub extern "C" fn free_display(ptr: *mut GlutinFacade) {
-    let box_ptr: Box<GlutinFacade> = unsafe { Box::from_raw(ptr) };
-    drop(box_ptr)
+    ffi::guard_status(|| {
+        let box_ptr: Box<GlutinFacade> = unsafe { Box::from_raw(ptr) };
+        drop(box_ptr)
+    });
 }
 
 use std::time::Duration;
@@ -221,32 +232,34 @@ pub extern "C" fn thread_yield() {
 }
 
 
-/// `extern void window_hide(Display display);`
+/// `extern bool window_hide(Display display);`
 ///
 #[no_mangle]
-pub extern "C" fn window_hide(display: &GlutinFacade) {
-    match display.get_window() {
-        Some(w) => {
-            w.hide();
-        }
-        None => {
-            panic!("Error retrieving window");
+pub extern "C" fn window_hide(display: &GlutinFacade) -> bool {
+    ffi::guard_result_status(|| {
+        match display.get_window() {
+            Some(w) => {
+                w.hide();
+                Ok(())
+            }
+            None => Err(Error::Io(io::Error::new(io::ErrorKind::NotFound, "Error retrieving window"))),
         }
-    };
+    })
 }
 
-/// `extern void window_show(Display display);`
+/// `extern bool window_show(Display display);`
 ///
 #[no_mangle]
-pub extern "C" fn window_show(display: &GlutinFacade) {
-    match display.get_window() {
-        Some(w) => {
-            w.show();
-        }
-        None => {
-            panic!("Error retrieving window");
+pub extern "C" fn window_show(display: &GlutinFacade) -> bool {
+    ffi::guard_result_status(|| {
+        match display.get_window() {
+            Some(w) => {
+                w.show();
+                Ok(())
+            }
+            None => Err(Error::Io(io::Error::new(io::ErrorKind::NotFound, "Error retrieving window"))),
         }
-    };
+    })
 }
 
 #[cfg(test)]
@@ -271,6 +284,702 @@ mod tests {
         Scene::from_compressed_binary_file(String::from("test.bin.gz")).expect("Unable to load compressed binary file")
     }
 
+    fn create_triangle_scene() -> Scene {
+        use std::cell::RefCell;
+        use nalgebra::{Eye, Matrix4};
+        use common::{Material, Mesh, Vertex8f32};
+        let identity: Matrix4<f32> = Eye::new_identity(4);
+        let vertices = vec![
+            Vertex8f32 { position: [-1.0, -1.0, 0.0], normal: [0.0, 0.0, 1.0], texcoord: [0.0, 0.0] },
+            Vertex8f32 { position: [1.0, -1.0, 0.0], normal: [0.0, 0.0, 1.0], texcoord: [1.0, 0.0] },
+            Vertex8f32 { position: [0.0, 1.0, 0.0], normal: [0.0, 0.0, 1.0], texcoord: [0.5, 1.0] },
+        ];
+        Scene {
+            materials: vec![Material { name: String::from("default"), diffuse: [1.0, 1.0, 1.0], diffuse_texname: String::new() }],
+            meshes: vec![Mesh {
+                name: String::from("Triangle"),
+                vertices: vertices,
+                material_index: 0,
+                radius: 1.5,
+                center: [0.0, 0.0, 0.0],
+                matrix: RefCell::new(identity),
+            }],
+            images: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn raycast_triangle() {
+        use nalgebra::{Matrix4, Vector3};
+        use raycast::{normal_matrix, Ray};
+        let scene = create_triangle_scene();
+        let hit = scene.raycast(&Ray::new([0.0, 0.0, 5.0], [0.0, 0.0, -1.0])).expect("Ray should hit the triangle");
+        assert_eq!(hit.mesh_index, 0);
+        assert_eq!(hit.triangle_index, 0);
+        assert!((hit.distance - 5.0).abs() < 0.0001);
+        assert!((hit.normal[2] - 1.0).abs() < 0.0001);
+        assert!(scene.raycast(&Ray::new([0.0, 0.0, 5.0], [0.0, 0.0, 1.0])).is_none());
+
+        // Moving the mesh moves the hit point
+        scene.meshes[0].matrix.borrow_mut().m34 = -2.0;
+        let hit = scene.raycast(&Ray::new([0.0, 0.0, 5.0], [0.0, 0.0, -1.0])).expect("Ray should hit the moved triangle");
+        assert!((hit.point[2] + 2.0).abs() < 0.0001);
+
+        // Normals stay perpendicular to surfaces which are scaled unevenly
+        let scale = Matrix4::new(2.0, 0.0, 0.0, 0.0,
+                                 0.0, 1.0, 0.0, 0.0,
+                                 0.0, 0.0, 1.0, 0.0,
+                                 0.0, 0.0, 0.0, 1.0);
+        let normal = normal_matrix(&scale) * Vector3::new(1.0, -1.0, 0.0);
+        assert!((normal.y + 2.0 * normal.x).abs() < 0.0001);
+    }
+
+    #[test]
+    fn orbit_controller() {
+        use camera::Camera;
+        use nalgebra::Vector3;
+        use orbit::OrbitController;
+        let camera = Camera::new(800.0, 600.0);
+        let mut orbit = OrbitController::new(Vector3::new(1.0, 2.0, 3.0), 5.0);
+        let orbit_camera = orbit.apply(&camera);
+        assert!((orbit_camera.position.z - 8.0).abs() < 0.0001);
+        assert!((orbit_camera.direction.z + 1.0).abs() < 0.0001);
+
+        // Rotating keeps the eye on the sphere around the target
+        orbit.rotate(100.0, -50.0);
+        let orbit_camera = orbit.apply(&camera);
+        let offset = orbit_camera.position - orbit.target;
+        assert!(((offset.x * offset.x + offset.y * offset.y + offset.z * offset.z).sqrt() - 5.0).abs() < 0.0001);
+        let facing = offset.x * orbit_camera.direction.x + offset.y * orbit_camera.direction.y + offset.z * orbit_camera.direction.z;
+        assert!((facing + 5.0).abs() < 0.0001);
+
+        orbit.zoom(1.0);
+        assert!(orbit.distance < 5.0);
+    }
+
+    #[test]
+    fn camera_orientation() {
+        use camera;
+        use camera::Camera;
+        use ffi;
+        use nalgebra::Vector3;
+        use std::f32::consts::FRAC_PI_2;
+        let camera = Camera::new(800.0, 600.0);
+        assert!((camera.direction.z + 1.0).abs() < 0.0001);
+        assert!((camera.orientation[3] - 1.0).abs() < 0.0001);
+
+        let camera = camera.set_position(&Vector3::new(0.0, 0.0, 0.0))
+            .look_at(&Vector3::new(10.0, 0.0, 0.0), &Vector3::new(0.0, 1.0, 0.0));
+        assert!((camera.direction.x - 1.0).abs() < 0.0001);
+        assert!((camera.up.y - 1.0).abs() < 0.0001);
+
+        // Rolling a quarter turn moves the up vector onto the old right vector
+        let right = camera.right;
+        let rolled = camera.roll_by(FRAC_PI_2);
+        assert!((rolled.up.x - right.x).abs() < 0.0001);
+        assert!((rolled.up.z - right.z).abs() < 0.0001);
+        assert!((rolled.direction.x - 1.0).abs() < 0.0001);
+
+        // Aiming preserves the roll
+        let aimed = rolled.aim(10.0, 0.0);
+        assert!((aimed.roll - rolled.roll).abs() < 0.0001);
+
+        // Degenerate projections are rejected
+        assert!(camera.set_fov(FRAC_PI_2).is_ok());
+        assert!(camera.set_fov(0.0).is_err() && camera.set_fov(4.0).is_err());
+        assert!(camera.set_clip_planes(0.0, 10.0).is_err() && camera.set_clip_planes(1.0, 1.0).is_err());
+        assert!(camera.set_orthographic(-1.0).is_err());
+        let mut in_place = camera.clone();
+        assert!(!camera::camera_set_clip_planes_in_place(&mut in_place, 10.0, 1.0));
+        assert!(ffi::last_error().is_some());
+        assert_eq!(in_place.far, camera.far);
+    }
+
+    #[test]
+    fn camera_path() {
+        use animation::{CameraPath, Interpolation};
+        use camera::Camera;
+        use nalgebra::Vector3;
+        let camera = Camera::new(800.0, 600.0);
+        let mut path = CameraPath::new(Interpolation::CatmullRom);
+        path.add_camera(2.0, &camera.set_position(&Vector3::new(2.0, 0.0, 0.0)));
+        path.add_camera(0.0, &camera.set_position(&Vector3::new(0.0, 0.0, 0.0)));
+        path.add_camera(1.0, &camera.set_position(&Vector3::new(1.0, 0.0, 0.0)));
+        assert_eq!(path.keyframes[1].time, 1.0);
+        assert_eq!(path.duration(), 2.0);
+
+        // Catmull-Rom passes through the keyframes
+        let middle = path.sample(1.0).expect("Path should not be empty");
+        assert!((middle.position[0] - 1.0).abs() < 0.0001);
+        let moved = path.apply(&camera, 0.5);
+        assert!(moved.position.x > 0.0 && moved.position.x < 1.0);
+
+        // Looping paths return to the first keyframe before wrapping around
+        path.looping = true;
+        assert_eq!(path.period(), 3.0);
+        let wrapped = path.sample(4.0).expect("Path should not be empty");
+        assert!((wrapped.position[0] - 1.0).abs() < 0.0001);
+        let closing = path.sample(2.5).expect("Path should not be empty");
+        assert!((closing.position[0] - 1.0).abs() < 0.0001);
+        path.loop_period = 4.0;
+        let closing = path.sample(3.0).expect("Path should not be empty");
+        assert!((closing.position[0] - 1.0).abs() < 0.0001);
+
+        let filename = std::env::temp_dir().join(format!("quick3d_camera_path_{}.bin", std::process::id()))
+            .to_string_lossy().into_owned();
+        path.to_binary_file(filename.clone()).expect("Unable to save camera path");
+        let loaded = CameraPath::from_binary_file(filename.clone()).expect("Unable to load camera path");
+        assert!(loaded == path);
+        let _ = ::std::fs::remove_file(filename);
+    }
+
+    #[test]
+    fn stereo_camera() {
+        use camera::Camera;
+        use nalgebra::{Vector3, Vector4};
+        use viewport::{StereoCamera, Viewport};
+        let (left, right) = Viewport::side_by_side(800, 600);
+        assert_eq!(left.x, 0);
+        assert_eq!(right.x, 400);
+        assert_eq!(right.width, 400);
+        let camera = Camera::new(400.0, 600.0).look_at(&Vector3::new(0.0f32, 0.0, -1.0), &Vector3::new(0.0f32, 1.0, 0.0));
+        let stereo = StereoCamera::new(0.065, 5.0);
+        let (left_eye, right_eye) = (stereo.left_eye(&camera), stereo.right_eye(&camera));
+        assert!((left_eye.position.x + 0.0325).abs() < 0.0001);
+        assert!((right_eye.position.x - 0.0325).abs() < 0.0001);
+        // A point at the convergence distance projects to the same place for both eyes
+        let ndc_x = |eye: &Camera| {
+            let clip: Vector4<f32> = eye.view_projection_matrix() * Vector4::new(0.0f32, 0.0, -5.0, 1.0);
+            clip.x / clip.w
+        };
+        assert!((ndc_x(&left_eye) - ndc_x(&right_eye)).abs() < 0.0001);
+    }
+
+    #[test]
+    fn input_map() {
+        use glium::glutin::{ElementState, Event, MouseButton, VirtualKeyCode};
+        use inputmap::InputMap;
+        let mut map = InputMap::new();
+        for line in ["action jump SPACE # comment", "action fire MOUSE_LEFT", "axis forward S W 0.5", "axis look MOUSE_X"].iter() {
+            map.parse_line(line).unwrap();
+        }
+        assert!(map.parse_line("action jump NOT_A_KEY").is_err());
+        map.update_events(&[Event::KeyboardInput(ElementState::Pressed, 0, Some(VirtualKeyCode::Space)),
+                            Event::KeyboardInput(ElementState::Pressed, 0, Some(VirtualKeyCode::W)),
+                            Event::MouseMoved(10, 10)]);
+        assert!(map.action_pressed("jump") && map.action_held("jump"));
+        assert_eq!(map.axis("forward"), 0.5);
+        // Key repeat does not press the action again
+        map.update_events(&[Event::KeyboardInput(ElementState::Pressed, 0, Some(VirtualKeyCode::Space)),
+                            Event::MouseInput(ElementState::Pressed, MouseButton::Left),
+                            Event::MouseMoved(15, 12)]);
+        assert!(!map.action_pressed("jump") && map.action_held("jump"));
+        assert!(map.action_pressed("fire"));
+        assert_eq!(map.axis("look"), 5.0);
+        map.update_events(&[Event::KeyboardInput(ElementState::Released, 0, Some(VirtualKeyCode::Space))]);
+        assert!(map.action_released("jump") && !map.action_held("jump"));
+        assert_eq!(map.axis("look"), 0.0);
+    }
+
+    #[test]
+    fn input_state() {
+        use glium::glutin::{ElementState, Event, MouseButton, MouseScrollDelta, TouchPhase, VirtualKeyCode};
+        use input::InputState;
+        let mut state = InputState::new();
+        state.update_events(&[Event::MouseInput(ElementState::Pressed, MouseButton::Right),
+                              Event::MouseInput(ElementState::Pressed, MouseButton::Middle),
+                              Event::KeyboardInput(ElementState::Pressed, 0, Some(VirtualKeyCode::A)),
+                              Event::MouseMoved(100, 50),
+                              Event::MouseWheel(MouseScrollDelta::LineDelta(0.0, 2.0), TouchPhase::Moved)]);
+        assert!(state.button_pressed(MouseButton::Right) && !state.button_down(MouseButton::Left));
+        assert!(state.button_down(MouseButton::Middle));
+        assert_eq!(state.mouse_position, Some((100, 50)));
+        assert_eq!(state.mouse_delta, (0, 0));
+        assert_eq!(state.scroll, (0.0, 2.0));
+        state.update_events(&[Event::MouseMoved(90, 55), Event::MouseInput(ElementState::Released, MouseButton::Right)]);
+        assert!(state.key_down(VirtualKeyCode::A) && !state.key_pressed(VirtualKeyCode::A));
+        assert!(state.button_released(MouseButton::Right) && !state.button_released(MouseButton::Left));
+        assert_eq!(state.mouse_delta, (-10, 5));
+        assert_eq!(state.scroll, (0.0, 0.0));
+        state.update_events(&[Event::Focused(false)]);
+        assert!(state.key_released(VirtualKeyCode::A) && !state.key_down(VirtualKeyCode::A));
+    }
+
+    #[test]
+    fn input_recording() {
+        use glium::glutin::{ElementState, Event, MouseButton, VirtualKeyCode};
+        use input::EventBuffer;
+        use recording::{InputRecorder, InputRecording, InputReplay};
+        let frames = vec![vec![Event::KeyboardInput(ElementState::Pressed, 17, Some(VirtualKeyCode::W)), Event::MouseMoved(3, 4)],
+                          vec![],
+                          vec![Event::MouseInput(ElementState::Released, MouseButton::Middle), Event::Closed]];
+        let mut recorder = InputRecorder::new();
+        for (i, events) in frames.iter().enumerate() {
+            recorder.record_at(i as f64 / 60.0, &EventBuffer::from_events(events.clone()));
+        }
+        let filename = std::env::temp_dir().join(format!("quick3d_recording_{}.bin", std::process::id()))
+            .to_string_lossy().into_owned();
+        recorder.recording.to_binary_file(filename.clone()).unwrap();
+        let recording = InputRecording::from_binary_file(filename.clone()).unwrap();
+        let _ = ::std::fs::remove_file(filename);
+        assert_eq!(recording.first_difference(&recorder.recording), None);
+
+        let mut replay = InputReplay::new(recording);
+        for events in frames.iter() {
+            let replayed = replay.next_events().unwrap();
+            assert_eq!(format!("{:?}", replayed.events()), format!("{:?}", events));
+        }
+        assert!(replay.finished() && replay.next_events().is_none());
+        assert_eq!(replay.time(), 2.0 / 60.0);
+    }
+
+    #[test]
+    fn recorded_key_codes() {
+        use glium::glutin::{ElementState, Event, VirtualKeyCode};
+        use input::VIRTUAL_KEY_CODES;
+        use recording::RecordedEvent;
+        assert_eq!(VIRTUAL_KEY_CODES.len(), VirtualKeyCode::Yen as usize + 1);
+        for (i, key) in VIRTUAL_KEY_CODES.iter().enumerate() {
+            assert_eq!(*key as usize, i);
+            let event = Event::KeyboardInput(ElementState::Released, 0, Some(*key));
+            let recorded = RecordedEvent::from_event(&event).expect("Key events are recorded");
+            assert_eq!(format!("{:?}", recorded.to_event()), format!("{:?}", event));
+        }
+    }
+
+    #[test]
+    fn event_data() {
+        use glium::glutin::{ElementState, Event, VirtualKeyCode};
+        use input::{EventBuffer, EventKind, KeyCode};
+        use std::path::PathBuf;
+        let events = EventBuffer::from_events(vec![Event::ReceivedCharacter('h'),
+                                                   Event::DroppedFile(PathBuf::from("a.bin")),
+                                                   Event::ReceivedCharacter('i'),
+                                                   Event::Focused(false),
+                                                   Event::KeyboardInput(ElementState::Pressed, 30, Some(VirtualKeyCode::LShift)),
+                                                   Event::DroppedFile(PathBuf::from("b.bin"))]);
+        assert_eq!(events.text(), "hi");
+        assert_eq!(events.dropped_files(), vec![PathBuf::from("a.bin"), PathBuf::from("b.bin")]);
+        assert_eq!(events.focused(), Some(false));
+        let data = events.event_data();
+        assert_eq!(data.len(), 6);
+        assert_eq!(data[0].kind, EventKind::EVENT_RECEIVED_CHARACTER);
+        assert_eq!(data[0].character, 'h' as u32);
+        assert_eq!(data[4].kind, EventKind::EVENT_KEYBOARD_INPUT);
+        assert_eq!(data[4].keycode, KeyCode::LSHIFT as i32);
+        assert!(data[4].pressed);
+        assert_eq!(data[5].index, 1);
+        for (i, event) in data.iter().enumerate() {
+            assert_eq!(events.event_data_at(i), Some(*event));
+        }
+        assert_eq!(events.event_data_at(6), None);
+    }
+
+    #[test]
+    fn console_commands() {
+        use console;
+        use console::{Console, CVarValue};
+        use ffi;
+        let mut console = Console::new();
+        console.register_cvar("camera_speed", CVarValue::Float(0.01), "Distance moved per frame");
+        console.register_cvar("wireframe", CVarValue::Bool(false), "Draw edges only");
+        console.register_command("add", "add <a> <b>: add two integers", |_, args| {
+            let values: Result<Vec<i64>, _> = args.iter().map(|a| a.parse::<i64>()).collect();
+            values.map(|v| format!("{}", v.iter().sum::<i64>())).map_err(|e| format!("{:?}", e))
+        });
+        assert_eq!(console.execute("set camera_speed 0.5; wireframe on").unwrap(), "");
+        assert_eq!(console.get_float("camera_speed"), 0.5);
+        assert!(console.get_bool("wireframe"));
+        assert_eq!(console.execute("camera_speed").unwrap(), "camera_speed = 0.5");
+        assert!(console.execute("set camera_speed fast").is_err());
+        assert_eq!(console.execute("add 2 3; echo \"a; b\"").unwrap(), "5\na; b");
+        assert!(console.execute("unknown").is_err());
+        assert_eq!(console.complete("c"), vec!["camera_speed", "commands", "cvars"]);
+        console.execute("reset camera_speed").unwrap();
+        assert_eq!(console.get_float("camera_speed"), 0.01);
+        assert!(!console::console_set(&mut console, std::ptr::null(), std::ptr::null()));
+        assert_eq!(ffi::last_error(), Some(String::from("Invalid argument: Unexpected null string")));
+        assert!(console::console_get_string(&console, std::ptr::null()).is_null());
+    }
+
+    #[test]
+    fn console_server() {
+        use console::Console;
+        use remote;
+        use remote::ConsoleServer;
+        use std::io::{BufRead, BufReader, Write};
+        use std::net::TcpStream;
+        let mut console = Console::new();
+        let mut server = ConsoleServer::bind_tcp(0).unwrap();
+        let mut client = TcpStream::connect(("127.0.0.1", server.port())).unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        client.write_all(b"echo hello remote\n").unwrap();
+        let deadline = std::time::Instant::now() + Duration::from_secs(2);
+        while server.process(&mut console) == 0 {
+            assert!(std::time::Instant::now() < deadline, "The console server did not receive the line");
+            thread::sleep(Duration::from_millis(5));
+        }
+        let mut reader = BufReader::new(client);
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "quick3d console\n");
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "> hello remote\n");
+
+        // Shutting down waits for the client thread, which closes the connection
+        server.shutdown();
+        assert_eq!(server.client_count(), 0);
+        assert!(server.take_errors().is_empty());
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "> ");
+        assert_eq!(reader.read_line(&mut line).unwrap(), 0);
+
+        assert!(remote::create_console_server_tcp(-1).is_none());
+        assert!(remote::create_console_server_tcp(65536).is_none());
+        assert_eq!(::ffi::last_error(), Some(String::from("Invalid argument: Invalid port 65536")));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn console_server_unix_path() {
+        use std::fs::File;
+        use remote::ConsoleServer;
+        let path = std::env::temp_dir().join(format!("quick3d_console_{}", std::process::id()));
+        File::create(&path).unwrap();
+        assert!(ConsoleServer::bind_unix(path.to_str().unwrap()).is_err());
+        assert!(path.is_file());
+        std::fs::remove_file(&path).unwrap();
+        drop(ConsoleServer::bind_unix(path.to_str().unwrap()).unwrap());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn ffi_last_error() {
+        use std::ffi::CStr;
+        use error::Error;
+        use ffi;
+        assert!(!ffi::guard_status(|| panic!("Unable to load {}", "missing.bin")));
+        let message = unsafe { CStr::from_ptr(ffi::quick3d_last_error()) };
+        assert_eq!(message.to_str().unwrap(), "Unable to load missing.bin");
+        assert_eq!(ffi::guard_or(-1, || 3), 3);
+        assert!(ffi::quick3d_last_error().is_null());
+        assert!(!ffi::guard_result_status(|| Err(Error::InvalidArgument(String::from("Invalid image data")))));
+        assert_eq!(ffi::last_error(), Some(String::from("Invalid argument: Invalid image data")));
+        assert_eq!(ffi::guard_result_or(-1, || Ok(3)), 3);
+        assert!(ffi::last_error().is_none());
+
+        // Null strings are reported before anything is created
+        use std::ptr;
+        assert!(::create_hidden_display(64, 64, ptr::null()).is_none());
+        assert_eq!(ffi::last_error(), Some(String::from("Invalid argument: Unexpected null string")));
+        assert!(!::obj2bin(ptr::null(), ptr::null()));
+        assert!(ffi::last_error().is_some());
+    }
+
+    #[test]
+    fn error_chain() {
+        use std::error::Error as StdError;
+        use error::Error;
+        use renderer::RendererError;
+        match Scene::from_binary_file(String::from("missing.bin")) {
+            Err(Error::Io(_)) => (),
+            Err(e) => panic!("Unexpected error: {:?}", e),
+            Ok(_) => panic!("Loaded a missing file"),
+        }
+        let error = Error::from(RendererError::EmptySceneError);
+        assert_eq!(format!("{}", error), "Renderer error: The scene does not contain any meshes");
+        let cause = error.cause().expect("Renderer errors have a cause");
+        assert_eq!(cause.description(), "the scene does not contain any meshes");
+        assert!(cause.cause().is_none());
+    }
+
+    #[test]
+    fn generated_header() {
+        let generated: &str = include_str!(concat!(env!("OUT_DIR"), "/quick3d.h"));
+        let checked_in: &str = include_str!("../quick3d.h");
+        assert!(generated == checked_in.replace("\r\n", "\n"),
+                "quick3d.h is out of date, build with QUICK3D_UPDATE_HEADER set to regenerate it");
+    }
+
+    #[test]
+    fn scene_from_arrays() {
+        use std::ffi::CString;
+        use std::ptr;
+        use error::Error;
+        use scene;
+        let mut scene = scene::create_scene().expect("Unable to create scene");
+        let name = CString::new("Triangle").unwrap();
+        let material = scene::scene_add_material(&mut scene, name.as_ptr(), 1.0, 0.5, 0.0, ptr::null());
+        assert_eq!(material, 0);
+        let positions: [f32; 9] = [-1.0, -1.0, 0.0, 1.0, -1.0, 0.0, 0.0, 1.0, 0.0];
+        assert_eq!(scene::scene_add_mesh(&mut scene, name.as_ptr(), positions.as_ptr(), ptr::null(), ptr::null(), 3, 0), 0);
+        assert_eq!(scene::scene_add_mesh(&mut scene, name.as_ptr(), positions.as_ptr(), ptr::null(), ptr::null(), 3, 1), -1);
+        assert_eq!(::ffi::last_error(), Some(String::from("Invalid argument: Material index out of range")));
+        assert_eq!(scene::scene_mesh_count(&scene), 1);
+        assert!((scene.meshes[0].radius - 2.0f32.sqrt()).abs() < 1e-5);
+        let pixels: [u8; 8] = [255, 0, 0, 255, 0, 255, 0, 255];
+        assert!(scene::scene_add_texture_rgba(&mut scene, name.as_ptr(), pixels.as_ptr(), 2, 1));
+        assert!(scene.add_image_rgba("Overflow", &pixels, 0x80000000, 0x80000000).is_err());
+        match scene.add_image_rgba("Short", &pixels, 4, 4) {
+            Err(Error::InvalidArgument(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+        scene.add_default_texture().expect("Unable to add default texture");
+        assert_eq!(scene.images.len(), 2);
+        scene::free_scene(Box::into_raw(scene));
+    }
+
+    #[test]
+    fn main_loop_dispatch() {
+        use glium::glutin::{ElementState, Event, MouseButton, VirtualKeyCode};
+        use input::{EventBuffer, EventData, EventKind, KeyCode};
+        use mainloop::MainLoop;
+        use libc::{c_int, c_void};
+        extern "C" fn key(user_data: *mut c_void, keycode: c_int, _scancode: c_int, pressed: bool) {
+            let calls = unsafe { &mut *(user_data as *mut Vec<String>) };
+            calls.push(format!("key {} {}", keycode, pressed));
+        }
+        extern "C" fn mouse(user_data: *mut c_void, event: *const EventData) {
+            let calls = unsafe { &mut *(user_data as *mut Vec<String>) };
+            let event = unsafe { &*event };
+            assert!(event.kind == EventKind::EVENT_MOUSE_MOVED || event.kind == EventKind::EVENT_MOUSE_INPUT);
+            calls.push(format!("mouse {} {} {}", event.x, event.y, event.button));
+        }
+        extern "C" fn resize(user_data: *mut c_void, width: c_int, height: c_int) {
+            let calls = unsafe { &mut *(user_data as *mut Vec<String>) };
+            calls.push(format!("resize {} {}", width, height));
+        }
+        let mut calls: Vec<String> = Vec::new();
+        let mut main_loop = MainLoop::new();
+        main_loop.user_data = &mut calls as *mut Vec<String> as *mut c_void;
+        main_loop.key = Some(key);
+        main_loop.mouse = Some(mouse);
+        main_loop.resize = Some(resize);
+        main_loop.dispatch(&EventBuffer::from_events(vec![
+            Event::Resized(800, 600),
+            Event::KeyboardInput(ElementState::Pressed, 1, Some(VirtualKeyCode::Escape)),
+            Event::Focused(true),
+            Event::MouseMoved(10, 20),
+            Event::MouseInput(ElementState::Released, MouseButton::Right),
+        ]));
+        assert_eq!(calls, vec![String::from("resize 800 600"),
+                               format!("key {} true", KeyCode::ESCAPE as i32),
+                               String::from("mouse 10 20 0"),
+                               String::from("mouse 0 0 1")]);
+    }
+
+    #[test]
+    fn fixed_timestep() {
+        use app::FrameClock;
+        let mut clock = FrameClock::new();
+        let step: f64 = 0.25;
+        assert_eq!(clock.fixed_steps(0.125, step, 5), (0, 0.5));
+        assert_eq!(clock.fixed_steps(0.5, step, 5), (2, 0.5));
+        // Time beyond the maximum number of steps is dropped
+        let (steps, alpha) = clock.fixed_steps(10.0, step, 5);
+        assert_eq!(steps, 5);
+        assert!(alpha >= 0.0 && alpha < 1.0);
+    }
+
+    #[test]
+    fn frame_stats() {
+        use stats::{FrameStats, RenderStats, STATS_HISTORY};
+        let mut stats = RenderStats::new();
+        stats.texture_memory = 1024;
+        assert_eq!(stats.average(), FrameStats::default());
+        for i in 0..STATS_HISTORY + 2 {
+            stats.push(FrameStats { frame_time: 10.0, draw_calls: (i % 2) as u32 * 4, .. FrameStats::default() });
+        }
+        assert_eq!(stats.frame_times().len(), STATS_HISTORY);
+        assert_eq!(stats.last().draw_calls, 4);
+        let average = stats.average();
+        assert_eq!(average.frame_time, 10.0);
+        assert_eq!(average.draw_calls, 2);
+        assert_eq!(average.texture_memory, 1024);
+
+        // Overlay and text draws count as draw calls but not as meshes
+        stats.begin_frame();
+        stats.record_draw(3);
+        stats.record_draw_call(6);
+        stats.end_frame();
+        assert_eq!((stats.last().draw_calls, stats.last().meshes_drawn, stats.last().vertices), (2, 1, 9));
+
+        // The graph is a background, a bar for each frame and the target line, from the bottom left
+        use std::collections::HashMap;
+        use overlay::SpriteBatch;
+        use stats::StatsOverlay;
+        let mut batch = SpriteBatch::new();
+        StatsOverlay::build(&mut batch, &[10.0, 20.0], 480.0);
+        assert_eq!(batch.len(), 4);
+        let vertices = batch.build(&HashMap::new());
+        assert_eq!(vertices[6].position, [8.0, 442.0]);
+        assert_eq!(vertices[8].position, [9.0, 472.0]);
+        assert_eq!(vertices[12].position, [10.0, 412.0]);
+    }
+
+    #[test]
+    fn profiler_scopes() {
+        use profiler::Profiler;
+        let profiler = Profiler::new();
+        // Nothing is recorded until the profiler is enabled
+        profiler.begin("ignored");
+        profiler.end();
+        profiler.end_frame();
+        assert!(profiler.frames().is_empty());
+
+        profiler.enable(false);
+        {
+            let _outer = profiler.scope("frame");
+            profiler.begin("update");
+            profiler.end();
+            profiler.begin("draw \"scene\"");
+            profiler.end();
+        }
+        profiler.end_frame();
+        let frames = profiler.frames();
+        assert_eq!(frames.len(), 1);
+        let depths: Vec<u32> = frames[0].scopes.iter().map(|s| s.depth).collect();
+        assert_eq!(depths, vec![0, 1, 1]);
+        assert!(frames[0].scopes[0].duration >= frames[0].scopes[1].duration);
+
+        let trace = profiler.to_chrome_trace();
+        assert!(trace.contains("\"name\":\"update\""));
+        assert!(trace.contains("\"name\":\"draw \\\"scene\\\"\""));
+        assert!(!trace.contains("\"tid\":2"));
+        profiler.disable();
+        assert_eq!(profiler.frames().len(), 1);
+    }
+
+    #[test]
+    fn profiler_disable_mid_frame() {
+        use profiler::Profiler;
+        let display = create_test_display();
+        let profiler = Profiler::new();
+        profiler.enable(true);
+        profiler.begin("pass");
+        if let Some(query) = profiler.gpu_query(&display) {
+            profiler.attach_gpu_query(query);
+        }
+        profiler.disable();
+        // The query of the discarded scope is dropped with it
+        let trace = profiler.to_chrome_trace();
+        assert!(!trace.contains("\"name\":\"pass\""));
+        assert!(profiler.frames().is_empty());
+    }
+
+    #[test]
+    fn glyph_atlas_layout() {
+        use text::{GlyphAtlas, DEFAULT_FONT, DEFAULT_FONT_SIZE};
+        assert!(GlyphAtlas::new(vec![0; 16], DEFAULT_FONT_SIZE).is_err());
+
+        let atlas = GlyphAtlas::new(DEFAULT_FONT.to_vec(), DEFAULT_FONT_SIZE).unwrap();
+        assert!(atlas.height.is_power_of_two());
+        assert_eq!(atlas.pixels.len(), (atlas.width * atlas.height) as usize);
+        let a = *atlas.glyph('A').unwrap();
+        assert!(a.size[0] > 0.0 && a.texcoords[2] <= 1.0 && a.texcoords[3] <= 1.0);
+        assert_eq!(atlas.glyph(' ').unwrap().size, [0.0, 0.0]);
+
+        // The bundled font is monospaced, unknown characters are drawn as '?'
+        assert_eq!(atlas.measure("AA", 1.0), (a.advance * 2.0, atlas.line_height));
+        assert_eq!(atlas.measure("A\u{263a}\nA", 2.0), (a.advance * 4.0, atlas.line_height * 4.0));
+        let quads = atlas.layout("A A", 1.0);
+        assert_eq!(quads.len(), 2);
+        assert_eq!(quads[1].rect[0] - quads[0].rect[0], a.advance * 2.0);
+        assert!(quads[0].rect[1] >= 0.0 && quads[0].rect[3] <= atlas.ascent + 1.0);
+    }
+
+    #[test]
+    fn sprite_batch_atlas() {
+        use std::collections::HashMap;
+        use overlay::{pack_sprites, SpriteBatch};
+        let mut batch = SpriteBatch::new();
+        batch.image("b.png", 0.0, 0.0, 8.0, 8.0, [1.0; 4], 1);
+        batch.rect(0.0, 0.0, 4.0, 4.0, [1.0, 0.0, 0.0, 1.0], 0);
+        batch.image("a.png", 0.0, 0.0, 8.0, 8.0, [1.0; 4], 0);
+        batch.line(0.0, 0.0, 10.0, 0.0, 2.0, [1.0; 4], 1);
+        batch.sprite("a.png", 0.0, 0.0, 8.0, 8.0, [0.5, 0.5, 1.0, 1.0], [1.0; 4], 2);
+        assert_eq!(batch.textures(), vec![String::from("a.png"), String::from("b.png")]);
+
+        let mut regions: HashMap<String, [f32; 4]> = HashMap::new();
+        regions.insert(String::from("a.png"), [0.0, 0.0, 0.5, 0.5]);
+        regions.insert(String::from("b.png"), [0.5, 0.5, 1.0, 1.0]);
+        let vertices = batch.build(&regions);
+        assert_eq!(vertices.len(), 30);
+
+        // Sorted by z, texture coordinates are moved into the region of each texture
+        assert_eq!(vertices[0].textured, 0.0);
+        assert_eq!(vertices[6].textured, 1.0);
+        // The top left of the image is the top left of its region
+        assert_eq!(vertices[6].texcoord, [0.0, 0.0]);
+        assert_eq!(vertices[8].texcoord, [0.5, 0.5]);
+        assert_eq!(vertices[12].texcoord, [0.5, 0.5]);
+        // The line is 2 pixels wide
+        assert_eq!(vertices[18].position, [0.0, 1.0]);
+        assert_eq!(vertices[20].position, [10.0, -1.0]);
+        assert_eq!(vertices[24].texcoord, [0.25, 0.25]);
+
+        // A texture missing from the atlas is drawn as a plain color
+        regions.remove("b.png");
+        assert_eq!(batch.build(&regions)[12].textured, 0.0);
+
+        batch.clear();
+        assert!(batch.textures().is_empty());
+        assert!(batch.build(&regions).is_empty());
+
+        // The tallest image is placed first, images don't overlap and are padded
+        let (width, height, positions) = pack_sprites(&[(4, 2), (1000, 8), (30, 8), (1, 1)]);
+        assert_eq!(width, 1024);
+        assert_eq!(positions, vec![(32, 10), (1, 1), (1, 10), (37, 10)]);
+        assert_eq!(height, 32);
+        assert_eq!(pack_sprites(&[(2000, 1)]).0, 2002);
+    }
+
+    #[test]
+    fn picking_ids() {
+        use picking::{decode_id, decode_region, encode_id};
+        assert_eq!(decode_id(0), None);
+        for index in vec![0, 1, 41, 65534] {
+            assert!(encode_id(index) != 0);
+            assert_eq!(decode_id(encode_id(index)), Some(index));
+        }
+        let ids = vec![vec![0, encode_id(3), encode_id(3)],
+                       vec![encode_id(1), 0, encode_id(3)],
+                       vec![0, 0, encode_id(1)]];
+        assert_eq!(decode_region(&ids), vec![1, 3]);
+        assert!(decode_region(&[vec![0, 0], vec![0, 0]]).is_empty());
+    }
+
+    #[test]
+    fn mesh_bounds() {
+        use common::{Mesh, Vertex8f32};
+        let scene = create_triangle_scene();
+        let bounds = scene.world_bounding_box();
+        assert_eq!(bounds.min, [-1.0, -1.0, 0.0]);
+        assert_eq!(bounds.max, [1.0, 1.0, 0.0]);
+
+        scene.meshes[0].matrix.borrow_mut().m14 = 3.0;
+        let bounds = scene.world_bounding_box();
+        assert_eq!(bounds.min[0], 2.0);
+        assert_eq!(bounds.max[0], 4.0);
+        assert_eq!(scene.local_bounding_box().min[0], -1.0);
+
+        // Changing the vertices recomputes the bounding sphere
+        let mut mesh = Mesh::new("Line", vec![
+            Vertex8f32 { position: [0.0, 0.0, 0.0], normal: [0.0, 1.0, 0.0], texcoord: [0.0, 0.0] },
+            Vertex8f32 { position: [2.0, 0.0, 0.0], normal: [0.0, 1.0, 0.0], texcoord: [1.0, 0.0] },
+        ], 0);
+        assert_eq!(mesh.center, [1.0, 0.0, 0.0]);
+        assert_eq!(mesh.radius, 1.0);
+        mesh.set_vertices(vec![
+            Vertex8f32 { position: [0.0, 0.0, 0.0], normal: [0.0, 1.0, 0.0], texcoord: [0.0, 0.0] },
+            Vertex8f32 { position: [0.0, 4.0, 0.0], normal: [0.0, 1.0, 0.0], texcoord: [0.0, 1.0] },
+        ]);
+        assert_eq!(mesh.center, [0.0, 2.0, 0.0]);
+        assert_eq!(mesh.radius, 2.0);
+    }
+
     #[test]
     fn test_scene_not_empty() {
         let scene: Scene = load_test_scene();
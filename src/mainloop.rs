@@ -0,0 +1,222 @@
+// Copyright (C) 2016 Chris Liebert
+
+extern crate libc;
+
+use std::cell::Cell;
+use std::ptr;
+
+use glium::backend::glutin_backend::GlutinFacade;
+use glium::glutin::Event;
+
+use app::FrameClock;
+use input::{EventBuffer, EventData};
+use ffi;
+
+/// Called once per frame with the seconds elapsed since the last update
+///
+pub type UpdateCallback = extern "C" fn(user_data: *mut libc::c_void, dt: libc::c_double);
+
+/// Called once per frame after the update to draw the frame
+///
+pub type RenderCallback = extern "C" fn(user_data: *mut libc::c_void);
+
+/// Called when a key is pressed or released, `keycode` is a `KeyCode` or -1
+///
+pub type KeyCallback = extern "C" fn(user_data: *mut libc::c_void,
+                                     keycode: libc::c_int,
+                                     scancode: libc::c_int,
+                                     pressed: bool);
+
+/// Called for `EVENT_MOUSE_MOVED`, `EVENT_MOUSE_INPUT` and `EVENT_MOUSE_WHEEL` events
+///
+pub type MouseCallback = extern "C" fn(user_data: *mut libc::c_void, event: *const EventData);
+
+/// Called when the window is resized
+///
+pub type ResizeCallback = extern "C" fn(user_data: *mut libc::c_void,
+                                        width: libc::c_int,
+                                        height: libc::c_int);
+
+/// A main loop driven by quick3d which calls back into the host program
+///
+/// Each frame the window events are dispatched to the key, mouse and resize callbacks, then
+/// the update callback is called with the elapsed time and the render callback draws the frame.
+/// Every callback is optional and receives the `user_data` pointer, which quick3d never reads.
+/// The loop runs until the window is closed or `quit` is called, usually from a callback.
+///
+pub struct MainLoop {
+    pub user_data: *mut libc::c_void,
+    pub update: Option<UpdateCallback>,
+    pub render: Option<RenderCallback>,
+    pub key: Option<KeyCallback>,
+    pub mouse: Option<MouseCallback>,
+    pub resize: Option<ResizeCallback>,
+    /// Frames are paced to this rate by sleeping, 0 runs as fast as possible
+    pub max_fps: u32,
+    quit: Cell<bool>,
+}
+
+impl MainLoop {
+    pub fn new() -> MainLoop {
+        MainLoop {
+            user_data: ptr::null_mut(),
+            update: None,
+            render: None,
+            key: None,
+            mouse: None,
+            resize: None,
+            max_fps: 0,
+            quit: Cell::new(false),
+        }
+    }
+
+    /// Stop the loop after the current frame
+    ///
+    pub fn quit(&self) {
+        self.quit.set(true);
+    }
+
+    /// Pass each event to the matching callback
+    ///
+    pub fn dispatch(&self, events: &EventBuffer) {
+        let mut dropped_index: i32 = 0;
+        for event in events.events() {
+            let data: EventData = EventData::from_event(event, dropped_index);
+            match event {
+                &Event::DroppedFile(_) => dropped_index += 1,
+                &Event::Resized(width, height) => {
+                    if let Some(resize) = self.resize {
+                        resize(self.user_data, width as libc::c_int, height as libc::c_int);
+                    }
+                },
+                &Event::KeyboardInput(..) => {
+                    if let Some(key) = self.key {
+                        key(self.user_data, data.keycode, data.scancode, data.pressed);
+                    }
+                },
+                &Event::MouseMoved(..) | &Event::MouseInput(..) | &Event::MouseWheel(..) => {
+                    if let Some(mouse) = self.mouse {
+                        mouse(self.user_data, &data);
+                    }
+                },
+                _ => (),
+            }
+        }
+    }
+
+    /// Run the loop until the window is closed or `quit` is called
+    ///
+    pub fn run(&self, display: &GlutinFacade) {
+        self.quit.set(false);
+        let mut clock: FrameClock = FrameClock::new();
+        while !self.quit.get() {
+            let dt: f64 = clock.tick();
+            let events: EventBuffer = EventBuffer::new(display);
+            if events.closed() {
+                break;
+            }
+            self.dispatch(&events);
+
+            if let Some(update) = self.update {
+                update(self.user_data, dt);
+            }
+            if self.quit.get() {
+                break;
+            }
+            if let Some(render) = self.render {
+                render(self.user_data);
+            }
+            clock.pace(self.max_fps);
+        }
+    }
+}
+
+/// `extern MainLoop create_main_loop(void* user_data);`
+///
+/// `user_data` is passed to every callback, it may be NULL.
+///
+#[no_mangle]
+pub extern "C" fn create_main_loop(user_data: *mut libc::c_void) -> Option<Box<MainLoop>> {
+    ffi::guard(|| {
+        let mut main_loop: MainLoop = MainLoop::new();
+        main_loop.user_data = user_data;
+        Box::new(main_loop)
+    })
+}
+
+/// `extern void free_main_loop(MainLoop ptr);`
+///
+#[no_mangle]
+pub extern "C" fn free_main_loop(ptr: *mut MainLoop) {
+    ffi::guard_status(|| {
+        let box_ptr: Box<MainLoop> = unsafe { Box::from_raw(ptr) };
+        drop(box_ptr)
+    });
+}
+
+/// `extern bool main_loop_set_update(MainLoop main_loop, UpdateCallback callback);`
+///
+/// Passing NULL removes the callback, as for the other setters. Callbacks can't be changed
+/// while the loop is running.
+///
+#[no_mangle]
+pub extern "C" fn main_loop_set_update(main_loop: &mut MainLoop, callback: Option<UpdateCallback>) -> bool {
+    ffi::guard_status(|| main_loop.update = callback)
+}
+
+/// `extern bool main_loop_set_render(MainLoop main_loop, RenderCallback callback);`
+///
+#[no_mangle]
+pub extern "C" fn main_loop_set_render(main_loop: &mut MainLoop, callback: Option<RenderCallback>) -> bool {
+    ffi::guard_status(|| main_loop.render = callback)
+}
+
+/// `extern bool main_loop_set_key(MainLoop main_loop, KeyCallback callback);`
+///
+#[no_mangle]
+pub extern "C" fn main_loop_set_key(main_loop: &mut MainLoop, callback: Option<KeyCallback>) -> bool {
+    ffi::guard_status(|| main_loop.key = callback)
+}
+
+/// `extern bool main_loop_set_mouse(MainLoop main_loop, MouseCallback callback);`
+///
+#[no_mangle]
+pub extern "C" fn main_loop_set_mouse(main_loop: &mut MainLoop, callback: Option<MouseCallback>) -> bool {
+    ffi::guard_status(|| main_loop.mouse = callback)
+}
+
+/// `extern bool main_loop_set_resize(MainLoop main_loop, ResizeCallback callback);`
+///
+#[no_mangle]
+pub extern "C" fn main_loop_set_resize(main_loop: &mut MainLoop, callback: Option<ResizeCallback>) -> bool {
+    ffi::guard_status(|| main_loop.resize = callback)
+}
+
+/// `extern bool main_loop_set_max_fps(MainLoop main_loop, int max_fps);`
+///
+#[no_mangle]
+pub extern "C" fn main_loop_set_max_fps(main_loop: &mut MainLoop, max_fps: libc::c_int) -> bool {
+    ffi::guard_status(|| main_loop.max_fps = max_fps.max(0) as u32)
+}
+
+/// `extern bool main_loop_run(MainLoop main_loop, Display display);`
+///
+/// Blocks until the window is closed or `main_loop_quit` is called.
+///
+#[no_mangle]
+pub extern "C" fn main_loop_run(main_loop: &MainLoop, display: &GlutinFacade) -> bool {
+    ffi::guard_status(|| {
+        main_loop.run(display);
+        // Errors from calls made inside of the callbacks were already reported to them
+        ffi::clear_last_error();
+    })
+}
+
+/// `extern bool main_loop_quit(MainLoop main_loop);`
+///
+/// Can be called from a callback to stop the loop after the current frame.
+///
+#[no_mangle]
+pub extern "C" fn main_loop_quit(main_loop: &MainLoop) -> bool {
+    ffi::guard_status(|| main_loop.quit())
+}
@@ -0,0 +1,216 @@
+// Copyright (C) 2016 Chris Liebert
+
+extern crate libc;
+
+use nalgebra;
+use nalgebra::Vector3;
+
+use camera::Camera;
+use common::BoundingBox;
+use renderer::Renderer;
+
+use std::f64::consts::{FRAC_PI_2, PI};
+use ffi;
+
+/// An orbit (arcball) controller for model viewing
+///
+/// The eye of an `OrbitController` lies on a sphere of radius `distance` around `target`,
+/// positioned by `azimuth` (around the y axis) and `elevation` (above the xz plane) in radians.
+/// The controller is applied to a `Camera` to produce a new `Camera` looking at the target.
+///
+/// ```c
+/// /* C representation */
+/// typedef void* OrbitController;
+/// ```
+///
+#[derive(Clone, Debug)]
+pub struct OrbitController {
+    pub target: Vector3<f32>,
+    pub distance: f32,
+    pub azimuth: f64,
+    pub elevation: f64,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    pub rotate_speed: f64,
+    pub pan_speed: f32,
+    pub zoom_speed: f32,
+}
+
+impl OrbitController {
+    /// Create a new `OrbitController` looking at `target` from the positive z axis
+    ///
+    pub fn new(target: Vector3<f32>, distance: f32) -> OrbitController {
+        OrbitController {
+            target: target,
+            distance: distance,
+            azimuth: 0.0,
+            elevation: 0.0,
+            min_distance: 0.01,
+            max_distance: 10000.0,
+            rotate_speed: 0.01,
+            pan_speed: 0.001,
+            zoom_speed: 0.1,
+        }
+    }
+
+    /// The unit vector pointing from the target to the eye
+    ///
+    fn offset_direction(&self) -> Vector3<f32> {
+        Vector3::new((self.elevation.cos() * self.azimuth.sin()) as f32,
+                     self.elevation.sin() as f32,
+                     (self.elevation.cos() * self.azimuth.cos()) as f32)
+    }
+
+    /// The position of the eye in world space
+    ///
+    pub fn eye_position(&self) -> Vector3<f32> {
+        self.target + self.offset_direction() * self.distance
+    }
+
+    /// Rotate the eye around the target, typically by mouse movement in pixels
+    ///
+    pub fn rotate(&mut self, x: f64, y: f64) {
+        // Keep the elevation away from the poles where the up vector is undefined
+        let limit: f64 = FRAC_PI_2 - 0.01;
+        self.azimuth = (self.azimuth - x * self.rotate_speed) % (2.0 * PI);
+        self.elevation = (self.elevation - y * self.rotate_speed).max(-limit).min(limit);
+    }
+
+    /// Move the target (and eye) parallel to the view plane, scaled by the distance
+    ///
+    pub fn pan(&mut self, x: f32, y: f32) {
+        let direction: Vector3<f32> = self.offset_direction() * -1.0;
+        let right: Vector3<f32> = nalgebra::normalize(&nalgebra::cross(&direction, &Vector3::new(0.0f32, 1.0, 0.0)));
+        let up: Vector3<f32> = nalgebra::cross(&right, &direction);
+        let scale: f32 = self.distance * self.pan_speed;
+        self.target = self.target - right * (x * scale) + up * (y * scale);
+    }
+
+    /// Move the eye towards (positive) or away from (negative) the target by a fixed amount
+    ///
+    pub fn dolly(&mut self, amount: f32) {
+        self.distance = (self.distance - amount).max(self.min_distance).min(self.max_distance);
+    }
+
+    /// Scale the distance to the target exponentially, typically by mouse wheel steps
+    ///
+    pub fn zoom(&mut self, steps: f32) {
+        let factor: f32 = (1.0 - self.zoom_speed).powf(steps);
+        self.distance = (self.distance * factor).max(self.min_distance).min(self.max_distance);
+    }
+
+    /// Center the target on a `BoundingBox` and move the eye back until it fits the `Camera` view
+    ///
+    pub fn frame_bounds(&mut self, bounds: &BoundingBox, camera: &Camera) {
+        if bounds.is_empty() {
+            return;
+        }
+        let center = bounds.center();
+        self.target = Vector3::new(center[0], center[1], center[2]);
+        self.distance = camera.framing_distance(bounds.radius()).max(self.min_distance);
+        self.max_distance = self.max_distance.max(self.distance);
+    }
+
+    /// Create a `Camera` at the eye position looking at the target
+    ///
+    /// The projection of `camera` is preserved.
+    ///
+    pub fn apply(&self, camera: &Camera) -> Camera {
+        // Match the angles used by `Camera::aim`, the camera looks back along the offset
+        let mut orbit_camera: Camera = camera.clone();
+        orbit_camera.position = self.eye_position();
+        orbit_camera.pitch = self.azimuth + PI;
+        orbit_camera.yaw = -self.elevation;
+        orbit_camera.roll = 0.0;
+        orbit_camera.aim(0.0, 0.0)
+    }
+}
+
+/// `extern OrbitController create_orbit_controller(float x, float y, float z, float distance);`
+///
+#[no_mangle]
+pub extern "C" fn create_orbit_controller(x: libc::c_float,
+                                          y: libc::c_float,
+                                          z: libc::c_float,
+                                          distance: libc::c_float)
+                                          -> Option<Box<OrbitController>> {
+    ffi::guard(|| {
+        Box::new(OrbitController::new(Vector3::new(x as f32, y as f32, z as f32), distance as f32))
+    })
+}
+
+/// `extern void free_orbit_controller(OrbitController ptr);`
+///
+#[no_mangle]
+pub extern "C" fn free_orbit_controller(ptr: *mut OrbitController) {
+    ffi::guard_status(|| {
+        let box_ptr: Box<OrbitController> = unsafe { Box::from_raw(ptr) };
+        drop(box_ptr)
+    });
+}
+
+/// `extern bool orbit_rotate(OrbitController orbit, double x, double y);`
+///
+#[no_mangle]
+pub extern "C" fn orbit_rotate(orbit: &mut OrbitController, x: libc::c_double, y: libc::c_double) -> bool {
+    ffi::guard_status(|| {
+        orbit.rotate(x as f64, y as f64);
+    })
+}
+
+/// `extern bool orbit_pan(OrbitController orbit, float x, float y);`
+///
+#[no_mangle]
+pub extern "C" fn orbit_pan(orbit: &mut OrbitController, x: libc::c_float, y: libc::c_float) -> bool {
+    ffi::guard_status(|| {
+        orbit.pan(x as f32, y as f32);
+    })
+}
+
+/// `extern bool orbit_dolly(OrbitController orbit, float amount);`
+///
+#[no_mangle]
+pub extern "C" fn orbit_dolly(orbit: &mut OrbitController, amount: libc::c_float) -> bool {
+    ffi::guard_status(|| {
+        orbit.dolly(amount as f32);
+    })
+}
+
+/// `extern bool orbit_zoom(OrbitController orbit, float steps);`
+///
+#[no_mangle]
+pub extern "C" fn orbit_zoom(orbit: &mut OrbitController, steps: libc::c_float) -> bool {
+    ffi::guard_status(|| {
+        orbit.zoom(steps as f32);
+    })
+}
+
+/// `extern bool orbit_frame_scene(OrbitController orbit, Renderer renderer, Camera camera);`
+///
+#[no_mangle]
+pub extern "C" fn orbit_frame_scene(orbit: &mut OrbitController, renderer: &Renderer, camera: &Camera) -> bool {
+    ffi::guard_status(|| {
+        orbit.frame_bounds(&renderer.scene.world_bounding_box(), camera);
+    })
+}
+
+/// `extern Camera orbit_apply(OrbitController orbit, Camera camera);`
+///
+#[no_mangle]
+pub extern "C" fn orbit_apply(orbit: &OrbitController, camera: *mut Camera) -> Option<Box<Camera>> {
+    ffi::guard(|| {
+        let camera: Box<Camera> = unsafe { Box::from_raw(camera) };
+        let new_camera = Box::new(orbit.apply(&camera));
+        drop(camera);
+        new_camera
+    })
+}
+
+/// `extern bool orbit_apply_in_place(OrbitController orbit, Camera camera);`
+///
+#[no_mangle]
+pub extern "C" fn orbit_apply_in_place(orbit: &OrbitController, camera: &mut Camera) -> bool {
+    ffi::guard_status(|| {
+        *camera = orbit.apply(camera);
+    })
+}
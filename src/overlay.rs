@@ -0,0 +1,579 @@
+// Copyright (C) 2016 Chris Liebert
+
+extern crate libc;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io;
+use std::slice;
+
+use glium;
+use glium::backend::glutin_backend::GlutinFacade;
+use glium::draw_parameters::TimeElapsedQuery;
+use glium::texture::{MipmapsOption, RawImage2d, SrgbTexture2d};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter};
+use glium::{Program, Surface};
+
+use common::ImageBlob;
+use error::Error;
+use renderer::{decode_image, Renderer, RendererError};
+use scene::DEFAULT_BLANK_TEXTURE;
+use ffi;
+
+/// The width of the overlay atlas in pixels, unless an image is wider, the height grows to fit the images
+///
+pub const SPRITE_ATLAS_WIDTH: u32 = 1024;
+
+/// Empty pixels between images so neighbours don't bleed into each other when filtered
+const SPRITE_ATLAS_PADDING: u32 = 1;
+
+/// A vertex of the overlay, positioned in pixels from the top left corner of the window
+///
+/// `textured` is 1.0 when the atlas is sampled and 0.0 for plain colors.
+///
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SpriteVertex {
+    pub position: [f32; 2],
+    pub texcoord: [f32; 2],
+    pub color: [f32; 4],
+    pub textured: f32,
+}
+
+implement_vertex!(SpriteVertex, position, texcoord, color, textured);
+
+struct Sprite {
+    z: i32,
+    texture: Option<String>,
+    vertices: [SpriteVertex; 6],
+}
+
+/// Rectangles, lines and images queued for the overlay
+///
+/// Positions and sizes are in pixels from the top left corner of the window. Items are drawn
+/// in increasing order of `z`, and in the order they were added when `z` is the same. All of the
+/// items share one vertex buffer and are drawn with a single draw call, the textures they use are
+/// packed into one atlas.
+///
+pub struct SpriteBatch {
+    sprites: Vec<Sprite>,
+}
+
+impl SpriteBatch {
+    pub fn new() -> SpriteBatch {
+        SpriteBatch { sprites: Vec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.sprites.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sprites.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.sprites.clear();
+    }
+
+    /// Add a quad from its four corners, starting at the top left and going clockwise
+    ///
+    /// `texcoords` are `[u0, v0, u1, v1]` in the image, they are moved into the atlas by `build`.
+    ///
+    fn push(&mut self, texture: Option<&str>, corners: [[f32; 2]; 4], texcoords: [f32; 4], color: [f32; 4], z: i32) {
+        let textured: f32 = match texture {
+            Some(_) => 1.0,
+            None => 0.0,
+        };
+        let (u0, v0, u1, v1) = (texcoords[0], texcoords[1], texcoords[2], texcoords[3]);
+        let vertex = |corner: usize, u: f32, v: f32| SpriteVertex {
+            position: corners[corner],
+            texcoord: [u, v],
+            color: color,
+            textured: textured,
+        };
+        self.sprites.push(Sprite {
+            z: z,
+            texture: texture.map(String::from),
+            vertices: [vertex(0, u0, v0), vertex(1, u1, v0), vertex(2, u1, v1),
+                       vertex(0, u0, v0), vertex(2, u1, v1), vertex(3, u0, v1)],
+        });
+    }
+
+    pub fn rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: [f32; 4], z: i32) {
+        self.push(None,
+                  [[x, y], [x + width, y], [x + width, y + height], [x, y + height]],
+                  [0.0, 0.0, 0.0, 0.0],
+                  color,
+                  z);
+    }
+
+    /// A line `width` pixels wide between (x0, y0) and (x1, y1)
+    ///
+    pub fn line(&mut self, x0: f32, y0: f32, x1: f32, y1: f32, width: f32, color: [f32; 4], z: i32) {
+        let (dx, dy) = (x1 - x0, y1 - y0);
+        let length: f32 = (dx * dx + dy * dy).sqrt();
+        if length == 0.0 {
+            return;
+        }
+        // Half of the width, perpendicular to the line
+        let (nx, ny) = (-dy / length * width * 0.5, dx / length * width * 0.5);
+        self.push(None,
+                  [[x0 + nx, y0 + ny], [x1 + nx, y1 + ny], [x1 - nx, y1 - ny], [x0 - nx, y0 - ny]],
+                  [0.0, 0.0, 0.0, 0.0],
+                  color,
+                  z);
+    }
+
+    /// Part of a texture, `source` is `[u0, v0, u1, v1]` from 0 to 1 where (0, 0) is the top left
+    /// corner of the image, `color` tints the texture
+    ///
+    pub fn sprite(&mut self,
+                  texture: &str,
+                  x: f32,
+                  y: f32,
+                  width: f32,
+                  height: f32,
+                  source: [f32; 4],
+                  color: [f32; 4],
+                  z: i32) {
+        self.push(Some(texture),
+                  [[x, y], [x + width, y], [x + width, y + height], [x, y + height]],
+                  source,
+                  color,
+                  z);
+    }
+
+    /// A whole texture
+    ///
+    pub fn image(&mut self, texture: &str, x: f32, y: f32, width: f32, height: f32, color: [f32; 4], z: i32) {
+        self.sprite(texture, x, y, width, height, [0.0, 0.0, 1.0, 1.0], color, z);
+    }
+
+    /// The names of the textures used by the items, without duplicates
+    ///
+    pub fn textures(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.sprites.iter().filter_map(|s| s.texture.clone()).collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Sort the items and move their texture coordinates into the atlas
+    ///
+    /// `regions` are `[u0, v0, u1, v1]` of each texture in the atlas, items with a texture which
+    /// isn't in `regions` are drawn as plain colors.
+    ///
+    pub fn build(&self, regions: &HashMap<String, [f32; 4]>) -> Vec<SpriteVertex> {
+        let mut order: Vec<&Sprite> = self.sprites.iter().collect();
+        // The sort is stable, so items with the same z keep their order
+        order.sort_by_key(|s| s.z);
+        let mut vertices: Vec<SpriteVertex> = Vec::with_capacity(order.len() * 6);
+        for sprite in order {
+            let region: Option<&[f32; 4]> = sprite.texture.as_ref().and_then(|t| regions.get(t));
+            for v in sprite.vertices.iter() {
+                let mut vertex: SpriteVertex = *v;
+                match region {
+                    Some(r) => {
+                        vertex.texcoord = [r[0] + v.texcoord[0] * (r[2] - r[0]),
+                                           r[1] + v.texcoord[1] * (r[3] - r[1])];
+                    },
+                    None => vertex.textured = 0.0,
+                }
+                vertices.push(vertex);
+            }
+        }
+        vertices
+    }
+}
+
+/// Place images of the given sizes in rows, starting from the top left corner with the tallest
+///
+/// Returns the width and height of the atlas and the top left corner of each image in pixels, in
+/// the order of `sizes`.
+///
+pub fn pack_sprites(sizes: &[(u32, u32)]) -> (u32, u32, Vec<(u32, u32)>) {
+    let widest: u32 = sizes.iter().map(|&(w, _)| w + 2 * SPRITE_ATLAS_PADDING).max().unwrap_or(0);
+    let width: u32 = widest.max(SPRITE_ATLAS_WIDTH);
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by(|&a, &b| sizes[b].1.cmp(&sizes[a].1));
+    let mut positions: Vec<(u32, u32)> = vec![(0, 0); sizes.len()];
+    let mut x: u32 = SPRITE_ATLAS_PADDING;
+    let mut y: u32 = SPRITE_ATLAS_PADDING;
+    let mut row_height: u32 = 0;
+    for i in order {
+        let (w, h) = sizes[i];
+        if x + w + SPRITE_ATLAS_PADDING > width {
+            x = SPRITE_ATLAS_PADDING;
+            y += row_height + SPRITE_ATLAS_PADDING;
+            row_height = 0;
+        }
+        positions[i] = (x, y);
+        x += w + SPRITE_ATLAS_PADDING;
+        row_height = row_height.max(h);
+    }
+    let height: u32 = (y + row_height + SPRITE_ATLAS_PADDING).next_power_of_two();
+    (width, height, positions)
+}
+
+/// The textures drawn by the overlay, packed into one texture
+///
+/// `regions` are `[u0, v0, u1, v1]` of each texture, where (0, 0) is the top left corner of the
+/// atlas and of the images.
+///
+pub struct SpriteAtlas {
+    pub regions: HashMap<String, [f32; 4]>,
+    pub texture: SrgbTexture2d,
+}
+
+impl SpriteAtlas {
+    /// Decode the images and copy them into a new atlas
+    ///
+    pub fn new(display: &GlutinFacade, blobs: &[&ImageBlob]) -> Result<SpriteAtlas, Error> {
+        let mut images = Vec::with_capacity(blobs.len());
+        for blob in blobs.iter() {
+            images.push(try!(decode_image(blob)));
+        }
+        let sizes: Vec<(u32, u32)> = images.iter().map(|i| i.dimensions()).collect();
+        let (width, height, positions) = pack_sprites(&sizes);
+        let mut pixels: Vec<u8> = vec![0; width as usize * height as usize * 4];
+        let mut regions: HashMap<String, [f32; 4]> = HashMap::new();
+        for (i, image) in images.iter().enumerate() {
+            let (x0, y0) = positions[i];
+            let (w, h) = sizes[i];
+            let row_length: usize = w as usize * 4;
+            for row in 0..h as usize {
+                let source: usize = row * row_length;
+                let target: usize = ((y0 as usize + row) * width as usize + x0 as usize) * 4;
+                pixels[target..target + row_length].copy_from_slice(&image[source..source + row_length]);
+            }
+            regions.insert(blobs[i].name.clone(), [x0 as f32 / width as f32,
+                                                   y0 as f32 / height as f32,
+                                                   (x0 + w) as f32 / width as f32,
+                                                   (y0 + h) as f32 / height as f32]);
+        }
+        let image = RawImage2d::from_raw_rgba(pixels, (width, height));
+        let texture: SrgbTexture2d = try!(
+            SrgbTexture2d::with_mipmaps(display, image, MipmapsOption::NoMipmap)
+                .map_err(RendererError::TextureCreationError)
+        );
+        Ok(SpriteAtlas {
+            regions: regions,
+            texture: texture,
+        })
+    }
+}
+
+/// The 2D layer drawn by a `Renderer` over the scene, below text
+///
+/// The shader is compiled the first time the overlay is drawn. The atlas is rebuilt when an item
+/// uses a texture which isn't in it, or after a texture is replaced.
+///
+pub struct Overlay {
+    pub batch: RefCell<SpriteBatch>,
+    atlas: RefCell<Option<SpriteAtlas>>,
+    program: RefCell<Option<Program>>,
+}
+
+impl Overlay {
+    pub fn new() -> Overlay {
+        Overlay {
+            batch: RefCell::new(SpriteBatch::new()),
+            atlas: RefCell::new(None),
+            program: RefCell::new(None),
+        }
+    }
+
+    /// Drop the atlas, so it is rebuilt with the current textures when the overlay is next drawn
+    ///
+    pub fn clear_atlas(&self) {
+        *self.atlas.borrow_mut() = None;
+    }
+
+    fn create_program(display: &GlutinFacade) -> Result<Program, Error> {
+        let program = try!(
+            Program::from_source(display, r#"
+#version 130
+
+in vec2 position;
+in vec2 texcoord;
+in vec4 color;
+in float textured;
+
+uniform vec2 screen;
+
+out vec2 v_texcoord;
+out vec4 v_color;
+out float v_textured;
+
+void main() {
+	v_texcoord = texcoord;
+	v_color = color;
+	v_textured = textured;
+	gl_Position = vec4(position.x / screen.x * 2.0 - 1.0, 1.0 - position.y / screen.y * 2.0, 0.0, 1.0);
+}
+"#, r#"
+#version 130
+
+in vec2 v_texcoord;
+in vec4 v_color;
+in float v_textured;
+
+uniform sampler2D image;
+
+out vec4 color;
+
+void main() {
+	color = mix(vec4(1.0), texture(image, v_texcoord), v_textured) * v_color;
+}
+"#, None).map_err(RendererError::ProgramCreationError)
+        );
+        Ok(program)
+    }
+}
+
+impl Renderer {
+    fn overlay_texture(&self, name: &str) -> Result<&glium::texture::CompressedSrgbTexture2d, Error> {
+        match self.textures.get(name) {
+            Some(t) => Ok(t),
+            None => Err(Error::Io(io::Error::new(io::ErrorKind::NotFound, format!("Unable to find texture {}", name)))),
+        }
+    }
+
+    /// Draw a rectangle over the next frame drawn by `render` or `render_viewports`
+    ///
+    pub fn draw_rect(&self, x: f32, y: f32, width: f32, height: f32, color: [f32; 4], z: i32) {
+        self.overlay.batch.borrow_mut().rect(x, y, width, height, color, z);
+    }
+
+    /// Draw a line over the next frame
+    ///
+    pub fn draw_line(&self, x0: f32, y0: f32, x1: f32, y1: f32, width: f32, color: [f32; 4], z: i32) {
+        self.overlay.batch.borrow_mut().line(x0, y0, x1, y1, width, color, z);
+    }
+
+    /// Draw part of a texture over the next frame, see `SpriteBatch::sprite`
+    ///
+    pub fn draw_sprite(&self,
+                       texture: &str,
+                       x: f32,
+                       y: f32,
+                       width: f32,
+                       height: f32,
+                       source: [f32; 4],
+                       color: [f32; 4],
+                       z: i32)
+                       -> Result<(), Error> {
+        try!(self.overlay_texture(texture));
+        self.overlay.batch.borrow_mut().sprite(texture, x, y, width, height, source, color, z);
+        Ok(())
+    }
+
+    /// Draw a whole texture over the next frame
+    ///
+    pub fn draw_image(&self,
+                      texture: &str,
+                      x: f32,
+                      y: f32,
+                      width: f32,
+                      height: f32,
+                      color: [f32; 4],
+                      z: i32)
+                      -> Result<(), Error> {
+        self.draw_sprite(texture, x, y, width, height, [0.0, 0.0, 1.0, 1.0], color, z)
+    }
+
+    /// Make sure the overlay atlas contains the textures in `names` and the blank texture
+    ///
+    fn update_overlay_atlas(&self, display: &GlutinFacade, names: Vec<String>) -> Result<(), Error> {
+        let mut names: Vec<String> = names;
+        match *self.overlay.atlas.borrow() {
+            Some(ref atlas) => {
+                if names.iter().all(|n| atlas.regions.contains_key(n)) {
+                    return Ok(());
+                }
+                // Keep the textures already in the atlas so alternating images don't rebuild it
+                names.extend(atlas.regions.keys().cloned());
+            },
+            None => (),
+        }
+        names.push(String::from(DEFAULT_BLANK_TEXTURE));
+        names.sort();
+        names.dedup();
+        let mut blobs: Vec<&ImageBlob> = Vec::with_capacity(names.len());
+        for name in names.iter() {
+            match self.scene.images.iter().find(|i| &i.name == name) {
+                Some(blob) => blobs.push(blob),
+                None => {
+                    return Err(Error::Io(io::Error::new(io::ErrorKind::NotFound,
+                                                        format!("Unable to find texture {}", name))))
+                },
+            }
+        }
+        *self.overlay.atlas.borrow_mut() = Some(try!(SpriteAtlas::new(display, &blobs)));
+        Ok(())
+    }
+
+    /// Compile the overlay shader, unless it already exists
+    ///
+    pub fn load_overlay_program(&self, display: &GlutinFacade) -> Result<(), Error> {
+        if self.overlay.program.borrow().is_none() {
+            *self.overlay.program.borrow_mut() = Some(try!(Overlay::create_program(display)));
+        }
+        Ok(())
+    }
+
+    /// Draw the queued overlay items with a single draw call, the queue is kept
+    ///
+    pub fn draw_overlay<S: Surface>(&self,
+                                    display: &GlutinFacade,
+                                    target: &mut S,
+                                    query: Option<&TimeElapsedQuery>)
+                                    -> Result<(), Error> {
+        self.draw_sprite_batch(display, target, &self.overlay.batch.borrow(), query)
+    }
+
+    /// Draw the items of a `SpriteBatch` with the overlay shader and atlas in a single draw call
+    ///
+    pub fn draw_sprite_batch<S: Surface>(&self,
+                                         display: &GlutinFacade,
+                                         target: &mut S,
+                                         batch: &SpriteBatch,
+                                         query: Option<&TimeElapsedQuery>)
+                                         -> Result<(), Error> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        try!(self.update_overlay_atlas(display, batch.textures()));
+        try!(self.load_overlay_program(display));
+        let program_ref = self.overlay.program.borrow();
+        let program: &Program = match *program_ref {
+            Some(ref p) => p,
+            None => unreachable!(),
+        };
+        let atlas_ref = self.overlay.atlas.borrow();
+        let atlas: &SpriteAtlas = match *atlas_ref {
+            Some(ref a) => a,
+            None => unreachable!(),
+        };
+        let vertices: Vec<SpriteVertex> = batch.build(&atlas.regions);
+        let vertex_buffer = try!(
+            glium::VertexBuffer::new(display, &vertices).map_err(RendererError::VertexBufferCreationError)
+        );
+        let (width, height) = target.get_dimensions();
+        let uniforms = uniform! {
+            screen: [width as f32, height as f32],
+            image: atlas.texture.sampled()
+                .minify_filter(MinifySamplerFilter::Linear)
+                .magnify_filter(MagnifySamplerFilter::Linear),
+        };
+        let params = glium::DrawParameters {
+            blend: glium::Blend::alpha_blending(),
+            time_elapsed_query: query,
+            .. Default::default()
+        };
+        try!(target.draw(&vertex_buffer,
+                         &glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList),
+                         program,
+                         &uniforms,
+                         &params).map_err(RendererError::DrawError));
+        self.stats.borrow_mut().record_draw_call(vertices.len());
+        Ok(())
+    }
+
+    /// Remove the queued overlay items, called once a frame has been drawn
+    ///
+    pub fn clear_overlay(&self) {
+        self.overlay.batch.borrow_mut().clear();
+    }
+}
+
+/// `extern bool renderer_add_texture(Renderer renderer, Display display, const char* name, const unsigned char* data, int length);`
+///
+/// Add an encoded image, such as the contents of a PNG file, for drawing with `renderer_draw_image`
+/// or as a material texture. A texture with the same name is replaced.
+///
+#[no_mangle]
+pub extern "C" fn renderer_add_texture(renderer: &mut Renderer,
+                                       display: &GlutinFacade,
+                                       name_cstr: *const libc::c_char,
+                                       data: *const libc::c_uchar,
+                                       length: libc::c_int)
+                                       -> bool {
+    ffi::guard_result_status(|| {
+        if data.is_null() || length <= 0 {
+            return Err(Error::InvalidArgument(String::from("Invalid image data")));
+        }
+        let image: Vec<u8> = unsafe { slice::from_raw_parts(data, length as usize) }.to_vec();
+        renderer.add_image(display, &try!(ffi::c_string(name_cstr)), image)
+    })
+}
+
+/// `extern bool renderer_draw_rect(Renderer renderer, float x, float y, float width, float height, float r, float g, float b, float a, int z);`
+///
+/// Draw a rectangle over the next frame, positions are in pixels from the top left of the window.
+/// Items with a greater `z` are drawn on top.
+///
+#[no_mangle]
+pub extern "C" fn renderer_draw_rect(renderer: &Renderer,
+                                     x: libc::c_float,
+                                     y: libc::c_float,
+                                     width: libc::c_float,
+                                     height: libc::c_float,
+                                     r: libc::c_float,
+                                     g: libc::c_float,
+                                     b: libc::c_float,
+                                     a: libc::c_float,
+                                     z: libc::c_int)
+                                     -> bool {
+    ffi::guard_status(|| renderer.draw_rect(x, y, width, height, [r, g, b, a], z))
+}
+
+/// `extern bool renderer_draw_line(Renderer renderer, float x0, float y0, float x1, float y1, float width, float r, float g, float b, float a, int z);`
+///
+#[no_mangle]
+pub extern "C" fn renderer_draw_line(renderer: &Renderer,
+                                     x0: libc::c_float,
+                                     y0: libc::c_float,
+                                     x1: libc::c_float,
+                                     y1: libc::c_float,
+                                     width: libc::c_float,
+                                     r: libc::c_float,
+                                     g: libc::c_float,
+                                     b: libc::c_float,
+                                     a: libc::c_float,
+                                     z: libc::c_int)
+                                     -> bool {
+    ffi::guard_status(|| renderer.draw_line(x0, y0, x1, y1, width, [r, g, b, a], z))
+}
+
+/// `extern bool renderer_draw_image(Renderer renderer, const char* texture, float x, float y, float width, float height, const float* source, float r, float g, float b, float a, int z);`
+///
+/// Draw a texture over the next frame. `source` is the part of the texture to draw as 4 floats,
+/// `u0, v0, u1, v1` from 0 to 1 starting at the top left of the image, or NULL for all of it.
+/// The color tints the texture, white leaves it unchanged.
+///
+#[no_mangle]
+pub extern "C" fn renderer_draw_image(renderer: &Renderer,
+                                      texture: *const libc::c_char,
+                                      x: libc::c_float,
+                                      y: libc::c_float,
+                                      width: libc::c_float,
+                                      height: libc::c_float,
+                                      source: *const libc::c_float,
+                                      r: libc::c_float,
+                                      g: libc::c_float,
+                                      b: libc::c_float,
+                                      a: libc::c_float,
+                                      z: libc::c_int)
+                                      -> bool {
+    ffi::guard_result_status(|| {
+        let source: [f32; 4] = match source.is_null() {
+            true => [0.0, 0.0, 1.0, 1.0],
+            false => {
+                let s: &[f32] = unsafe { slice::from_raw_parts(source, 4) };
+                [s[0], s[1], s[2], s[3]]
+            },
+        };
+        renderer.draw_sprite(&try!(ffi::c_string(texture)), x, y, width, height, source, [r, g, b, a], z)
+    })
+}
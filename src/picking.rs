@@ -0,0 +1,309 @@
+// Copyright (C) 2016 Chris Liebert
+
+extern crate libc;
+
+use std::collections::BTreeSet;
+use std::io;
+
+use glium;
+use glium::backend::glutin_backend::GlutinFacade;
+use glium::draw_parameters::TimeElapsedQuery;
+use glium::framebuffer::{DepthRenderBuffer, SimpleFrameBuffer};
+use glium::texture::{DepthFormat, MipmapsOption, UncompressedUintFormat, UnsignedTexture2d};
+use glium::{Program, Rect, Surface};
+
+use frustum_query::frustum::Frustum;
+
+use camera::Camera;
+use input::Mouse;
+use error::Error;
+use renderer::{Renderer, RendererError};
+use ffi;
+
+/// The id drawn into the `PickingBuffer` for the `Mesh` at `index` in `Scene::meshes`
+///
+pub fn encode_id(index: usize) -> u32 {
+    index as u32 + 1
+}
+
+/// The index of the `Mesh` which drew an id, or `None` where no `Mesh` was drawn
+///
+pub fn decode_id(id: u32) -> Option<usize> {
+    match id {
+        0 => None,
+        id => Some(id as usize - 1),
+    }
+}
+
+/// The indices of the meshes drawn in a block of ids, in ascending order without duplicates
+///
+pub fn decode_region(ids: &[Vec<u32>]) -> Vec<usize> {
+    let mut selected: BTreeSet<usize> = BTreeSet::new();
+    for row in ids.iter() {
+        for id in row.iter() {
+            if let Some(index) = decode_id(*id) {
+                selected.insert(index);
+            }
+        }
+    }
+    selected.into_iter().collect()
+}
+
+/// Offscreen render target used for pixel-exact selection
+///
+/// Each visible `Mesh` is drawn into an unsigned integer texture with it's index in
+/// `Scene::meshes` plus one, zero marks pixels where no `Mesh` was drawn.
+///
+pub struct PickingBuffer {
+    pub width: u32,
+    pub height: u32,
+    pub id_texture: UnsignedTexture2d,
+    pub depth_buffer: DepthRenderBuffer,
+    pub program: Program,
+}
+
+impl PickingBuffer {
+    /// Create a new `PickingBuffer` with the dimensions of the window
+    ///
+    pub fn new(display: &GlutinFacade, width: u32, height: u32) -> Result<PickingBuffer, Error> {
+        if width == 0 || height == 0 {
+            return Err(Error::InvalidArgument(String::from("The picking buffer must not be empty")));
+        }
+        let id_texture = try!(
+            UnsignedTexture2d::empty_with_format(display,
+                                                 UncompressedUintFormat::U32,
+                                                 MipmapsOption::NoMipmap,
+                                                 width,
+                                                 height)
+                .map_err(RendererError::TextureCreationError)
+        );
+        let depth_buffer = try!(
+            DepthRenderBuffer::new(display, DepthFormat::I24, width, height)
+                .map_err(RendererError::RenderBufferCreationError)
+        );
+        let program = try!(
+            Program::from_source(display, r#"
+#version 130
+
+in vec3 position;
+
+uniform mat4 projection;
+uniform mat4 modelview;
+uniform mat4 model;
+
+void main() {
+	gl_Position = projection * modelview * model * vec4(position, 1.0);
+}
+"#, r#"
+#version 130
+
+uniform uint mesh_id;
+
+out uint id;
+
+void main() {
+	id = mesh_id;
+}
+"#, None).map_err(RendererError::ProgramCreationError)
+        );
+        Ok(PickingBuffer {
+            width: width,
+            height: height,
+            id_texture: id_texture,
+            depth_buffer: depth_buffer,
+            program: program,
+        })
+    }
+
+    /// Read the mesh ids inside of a rectangle in window coordinates (origin at the top-left)
+    ///
+    fn read_ids(&self, left: u32, top: u32, width: u32, height: u32) -> Result<Vec<Vec<u32>>, Error> {
+        let rect = Rect {
+            left: left,
+            bottom: self.height - top - height,
+            width: width,
+            height: height,
+        };
+        let image = match self.id_texture.main_level().first_layer().into_image(None) {
+            Some(image) => image,
+            None => return Err(Error::Io(io::Error::new(io::ErrorKind::Other, "Unable to access picking texture"))),
+        };
+        Ok(image.raw_read::<Vec<Vec<u32>>, u32>(&rect))
+    }
+}
+
+impl Renderer {
+    /// Enable the offscreen picking pass, the ids are redrawn after each call to `render`
+    ///
+    /// This should be called again with the new dimensions when the window is resized.
+    ///
+    pub fn enable_picking(&mut self, display: &GlutinFacade, width: u32, height: u32) -> Result<(), Error> {
+        self.picking = Some(try!(PickingBuffer::new(display, width, height)));
+        Ok(())
+    }
+
+    /// Disable the offscreen picking pass and free the picking textures
+    ///
+    pub fn disable_picking(&mut self) {
+        self.picking = None;
+    }
+
+    /// Draw the mesh ids of the `Scene` into the `PickingBuffer`
+    ///
+    pub fn render_picking(&self,
+                          display: &GlutinFacade,
+                          camera: &Camera,
+                          query: Option<&TimeElapsedQuery>)
+                          -> Result<(), Error> {
+        let picking: &PickingBuffer = match self.picking {
+            Some(ref p) => p,
+            None => return Ok(()),
+        };
+
+        let frustum: Frustum = Frustum::from_modelview_and_projection_2d(
+            &camera.modelview_matrix,
+            &camera.projection_matrix,
+        );
+
+        // Integer attachments can't be cleared with a float color, so zeros are uploaded instead
+        let blank: Vec<Vec<u32>> = vec![vec![0u32; picking.width as usize]; picking.height as usize];
+        picking.id_texture.write(Rect { left: 0, bottom: 0, width: picking.width, height: picking.height }, blank);
+
+        let mut target = try!(
+            SimpleFrameBuffer::with_depth_buffer(display, &picking.id_texture, &picking.depth_buffer)
+                .map_err(RendererError::FrameBufferValidationError)
+        );
+        target.clear_depth(1.0);
+
+        let params = glium::DrawParameters {
+            depth: glium::Depth {
+                test: glium::DepthTest::IfLess,
+                write: true,
+                .. Default::default()
+            },
+            time_elapsed_query: query,
+            .. Default::default()
+        };
+
+        for i in 0..self.vertex_buffers.len() as usize {
+            if !self.is_mesh_visible(i, &frustum) {
+                continue;
+            }
+            let matrix = *self.scene.meshes[i].matrix.borrow();
+            let uniforms = uniform! {
+                projection: camera.projection_matrix,
+                modelview: camera.modelview_matrix,
+                model: *(matrix).as_ref(),
+                mesh_id: encode_id(i),
+            };
+            try!(target.draw(
+                &self.vertex_buffers[i],
+                &self.index_buffer,
+                &picking.program,
+                &uniforms,
+                &params).map_err(RendererError::DrawError)
+            );
+        }
+        Ok(())
+    }
+
+    /// Find the index of the `Mesh` drawn at the window coordinates (x, y) during the last picking pass
+    ///
+    pub fn pick(&self, x: i32, y: i32) -> Result<Option<usize>, Error> {
+        let picking: &PickingBuffer = match self.picking {
+            Some(ref p) => p,
+            None => return Ok(None),
+        };
+        if x < 0 || y < 0 || x as u32 >= picking.width || y as u32 >= picking.height {
+            return Ok(None);
+        }
+        let ids = try!(picking.read_ids(x as u32, y as u32, 1, 1));
+        Ok(decode_id(ids[0][0]))
+    }
+
+    /// Find the indices of every `Mesh` drawn inside of a rectangle for box selection
+    ///
+    /// The corners are window coordinates and may be given in any order, the rectangle is
+    /// clipped to the window. Indices are returned in ascending order without duplicates.
+    ///
+    pub fn pick_region(&self, x0: i32, y0: i32, x1: i32, y1: i32) -> Result<Vec<usize>, Error> {
+        let picking: &PickingBuffer = match self.picking {
+            Some(ref p) => p,
+            None => return Ok(Vec::new()),
+        };
+        if picking.width == 0 || picking.height == 0 {
+            return Err(Error::InvalidArgument(String::from("The picking region is empty")));
+        }
+        let clamp_x = |x: i32| -> u32 { x.max(0).min(picking.width as i32 - 1) as u32 };
+        let clamp_y = |y: i32| -> u32 { y.max(0).min(picking.height as i32 - 1) as u32 };
+        let left: u32 = clamp_x(x0.min(x1));
+        let right: u32 = clamp_x(x0.max(x1));
+        let top: u32 = clamp_y(y0.min(y1));
+        let bottom: u32 = clamp_y(y0.max(y1));
+        let ids = try!(picking.read_ids(left, top, right - left + 1, bottom - top + 1));
+        Ok(decode_region(&ids))
+    }
+}
+
+/// `extern bool renderer_enable_picking(Renderer renderer, Display display, int screen_width, int screen_height);`
+///
+#[no_mangle]
+pub extern "C" fn renderer_enable_picking(renderer: &mut Renderer,
+                                          display: &GlutinFacade,
+                                          screen_width: libc::int32_t,
+                                          screen_height: libc::int32_t) -> bool {
+    ffi::guard_result_status(|| {
+        if screen_width <= 0 || screen_height <= 0 {
+            return Err(Error::InvalidArgument(format!("Invalid picking buffer size {}x{}", screen_width, screen_height)));
+        }
+        renderer.enable_picking(display, screen_width as u32, screen_height as u32)
+    })
+}
+
+/// `extern bool renderer_disable_picking(Renderer renderer);`
+///
+#[no_mangle]
+pub extern "C" fn renderer_disable_picking(renderer: &mut Renderer) -> bool {
+    ffi::guard_status(|| {
+        renderer.disable_picking();
+    })
+}
+
+/// `extern int renderer_pick(Renderer renderer, Mouse* mouse);`
+///
+/// Returns the index of the `Mesh` under the mouse, or -1 if there is none.
+///
+#[no_mangle]
+pub extern "C" fn renderer_pick(renderer: &Renderer, mouse: &Mouse) -> libc::int32_t {
+    ffi::guard_result_or(-1, || {
+        match try!(renderer.pick(mouse.x, mouse.y)) {
+            Some(i) => Ok(i as libc::int32_t),
+            None => Ok(-1),
+        }
+    })
+}
+
+/// `extern int renderer_pick_region(Renderer renderer, int x0, int y0, int x1, int y1, int* indices, int capacity);`
+///
+/// Writes up to `capacity` mesh indices into `indices` and returns the total number of meshes
+/// inside of the region.
+///
+#[no_mangle]
+pub extern "C" fn renderer_pick_region(renderer: &Renderer,
+                                       x0: libc::int32_t,
+                                       y0: libc::int32_t,
+                                       x1: libc::int32_t,
+                                       y1: libc::int32_t,
+                                       indices: *mut libc::int32_t,
+                                       capacity: libc::int32_t)
+                                       -> libc::int32_t {
+    ffi::guard_result_or(-1, || {
+        let selected: Vec<usize> = try!(renderer.pick_region(x0, y0, x1, y1));
+        if !indices.is_null() {
+            for (i, index) in selected.iter().take(capacity.max(0) as usize).enumerate() {
+                unsafe { *indices.offset(i as isize) = *index as libc::int32_t };
+            }
+        }
+        Ok(selected.len() as libc::int32_t)
+    })
+}
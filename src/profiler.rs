@@ -0,0 +1,354 @@
+// Copyright (C) 2016 Chris Liebert
+
+extern crate libc;
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::time::Instant;
+
+use glium::backend::glutin_backend::GlutinFacade;
+use glium::draw_parameters::TimeElapsedQuery;
+use glium::{Program, Rect, Surface};
+
+use rustc_serialize::json::Json;
+
+use camera::Camera;
+use common;
+use error::Error;
+use renderer::Renderer;
+use ffi;
+
+/// The number of frames kept for export
+///
+pub const PROFILER_HISTORY: usize = 300;
+
+/// A timed section of a frame
+///
+/// Times are in microseconds, `start` is measured from the creation of the `Profiler`.
+/// `depth` is the number of scopes the scope is nested in. The GPU time is only known for
+/// render passes when GPU timing is enabled, and arrives a few frames later.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProfileScope {
+    pub name: String,
+    pub depth: u32,
+    pub start: f64,
+    pub duration: f64,
+    pub gpu_duration: Option<f64>,
+}
+
+/// The scopes recorded between the end of two frames
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProfileFrame {
+    pub index: u64,
+    pub scopes: Vec<ProfileScope>,
+}
+
+struct ProfilerState {
+    enabled: bool,
+    gpu_timing: bool,
+    epoch: Instant,
+    frame_index: u64,
+    scopes: Vec<ProfileScope>,
+    /// Indices in `scopes` of the scopes which are still open
+    stack: Vec<usize>,
+    frames: VecDeque<ProfileFrame>,
+    /// GPU queries waiting for their results, with the frame and scope they belong to
+    pending: Vec<(u64, usize, TimeElapsedQuery)>,
+}
+
+/// Records hierarchical CPU scopes and GPU pass times for each frame
+///
+/// Scopes are opened and closed in nested order, either with `begin` and `end` or with the
+/// guard returned by `scope`. Frames are delimited by `end_frame`, which the `Renderer` calls
+/// at the end of `render` and `render_viewports`. The profiler does nothing until enabled.
+///
+pub struct Profiler {
+    state: RefCell<ProfilerState>,
+}
+
+/// Closes a scope when it goes out of scope
+///
+pub struct ScopeGuard<'a> {
+    profiler: &'a Profiler,
+}
+
+impl<'a> Drop for ScopeGuard<'a> {
+    fn drop(&mut self) {
+        self.profiler.end();
+    }
+}
+
+impl ProfilerState {
+    fn now(&self) -> f64 {
+        common::microseconds(self.epoch.elapsed())
+    }
+
+    fn set_gpu_duration(&mut self, frame_index: u64, scope: usize, nanoseconds: u32) {
+        let duration: Option<f64> = Some(nanoseconds as f64 / 1000.0);
+        if frame_index == self.frame_index {
+            self.scopes[scope].gpu_duration = duration;
+        } else if let Some(frame) = self.frames.iter_mut().find(|f| f.index == frame_index) {
+            frame.scopes[scope].gpu_duration = duration;
+        }
+    }
+
+    /// Read the results of the finished queries, or wait for all of them
+    ///
+    fn resolve(&mut self, wait: bool) {
+        let pending: Vec<(u64, usize, TimeElapsedQuery)> = self.pending.drain(..).collect();
+        for (frame_index, scope, query) in pending {
+            if wait || query.is_ready() {
+                let nanoseconds: u32 = query.get();
+                self.set_gpu_duration(frame_index, scope, nanoseconds);
+            } else {
+                self.pending.push((frame_index, scope, query));
+            }
+        }
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler {
+            state: RefCell::new(ProfilerState {
+                enabled: false,
+                gpu_timing: false,
+                epoch: Instant::now(),
+                frame_index: 0,
+                scopes: Vec::new(),
+                stack: Vec::new(),
+                frames: VecDeque::with_capacity(PROFILER_HISTORY),
+                pending: Vec::new(),
+            }),
+        }
+    }
+
+    /// Start recording, `gpu_timing` adds a time elapsed query to each render pass
+    ///
+    pub fn enable(&self, gpu_timing: bool) {
+        let mut state = self.state.borrow_mut();
+        state.enabled = true;
+        state.gpu_timing = gpu_timing;
+    }
+
+    /// Stop recording, the captured frames are kept
+    ///
+    pub fn disable(&self) {
+        let mut state = self.state.borrow_mut();
+        state.enabled = false;
+        state.scopes.clear();
+        state.stack.clear();
+        // The queries of the unfinished frame refer to the scopes which were just removed
+        let frame_index: u64 = state.frame_index;
+        state.pending.retain(|&(index, _, _)| index != frame_index);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.state.borrow().enabled
+    }
+
+    /// Remove the captured frames
+    ///
+    pub fn clear(&self) {
+        let mut state = self.state.borrow_mut();
+        state.frames.clear();
+        state.pending.clear();
+    }
+
+    pub fn begin(&self, name: &str) {
+        let mut state = self.state.borrow_mut();
+        if !state.enabled {
+            return;
+        }
+        let scope: ProfileScope = ProfileScope {
+            name: String::from(name),
+            depth: state.stack.len() as u32,
+            start: state.now(),
+            duration: 0.0,
+            gpu_duration: None,
+        };
+        state.scopes.push(scope);
+        let index: usize = state.scopes.len() - 1;
+        state.stack.push(index);
+    }
+
+    /// Close the innermost open scope
+    ///
+    pub fn end(&self) {
+        let mut state = self.state.borrow_mut();
+        if let Some(index) = state.stack.pop() {
+            let now: f64 = state.now();
+            let scope: &mut ProfileScope = &mut state.scopes[index];
+            scope.duration = now - scope.start;
+        }
+    }
+
+    /// Open a scope which is closed when the guard is dropped
+    ///
+    pub fn scope<'a>(&'a self, name: &str) -> ScopeGuard<'a> {
+        self.begin(name);
+        ScopeGuard { profiler: self }
+    }
+
+    /// Create a query to time the GPU work of a pass, if GPU timing is enabled and supported
+    ///
+    pub fn gpu_query(&self, display: &GlutinFacade) -> Option<TimeElapsedQuery> {
+        let state = self.state.borrow();
+        match state.enabled && state.gpu_timing && !state.stack.is_empty() {
+            true => TimeElapsedQuery::new(display).ok(),
+            false => None,
+        }
+    }
+
+    /// Run a render pass in a profiling scope, with a query for its GPU time when GPU timing is enabled
+    ///
+    pub fn profile_pass<T, F>(&self, display: &GlutinFacade, name: &str, pass: F) -> T
+        where F: FnOnce(Option<&TimeElapsedQuery>) -> T
+    {
+        let _scope = self.scope(name);
+        let query: Option<TimeElapsedQuery> = self.gpu_query(display);
+        let result: T = pass(query.as_ref());
+        if let Some(query) = query {
+            self.attach_gpu_query(query);
+        }
+        result
+    }
+
+    /// Give the query used by a pass to the innermost open scope
+    ///
+    pub fn attach_gpu_query(&self, query: TimeElapsedQuery) {
+        let mut state = self.state.borrow_mut();
+        let scope: Option<usize> = state.stack.last().cloned();
+        if let Some(scope) = scope {
+            let frame_index: u64 = state.frame_index;
+            state.pending.push((frame_index, scope, query));
+        }
+    }
+
+    /// Close any open scopes and store the frame
+    ///
+    pub fn end_frame(&self) {
+        if !self.is_enabled() {
+            return;
+        }
+        while !self.state.borrow().stack.is_empty() {
+            self.end();
+        }
+        let mut state = self.state.borrow_mut();
+        let frame: ProfileFrame = ProfileFrame {
+            index: state.frame_index,
+            scopes: state.scopes.drain(..).collect(),
+        };
+        if state.frames.len() == PROFILER_HISTORY {
+            let oldest: u64 = state.frames.pop_front().map(|f| f.index).unwrap_or(0);
+            state.pending.retain(|&(index, _, _)| index != oldest);
+        }
+        state.frames.push_back(frame);
+        state.frame_index += 1;
+        state.resolve(false);
+    }
+
+    /// The captured frames, oldest first
+    ///
+    pub fn frames(&self) -> Vec<ProfileFrame> {
+        self.state.borrow().frames.iter().cloned().collect()
+    }
+
+    /// Write the captured frames in the Chrome trace event format
+    ///
+    /// The file can be opened in chrome://tracing. CPU scopes are on thread 1 and GPU pass
+    /// times on thread 2, a GPU event starts with the CPU scope which submitted it.
+    ///
+    pub fn to_chrome_trace(&self) -> String {
+        self.state.borrow_mut().resolve(true);
+        let state = self.state.borrow();
+        let mut events: Vec<String> = Vec::new();
+        for frame in state.frames.iter() {
+            for scope in frame.scopes.iter() {
+                let name: String = Json::String(scope.name.clone()).to_string();
+                events.push(format!("{{\"name\":{},\"cat\":\"cpu\",\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\
+                                     \"pid\":1,\"tid\":1,\"args\":{{\"frame\":{}}}}}",
+                                    name, scope.start, scope.duration, frame.index));
+                if let Some(gpu_duration) = scope.gpu_duration {
+                    events.push(format!("{{\"name\":{},\"cat\":\"gpu\",\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\
+                                         \"pid\":1,\"tid\":2,\"args\":{{\"frame\":{}}}}}",
+                                        name, scope.start, gpu_duration, frame.index));
+                }
+            }
+        }
+        format!("{{\"traceEvents\":[\n{}\n],\"displayTimeUnit\":\"ms\"}}\n", events.join(",\n"))
+    }
+
+    pub fn save_chrome_trace(&self, filename: &str) -> Result<(), Error> {
+        let file = try!(File::create(filename));
+        let mut writer = BufWriter::new(file);
+        try!(writer.write_all(self.to_chrome_trace().as_bytes()));
+        Ok(())
+    }
+}
+
+impl Renderer {
+    /// Draw the `Scene` in a profiling scope, timed on the GPU when GPU timing is enabled
+    ///
+    pub fn draw_profiled<S: Surface>(&self,
+                                     display: &GlutinFacade,
+                                     target: &mut S,
+                                     program: &Program,
+                                     camera: &Camera,
+                                     viewport: Option<Rect>,
+                                     name: &str)
+                                     -> Result<(), Error> {
+        self.profiler.profile_pass(display, name, |query| {
+            self.draw_with_query(target, program, camera, viewport, query)
+        })
+    }
+}
+
+/// `extern bool renderer_enable_profiling(Renderer renderer, bool gpu_timing);`
+///
+#[no_mangle]
+pub extern "C" fn renderer_enable_profiling(renderer: &Renderer, gpu_timing: bool) -> bool {
+    ffi::guard_status(|| renderer.profiler.enable(gpu_timing))
+}
+
+/// `extern bool renderer_disable_profiling(Renderer renderer);`
+///
+#[no_mangle]
+pub extern "C" fn renderer_disable_profiling(renderer: &Renderer) -> bool {
+    ffi::guard_status(|| renderer.profiler.disable())
+}
+
+/// `extern bool renderer_profile_begin(Renderer renderer, const char* name);`
+///
+/// Open a profiling scope, which must be closed with `renderer_profile_end`.
+///
+#[no_mangle]
+pub extern "C" fn renderer_profile_begin(renderer: &Renderer, name_cstr: *const libc::c_char) -> bool {
+    ffi::guard_result_status(|| {
+        renderer.profiler.begin(&try!(ffi::c_string(name_cstr)));
+        Ok(())
+    })
+}
+
+/// `extern bool renderer_profile_end(Renderer renderer);`
+///
+#[no_mangle]
+pub extern "C" fn renderer_profile_end(renderer: &Renderer) -> bool {
+    ffi::guard_status(|| renderer.profiler.end())
+}
+
+/// `extern bool renderer_save_chrome_trace(Renderer renderer, const char* file);`
+///
+/// Write the captured frames as Chrome trace JSON.
+///
+#[no_mangle]
+pub extern "C" fn renderer_save_chrome_trace(renderer: &Renderer, file: *const libc::c_char) -> bool {
+    ffi::guard_result_status(|| {
+        let filename: String = try!(ffi::c_string(file));
+        renderer.profiler.save_chrome_trace(&filename)
+    })
+}
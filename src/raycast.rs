@@ -0,0 +1,303 @@
+// Copyright (C) 2016 Chris Liebert
+
+extern crate libc;
+
+use nalgebra;
+use nalgebra::{Inverse, Matrix3, Matrix4, Transpose, Vector3, Vector4};
+
+use camera::Camera;
+use common::Mesh;
+use input::Mouse;
+use renderer::Renderer;
+use scene::Scene;
+use ffi;
+
+/// The matrix which transforms normals for a model matrix, the inverse transpose of it's upper 3x3
+///
+/// Unlike the model matrix itself this keeps normals perpendicular to their surface when the
+/// scale is not uniform.
+///
+pub fn normal_matrix(matrix: &Matrix4<f32>) -> Matrix3<f32> {
+    let upper: Matrix3<f32> = Matrix3::new(matrix.m11, matrix.m12, matrix.m13,
+                                           matrix.m21, matrix.m22, matrix.m23,
+                                           matrix.m31, matrix.m32, matrix.m33);
+    match upper.inverse() {
+        Some(inverse) => inverse.transpose(),
+        None => upper,
+    }
+}
+
+/// A half-line in world space
+///
+/// A `Ray` starts at `origin` and extends infinitely along the unit vector `direction`.
+///
+/// ```c
+/// /* C representation */
+/// typedef struct Ray { float origin[3]; float direction[3]; } Ray;
+/// ```
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct Ray {
+    pub origin: [f32; 3],
+    pub direction: [f32; 3],
+}
+
+/// The nearest intersection between a `Ray` and the geometry of a `Scene`
+///
+/// `mesh_index` and `triangle_index` index into `Scene::meshes` and the vertices of that
+/// `Mesh` (three vertices per triangle). `point` and `normal` are in world space and
+/// `texcoord` is interpolated from the vertices using the `barycentric` coordinates of the hit.
+/// When there is no intersection both indices are -1.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct RayHit {
+    pub mesh_index: i32,
+    pub triangle_index: i32,
+    pub distance: f32,
+    pub point: [f32; 3],
+    pub normal: [f32; 3],
+    pub barycentric: [f32; 3],
+    pub texcoord: [f32; 2],
+}
+
+impl RayHit {
+    /// A `RayHit` representing a miss
+    ///
+    pub fn none() -> RayHit {
+        RayHit {
+            mesh_index: -1,
+            triangle_index: -1,
+            distance: 0.0,
+            point: [0.0; 3],
+            normal: [0.0; 3],
+            barycentric: [0.0; 3],
+            texcoord: [0.0; 2],
+        }
+    }
+}
+
+impl Ray {
+    /// Create a new `Ray`, the direction is normalized
+    ///
+    pub fn new(origin: [f32; 3], direction: [f32; 3]) -> Ray {
+        let d: Vector3<f32> = nalgebra::normalize(&Vector3::new(direction[0], direction[1], direction[2]));
+        Ray {
+            origin: origin,
+            direction: [d.x, d.y, d.z],
+        }
+    }
+
+    /// The point at distance `t` along the `Ray`
+    ///
+    pub fn point_at(&self, t: f32) -> [f32; 3] {
+        [self.origin[0] + t * self.direction[0],
+         self.origin[1] + t * self.direction[1],
+         self.origin[2] + t * self.direction[2]]
+    }
+
+    /// Test whether the `Ray` passes through a sphere
+    ///
+    pub fn intersects_sphere(&self, center: &[f32; 3], radius: f32) -> bool {
+        let origin: Vector3<f32> = Vector3::new(self.origin[0], self.origin[1], self.origin[2]);
+        let direction: Vector3<f32> = Vector3::new(self.direction[0], self.direction[1], self.direction[2]);
+        let to_center: Vector3<f32> = Vector3::new(center[0], center[1], center[2]) - origin;
+        let projection: f32 = nalgebra::dot(&to_center, &direction);
+        let distance_squared: f32 = nalgebra::dot(&to_center, &to_center) - projection * projection;
+        if distance_squared > radius * radius {
+            return false;
+        }
+        // The sphere is behind the origin unless the origin lies inside of it
+        projection >= 0.0 || nalgebra::dot(&to_center, &to_center) <= radius * radius
+    }
+
+    /// Möller–Trumbore ray/triangle intersection
+    ///
+    /// Returns the distance along the ray and the barycentric weights of `b` and `c`.
+    ///
+    pub fn intersect_triangle(&self, a: &Vector3<f32>, b: &Vector3<f32>, c: &Vector3<f32>) -> Option<(f32, f32, f32)> {
+        let epsilon: f32 = 0.000001;
+        let origin: Vector3<f32> = Vector3::new(self.origin[0], self.origin[1], self.origin[2]);
+        let direction: Vector3<f32> = Vector3::new(self.direction[0], self.direction[1], self.direction[2]);
+        let edge1: Vector3<f32> = *b - *a;
+        let edge2: Vector3<f32> = *c - *a;
+        let p: Vector3<f32> = nalgebra::cross(&direction, &edge2);
+        let determinant: f32 = nalgebra::dot(&edge1, &p);
+        if determinant.abs() < epsilon {
+            // The ray is parallel to the triangle
+            return None;
+        }
+        let inverse_determinant: f32 = 1.0 / determinant;
+        let s: Vector3<f32> = origin - *a;
+        let u: f32 = nalgebra::dot(&s, &p) * inverse_determinant;
+        if u < 0.0 || u > 1.0 {
+            return None;
+        }
+        let q: Vector3<f32> = nalgebra::cross(&s, &edge1);
+        let v: f32 = nalgebra::dot(&direction, &q) * inverse_determinant;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t: f32 = nalgebra::dot(&edge2, &q) * inverse_determinant;
+        if t < epsilon {
+            return None;
+        }
+        Some((t, u, v))
+    }
+
+    /// Find the nearest triangle of a `Mesh` hit by the `Ray`, using the current matrix of the `Mesh`
+    ///
+    pub fn intersect_mesh(&self, mesh: &Mesh, mesh_index: usize) -> Option<RayHit> {
+        let (center, radius) = mesh.world_bounding_sphere();
+        if !self.intersects_sphere(&center, radius) {
+            return None;
+        }
+        let matrix: Matrix4<f32> = *mesh.matrix.borrow();
+        let normal_matrix: Matrix3<f32> = normal_matrix(&matrix);
+
+        let mut nearest: Option<RayHit> = None;
+        for (triangle_index, triangle) in mesh.vertices.chunks(3).enumerate() {
+            if triangle.len() < 3 {
+                break;
+            }
+            let mut world: [Vector3<f32>; 3] = [Vector3::new(0.0f32, 0.0, 0.0); 3];
+            for i in 0..3 {
+                let p = triangle[i].position;
+                let transformed: Vector4<f32> = matrix * Vector4::new(p[0], p[1], p[2], 1.0f32);
+                world[i] = Vector3::new(transformed.x, transformed.y, transformed.z);
+            }
+            let (t, u, v) = match self.intersect_triangle(&world[0], &world[1], &world[2]) {
+                Some(hit) => hit,
+                None => continue,
+            };
+            match nearest {
+                Some(ref hit) if hit.distance <= t => continue,
+                _ => (),
+            }
+            let w: f32 = 1.0 - u - v;
+            let mut normal: Vector3<f32> = Vector3::new(0.0f32, 0.0, 0.0);
+            let mut texcoord: [f32; 2] = [0.0; 2];
+            for (i, weight) in [w, u, v].iter().enumerate() {
+                let n = triangle[i].normal;
+                normal = normal + normal_matrix * Vector3::new(n[0], n[1], n[2]) * *weight;
+                texcoord[0] += triangle[i].texcoord[0] * *weight;
+                texcoord[1] += triangle[i].texcoord[1] * *weight;
+            }
+            if nalgebra::norm(&normal) == 0.0 {
+                // Fall back to the face normal if the vertex normals are missing
+                normal = nalgebra::cross(&(world[1] - world[0]), &(world[2] - world[0]));
+            }
+            let normal: Vector3<f32> = nalgebra::normalize(&normal);
+            nearest = Some(RayHit {
+                mesh_index: mesh_index as i32,
+                triangle_index: triangle_index as i32,
+                distance: t,
+                point: self.point_at(t),
+                normal: [normal.x, normal.y, normal.z],
+                barycentric: [w, u, v],
+                texcoord: texcoord,
+            });
+        }
+        nearest
+    }
+}
+
+impl Scene {
+    /// Find the nearest `Mesh` intersected by a `Ray`
+    ///
+    pub fn raycast(&self, ray: &Ray) -> Option<RayHit> {
+        let mut nearest: Option<RayHit> = None;
+        for (i, mesh) in self.meshes.iter().enumerate() {
+            match ray.intersect_mesh(mesh, i) {
+                Some(hit) => {
+                    let closer = match nearest {
+                        Some(ref n) => hit.distance < n.distance,
+                        None => true,
+                    };
+                    if closer {
+                        nearest = Some(hit);
+                    }
+                },
+                None => (),
+            }
+        }
+        nearest
+    }
+}
+
+impl Renderer {
+    /// Find the nearest `Mesh` intersected by a `Ray`
+    ///
+    pub fn raycast(&self, ray: &Ray) -> Option<RayHit> {
+        self.scene.raycast(ray)
+    }
+
+    /// Find the nearest `Mesh` under the screen coordinates (x, y)
+    ///
+    pub fn pick_ray(&self, camera: &Camera, x: i32, y: i32, screen_width: f32, screen_height: f32) -> Option<RayHit> {
+        self.raycast(&camera.ray_from_screen(x, y, screen_width, screen_height))
+    }
+}
+
+/// `extern Ray* camera_ray_from_mouse(Camera camera, Mouse* mouse, float screen_width, float screen_height);`
+///
+#[no_mangle]
+pub extern "C" fn camera_ray_from_mouse(camera: &Camera,
+                                        mouse: &Mouse,
+                                        screen_width: libc::c_float,
+                                        screen_height: libc::c_float)
+                                        -> Option<Box<Ray>> {
+    ffi::guard(|| {
+        Box::new(camera.ray_from_screen(mouse.x, mouse.y, screen_width as f32, screen_height as f32))
+    })
+}
+
+/// `extern RayHit* renderer_raycast(Renderer renderer, Ray* ray);`
+///
+#[no_mangle]
+pub extern "C" fn renderer_raycast(renderer: &Renderer, ray: &Ray) -> Option<Box<RayHit>> {
+    ffi::guard(|| {
+        match renderer.raycast(ray) {
+            Some(hit) => Box::new(hit),
+            None => Box::new(RayHit::none()),
+        }
+    })
+}
+
+/// `extern RayHit* renderer_pick_ray(Renderer renderer, Camera camera, Mouse* mouse, float screen_width, float screen_height);`
+///
+#[no_mangle]
+pub extern "C" fn renderer_pick_ray(renderer: &Renderer,
+                                    camera: &Camera,
+                                    mouse: &Mouse,
+                                    screen_width: libc::c_float,
+                                    screen_height: libc::c_float)
+                                    -> Option<Box<RayHit>> {
+    ffi::guard(|| {
+        match renderer.pick_ray(camera, mouse.x, mouse.y, screen_width as f32, screen_height as f32) {
+            Some(hit) => Box::new(hit),
+            None => Box::new(RayHit::none()),
+        }
+    })
+}
+
+/// `extern void free_ray(Ray* ptr);`
+///
+#[no_mangle]
+pub extern "C" fn free_ray(ptr: *mut Ray) {
+    ffi::guard_status(|| {
+        let box_ptr: Box<Ray> = unsafe { Box::from_raw(ptr) };
+        drop(box_ptr);
+    });
+}
+
+/// `extern void free_ray_hit(RayHit* ptr);`
+///
+#[no_mangle]
+pub extern "C" fn free_ray_hit(ptr: *mut RayHit) {
+    ffi::guard_status(|| {
+        let box_ptr: Box<RayHit> = unsafe { Box::from_raw(ptr) };
+        drop(box_ptr);
+    });
+}
@@ -0,0 +1,343 @@
+// Copyright (C) 2016 Chris Liebert
+
+extern crate libc;
+
+use bincode::rustc_serialize::{encode_into, decode_from};
+use bincode::SizeLimit::Infinite;
+use std::fs::File;
+use std::io::{BufWriter, BufReader};
+use std::path::PathBuf;
+use std::time::Instant;
+
+use glium::glutin::{ElementState, Event, MouseScrollDelta, Touch};
+
+use error::Error;
+use input::{EventBuffer, mouse_button_from_index, mouse_button_index, touch_phase_from_index, touch_phase_index,
+            virtual_key_code_from_index};
+use ffi;
+
+/// A serializable copy of a window `Event`
+///
+/// Keys are stored by their `VirtualKeyCode` discriminant and mouse buttons by the index used
+/// across the FFI (0 left, 1 right, 2 middle). Touch phases are numbered in declaration order.
+///
+#[derive(Clone, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum RecordedEvent {
+    Resized(u32, u32),
+    Moved(i32, i32),
+    Closed,
+    DroppedFile(String),
+    ReceivedCharacter(char),
+    Focused(bool),
+    KeyboardInput(bool, u8, Option<u32>),
+    MouseMoved(i32, i32),
+    MouseEntered,
+    MouseLeft,
+    MouseWheelLines(f32, f32, u8),
+    MouseWheelPixels(f32, f32, u8),
+    MouseInput(bool, i32),
+    Touch(u8, f64, f64, u64),
+    Refresh,
+}
+
+impl RecordedEvent {
+    /// Copy an `Event`, events which are not useful to replay are skipped
+    ///
+    pub fn from_event(event: &Event) -> Option<RecordedEvent> {
+        match event {
+            &Event::Resized(w, h) => Some(RecordedEvent::Resized(w, h)),
+            &Event::Moved(x, y) => Some(RecordedEvent::Moved(x, y)),
+            &Event::Closed => Some(RecordedEvent::Closed),
+            &Event::DroppedFile(ref path) => Some(RecordedEvent::DroppedFile(path.to_string_lossy().into_owned())),
+            &Event::ReceivedCharacter(c) => Some(RecordedEvent::ReceivedCharacter(c)),
+            &Event::Focused(focused) => Some(RecordedEvent::Focused(focused)),
+            &Event::KeyboardInput(state, scancode, keycode) => {
+                let key: Option<u32> = keycode.map(|k| k as u32);
+                Some(RecordedEvent::KeyboardInput(state == ElementState::Pressed, scancode, key))
+            },
+            &Event::MouseMoved(x, y) => Some(RecordedEvent::MouseMoved(x, y)),
+            &Event::MouseEntered => Some(RecordedEvent::MouseEntered),
+            &Event::MouseLeft => Some(RecordedEvent::MouseLeft),
+            &Event::MouseWheel(MouseScrollDelta::LineDelta(x, y), phase) => {
+                Some(RecordedEvent::MouseWheelLines(x, y, touch_phase_index(phase) as u8))
+            },
+            &Event::MouseWheel(MouseScrollDelta::PixelDelta(x, y), phase) => {
+                Some(RecordedEvent::MouseWheelPixels(x, y, touch_phase_index(phase) as u8))
+            },
+            &Event::MouseInput(state, button) => {
+                Some(RecordedEvent::MouseInput(state == ElementState::Pressed, mouse_button_index(button)))
+            },
+            &Event::Touch(ref touch) => {
+                Some(RecordedEvent::Touch(touch_phase_index(touch.phase) as u8, touch.location.0, touch.location.1, touch.id))
+            },
+            &Event::Refresh => Some(RecordedEvent::Refresh),
+            _ => None,
+        }
+    }
+
+    /// Recreate the recorded `Event`
+    ///
+    pub fn to_event(&self) -> Event {
+        let state = |pressed: bool| match pressed {
+            true => ElementState::Pressed,
+            false => ElementState::Released,
+        };
+        match self {
+            &RecordedEvent::Resized(w, h) => Event::Resized(w, h),
+            &RecordedEvent::Moved(x, y) => Event::Moved(x, y),
+            &RecordedEvent::Closed => Event::Closed,
+            &RecordedEvent::DroppedFile(ref path) => Event::DroppedFile(PathBuf::from(path)),
+            &RecordedEvent::ReceivedCharacter(c) => Event::ReceivedCharacter(c),
+            &RecordedEvent::Focused(focused) => Event::Focused(focused),
+            &RecordedEvent::KeyboardInput(pressed, scancode, key) => {
+                Event::KeyboardInput(state(pressed), scancode, key.and_then(virtual_key_code_from_index))
+            },
+            &RecordedEvent::MouseMoved(x, y) => Event::MouseMoved(x, y),
+            &RecordedEvent::MouseEntered => Event::MouseEntered,
+            &RecordedEvent::MouseLeft => Event::MouseLeft,
+            &RecordedEvent::MouseWheelLines(x, y, phase) => {
+                Event::MouseWheel(MouseScrollDelta::LineDelta(x, y), touch_phase_from_index(phase as i32))
+            },
+            &RecordedEvent::MouseWheelPixels(x, y, phase) => {
+                Event::MouseWheel(MouseScrollDelta::PixelDelta(x, y), touch_phase_from_index(phase as i32))
+            },
+            &RecordedEvent::MouseInput(pressed, button) => {
+                Event::MouseInput(state(pressed), mouse_button_from_index(button as libc::c_int))
+            },
+            &RecordedEvent::Touch(phase, x, y, id) => {
+                Event::Touch(Touch {
+                    phase: touch_phase_from_index(phase as i32),
+                    location: (x, y),
+                    id: id,
+                })
+            },
+            &RecordedEvent::Refresh => Event::Refresh,
+        }
+    }
+}
+
+/// The events polled during a single frame
+///
+/// `time` is the number of seconds between the start of the recording and the frame.
+///
+#[derive(Clone, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct RecordedFrame {
+    pub frame: u64,
+    pub time: f64,
+    pub events: Vec<RecordedEvent>,
+}
+
+/// A sequence of recorded frames which can be saved and replayed
+///
+#[derive(Clone, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct InputRecording {
+    pub frames: Vec<RecordedFrame>,
+}
+
+impl InputRecording {
+    pub fn new() -> InputRecording {
+        InputRecording { frames: Vec::new() }
+    }
+
+    /// The index of the first frame where two recordings differ
+    ///
+    pub fn first_difference(&self, other: &InputRecording) -> Option<usize> {
+        for i in 0..self.frames.len().max(other.frames.len()) {
+            if self.frames.get(i) != other.frames.get(i) {
+                return Some(i);
+            }
+        }
+        None
+    }
+
+    pub fn from_binary_file(filename: String) -> Result<InputRecording, Error> {
+        let file = try!(File::open(filename.clone()));
+        let mut reader = BufReader::new(file);
+        let recording: InputRecording = try!(decode_from(&mut reader, Infinite));
+        Ok(recording)
+    }
+
+    pub fn to_binary_file(&self, filename: String) -> Result<(), Error> {
+        let file = try!(File::create(filename.clone()));
+        let mut writer = BufWriter::new(file);
+        try!(encode_into(&self, &mut writer, Infinite));
+        Ok(())
+    }
+}
+
+/// Records every `EventBuffer` with the frame number and time it was polled
+///
+/// ```c
+/// /* C representation */
+/// typedef void* InputRecorder;
+/// ```
+///
+pub struct InputRecorder {
+    pub recording: InputRecording,
+    start: Instant,
+}
+
+impl InputRecorder {
+    pub fn new() -> InputRecorder {
+        InputRecorder {
+            recording: InputRecording::new(),
+            start: Instant::now(),
+        }
+    }
+
+    /// Record the events of the next frame, frames without events are recorded as well
+    ///
+    pub fn record(&mut self, events: &EventBuffer) {
+        let elapsed = self.start.elapsed();
+        let time: f64 = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1000000000.0;
+        self.record_at(time, events);
+    }
+
+    /// Record the events of the next frame at a given time
+    ///
+    pub fn record_at(&mut self, time: f64, events: &EventBuffer) {
+        let frame: u64 = self.recording.frames.len() as u64;
+        self.recording.frames.push(RecordedFrame {
+            frame: frame,
+            time: time,
+            events: events.events().iter().filter_map(RecordedEvent::from_event).collect(),
+        });
+    }
+}
+
+/// A synthetic event source which returns the recorded frames in order
+///
+/// ```c
+/// /* C representation */
+/// typedef void* InputReplay;
+/// ```
+///
+pub struct InputReplay {
+    pub recording: InputRecording,
+    pub next_frame: usize,
+}
+
+impl InputReplay {
+    pub fn new(recording: InputRecording) -> InputReplay {
+        InputReplay {
+            recording: recording,
+            next_frame: 0,
+        }
+    }
+
+    pub fn finished(&self) -> bool {
+        self.next_frame >= self.recording.frames.len()
+    }
+
+    /// The recorded time of the most recently returned frame
+    ///
+    pub fn time(&self) -> f64 {
+        match self.next_frame {
+            0 => 0.0,
+            n => self.recording.frames[n - 1].time,
+        }
+    }
+
+    /// The events of the next frame, in place of `EventBuffer::new`
+    ///
+    pub fn next_events(&mut self) -> Option<EventBuffer> {
+        let events: Vec<Event> = match self.recording.frames.get(self.next_frame) {
+            Some(frame) => frame.events.iter().map(RecordedEvent::to_event).collect(),
+            None => return None,
+        };
+        self.next_frame += 1;
+        Some(EventBuffer::from_events(events))
+    }
+}
+
+/// `extern InputRecorder create_input_recorder();`
+///
+#[no_mangle]
+pub extern "C" fn create_input_recorder() -> Option<Box<InputRecorder>> {
+    ffi::guard(|| {
+        Box::new(InputRecorder::new())
+    })
+}
+
+/// `extern void free_input_recorder(InputRecorder ptr);`
+///
+#[no_mangle]
+pub extern "C" fn free_input_recorder(ptr: *mut InputRecorder) {
+    ffi::guard_status(|| {
+        let box_ptr: Box<InputRecorder> = unsafe { Box::from_raw(ptr) };
+        drop(box_ptr)
+    });
+}
+
+/// `extern bool input_recorder_record(InputRecorder recorder, EventBuffer events);`
+///
+#[no_mangle]
+pub extern "C" fn input_recorder_record(recorder: &mut InputRecorder, events: &EventBuffer) -> bool {
+    ffi::guard_status(|| {
+        recorder.record(events);
+    })
+}
+
+/// `extern bool input_recorder_save(InputRecorder recorder, const char* file);`
+///
+#[no_mangle]
+pub extern "C" fn input_recorder_save(recorder: &InputRecorder, file: *const libc::c_char) -> bool {
+    ffi::guard_result_status(|| {
+        let filename: String = try!(ffi::c_string(file));
+        recorder.recording.to_binary_file(filename)
+    })
+}
+
+/// `extern InputReplay input_replay_from_file(const char* file);`
+///
+#[no_mangle]
+pub extern "C" fn input_replay_from_file(file: *const libc::c_char) -> Option<Box<InputReplay>> {
+    ffi::guard_result(|| -> Result<Box<InputReplay>, Error> {
+        let filename: String = try!(ffi::c_string(file));
+        let recording: InputRecording = try!(InputRecording::from_binary_file(filename));
+        Ok(Box::new(InputReplay::new(recording)))
+    })
+}
+
+/// `extern void free_input_replay(InputReplay ptr);`
+///
+#[no_mangle]
+pub extern "C" fn free_input_replay(ptr: *mut InputReplay) {
+    ffi::guard_status(|| {
+        let box_ptr: Box<InputReplay> = unsafe { Box::from_raw(ptr) };
+        drop(box_ptr)
+    });
+}
+
+/// Get the events of the next recorded frame, an empty `EventBuffer` is returned once the replay
+/// has finished. The `EventBuffer` must be freed with `free_events`.
+///
+/// `extern EventBuffer input_replay_next(InputReplay replay);`
+///
+#[no_mangle]
+pub extern "C" fn input_replay_next(replay: &mut InputReplay) -> Option<Box<EventBuffer>> {
+    ffi::guard(|| {
+        match replay.next_events() {
+            Some(events) => Box::new(events),
+            None => Box::new(EventBuffer::from_events(Vec::new())),
+        }
+    })
+}
+
+/// `extern bool input_replay_finished(InputReplay replay);`
+///
+#[no_mangle]
+pub extern "C" fn input_replay_finished(replay: &InputReplay) -> bool {
+    ffi::guard_or(false, || {
+        replay.finished()
+    })
+}
+
+/// `extern double input_replay_time(InputReplay replay);`
+///
+#[no_mangle]
+pub extern "C" fn input_replay_time(replay: &InputReplay) -> libc::c_double {
+    ffi::guard_or(0.0, || {
+        replay.time() as libc::c_double
+    })
+}
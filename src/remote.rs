@@ -0,0 +1,398 @@
+// Copyright (C) 2016 Chris Liebert
+
+extern crate libc;
+
+use std;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+
+use console::Console;
+use error::Error;
+use ffi;
+
+/// The write half of a client connection
+///
+enum ClientStream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl ClientStream {
+    fn write_all(&mut self, bytes: &[u8]) -> io::Result<()> {
+        match self {
+            &mut ClientStream::Tcp(ref mut s) => s.write_all(bytes),
+            #[cfg(unix)]
+            &mut ClientStream::Unix(ref mut s) => s.write_all(bytes),
+        }
+    }
+
+    fn shutdown(&self) {
+        let _ = match self {
+            &ClientStream::Tcp(ref s) => s.shutdown(Shutdown::Both),
+            #[cfg(unix)]
+            &ClientStream::Unix(ref s) => s.shutdown(Shutdown::Both),
+        };
+    }
+}
+
+/// Where the server is listening
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConsoleAddress {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+/// State shared between the server and it's listener and client threads
+///
+/// `errors` holds the failures of the listener thread until the server is next used, since
+/// the thread can't report them itself.
+///
+struct Shared {
+    pending: Mutex<Vec<(usize, String)>>,
+    clients: Mutex<HashMap<usize, ClientStream>>,
+    client_threads: Mutex<Vec<thread::JoinHandle<()>>>,
+    errors: Mutex<Vec<String>>,
+    running: AtomicBool,
+    next_client: AtomicUsize,
+}
+
+/// Lock a mutex of the shared state, a thread which panicked while holding it leaves the data usable
+///
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Accept a connection on the listener thread, keeping the error if it fails
+///
+fn accept<S, F>(shared: &Arc<Shared>, stream: io::Result<S>, clone: F)
+    where S: io::Read + Send + 'static, F: FnOnce(&S) -> io::Result<ClientStream>
+{
+    let result: io::Result<(S, ClientStream)> = stream.and_then(|s| clone(&s).map(|writer| (s, writer)));
+    match result {
+        Ok((reader, writer)) => add_client(shared, reader, writer),
+        Err(e) => lock(&shared.errors).push(format!("Unable to accept console client: {}", e)),
+    }
+}
+
+/// A console server accepting connections on a local TCP port or Unix socket
+///
+/// Each client connection is read on it's own thread, lines are queued until `process` runs
+/// them through a `Console` on the calling thread and sends the output back to the client that
+/// sent the line. `read` takes the queued lines instead, like `ConsoleInput`, for hosts which
+/// interpret the lines themselves.
+///
+/// The TCP server only binds to the loopback interface.
+///
+/// ```c
+/// /* C representation */
+/// typedef void* ConsoleServer;
+/// ```
+///
+pub struct ConsoleServer {
+    pub address: ConsoleAddress,
+    shared: Arc<Shared>,
+    listener_thread: Option<thread::JoinHandle<()>>,
+}
+
+/// Read lines from a client until it disconnects
+///
+fn read_client<R: io::Read>(shared: Arc<Shared>, id: usize, reader: R) {
+    for line in BufReader::new(reader).lines() {
+        match line {
+            Ok(line) => {
+                let line: String = line.replace("\r", "");
+                lock(&shared.pending).push((id, line));
+            },
+            Err(_) => break,
+        }
+    }
+    lock(&shared.clients).remove(&id);
+}
+
+/// Register a new client and start reading from it
+///
+fn add_client<R: io::Read + Send + 'static>(shared: &Arc<Shared>, reader: R, mut writer: ClientStream) {
+    let id: usize = shared.next_client.fetch_add(1, Ordering::SeqCst);
+    if writer.write_all(b"quick3d console\n> ").is_err() {
+        return;
+    }
+    lock(&shared.clients).insert(id, writer);
+    let thread_shared: Arc<Shared> = shared.clone();
+    let handle = thread::spawn(move || read_client(thread_shared, id, reader));
+    lock(&shared.client_threads).push(handle);
+}
+
+impl ConsoleServer {
+    /// Listen on a TCP port of the loopback interface, port 0 picks an unused port
+    ///
+    pub fn bind_tcp(port: u16) -> io::Result<ConsoleServer> {
+        let listener: TcpListener = try!(TcpListener::bind(("127.0.0.1", port)));
+        let address: SocketAddr = try!(listener.local_addr());
+        let shared: Arc<Shared> = ConsoleServer::shared();
+        let thread_shared: Arc<Shared> = shared.clone();
+        let handle = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if !thread_shared.running.load(Ordering::SeqCst) {
+                    break;
+                }
+                accept(&thread_shared, stream, |s: &TcpStream| s.try_clone().map(ClientStream::Tcp));
+            }
+        });
+        Ok(ConsoleServer {
+            address: ConsoleAddress::Tcp(address),
+            shared: shared,
+            listener_thread: Some(handle),
+        })
+    }
+
+    /// Listen on a Unix socket, a stale socket left at the path is replaced
+    ///
+    /// Fails without touching the file if the path exists and is not a socket.
+    ///
+    #[cfg(unix)]
+    pub fn bind_unix(path: &str) -> io::Result<ConsoleServer> {
+        match std::fs::symlink_metadata(path) {
+            Ok(metadata) => {
+                if !metadata.file_type().is_socket() {
+                    return Err(io::Error::new(io::ErrorKind::AlreadyExists,
+                                              format!("{} exists and is not a socket", path)));
+                }
+                try!(std::fs::remove_file(path));
+            },
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => (),
+            Err(e) => return Err(e),
+        }
+        let listener: UnixListener = try!(UnixListener::bind(path));
+        let shared: Arc<Shared> = ConsoleServer::shared();
+        let thread_shared: Arc<Shared> = shared.clone();
+        let handle = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if !thread_shared.running.load(Ordering::SeqCst) {
+                    break;
+                }
+                accept(&thread_shared, stream, |s: &UnixStream| s.try_clone().map(ClientStream::Unix));
+            }
+        });
+        Ok(ConsoleServer {
+            address: ConsoleAddress::Unix(PathBuf::from(path)),
+            shared: shared,
+            listener_thread: Some(handle),
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn bind_unix(path: &str) -> io::Result<ConsoleServer> {
+        Err(io::Error::new(io::ErrorKind::Other, format!("Unix sockets are not supported on this platform: {}", path)))
+    }
+
+    fn shared() -> Arc<Shared> {
+        Arc::new(Shared {
+            pending: Mutex::new(Vec::new()),
+            clients: Mutex::new(HashMap::new()),
+            client_threads: Mutex::new(Vec::new()),
+            errors: Mutex::new(Vec::new()),
+            running: AtomicBool::new(true),
+            next_client: AtomicUsize::new(0),
+        })
+    }
+
+    /// The TCP port the server is listening on, or 0 for a Unix socket
+    ///
+    pub fn port(&self) -> u16 {
+        match self.address {
+            ConsoleAddress::Tcp(address) => address.port(),
+            ConsoleAddress::Unix(_) => 0,
+        }
+    }
+
+    pub fn client_count(&self) -> usize {
+        lock(&self.shared.clients).len()
+    }
+
+    fn take_pending(&self) -> Vec<(usize, String)> {
+        std::mem::replace(&mut *lock(&self.shared.pending), Vec::new())
+    }
+
+    /// Take the errors of the listener thread since the last call, such as clients which couldn't
+    /// be accepted
+    ///
+    pub fn take_errors(&self) -> Vec<String> {
+        std::mem::replace(&mut *lock(&self.shared.errors), Vec::new())
+    }
+
+    /// Take the lines received since the last call without running them
+    ///
+    pub fn read(&self) -> String {
+        let mut text: String = String::new();
+        for (_, line) in self.take_pending() {
+            text.push_str(&line);
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Run the lines received since the last call and reply to the clients which sent them
+    ///
+    /// Returns the number of lines run.
+    ///
+    pub fn process(&self, console: &mut Console) -> usize {
+        let lines: Vec<(usize, String)> = self.take_pending();
+        for &(id, ref line) in lines.iter() {
+            let mut reply: String = match console.execute(line) {
+                Ok(output) => output,
+                Err(e) => format!("Error: {}", e),
+            };
+            if !reply.is_empty() {
+                reply.push('\n');
+            }
+            reply.push_str("> ");
+            let mut clients = lock(&self.shared.clients);
+            let failed: bool = match clients.get_mut(&id) {
+                Some(client) => client.write_all(reply.as_bytes()).is_err(),
+                None => false,
+            };
+            if failed {
+                clients.remove(&id);
+            }
+        }
+        lines.len()
+    }
+
+    /// Stop accepting connections, disconnect every client and wait for their threads to finish
+    ///
+    pub fn shutdown(&mut self) {
+        if !self.shared.running.swap(false, Ordering::SeqCst) {
+            return;
+        }
+        // Wake the listener thread, which is blocked waiting for a connection
+        match self.address {
+            ConsoleAddress::Tcp(address) => { let _ = TcpStream::connect(address); },
+            ConsoleAddress::Unix(ref path) => {
+                #[cfg(unix)]
+                { let _ = UnixStream::connect(path); }
+                let _ = std::fs::remove_file(path);
+            },
+        }
+        if let Some(handle) = self.listener_thread.take() {
+            let _ = handle.join();
+        }
+        for (_, client) in lock(&self.shared.clients).drain() {
+            client.shutdown();
+        }
+        // The readers return once their connection is shut down
+        let client_threads: Vec<thread::JoinHandle<()>> = lock(&self.shared.client_threads).drain(..).collect();
+        for handle in client_threads {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for ConsoleServer {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// `extern ConsoleServer create_console_server_tcp(int port);`
+///
+/// Port 0 picks an unused port, see `console_server_port`.
+///
+#[no_mangle]
+pub extern "C" fn create_console_server_tcp(port: libc::c_int) -> Option<Box<ConsoleServer>> {
+    ffi::guard_result(|| -> Result<Box<ConsoleServer>, Error> {
+        if port < 0 || port > u16::max_value() as libc::c_int {
+            return Err(Error::InvalidArgument(format!("Invalid port {}", port)));
+        }
+        let server: ConsoleServer = try!(ConsoleServer::bind_tcp(port as u16));
+        Ok(Box::new(server))
+    })
+}
+
+/// `extern ConsoleServer create_console_server_unix(const char* path);`
+///
+#[no_mangle]
+pub extern "C" fn create_console_server_unix(path: *const libc::c_char) -> Option<Box<ConsoleServer>> {
+    ffi::guard_result(|| -> Result<Box<ConsoleServer>, Error> {
+        let path: String = try!(ffi::c_string(path));
+        let server: ConsoleServer = try!(ConsoleServer::bind_unix(&path));
+        Ok(Box::new(server))
+    })
+}
+
+/// `extern void free_console_server(ConsoleServer ptr);`
+///
+#[no_mangle]
+pub extern "C" fn free_console_server(ptr: *mut ConsoleServer) {
+    ffi::guard_status(|| {
+        let box_ptr: Box<ConsoleServer> = unsafe { Box::from_raw(ptr) };
+        drop(box_ptr)
+    });
+}
+
+/// `extern int console_server_port(ConsoleServer server);`
+///
+#[no_mangle]
+pub extern "C" fn console_server_port(server: &ConsoleServer) -> libc::c_int {
+    ffi::guard_or(-1, || {
+        server.port() as libc::c_int
+    })
+}
+
+/// `extern int console_server_client_count(ConsoleServer server);`
+///
+#[no_mangle]
+pub extern "C" fn console_server_client_count(server: &ConsoleServer) -> libc::c_int {
+    ffi::guard_or(-1, || {
+        server.client_count() as libc::c_int
+    })
+}
+
+/// Keep the latest error of the listener thread as the last error
+///
+fn report_listener_errors(server: &ConsoleServer) {
+    if let Some(e) = server.take_errors().pop() {
+        ffi::set_last_error(&e);
+    }
+}
+
+/// Run the received lines through a `Console`, returns the number of lines run
+///
+/// A client which couldn't be accepted since the last call is reported by `quick3d_last_error`.
+///
+/// `extern int console_server_process(ConsoleServer server, Console console);`
+///
+#[no_mangle]
+pub extern "C" fn console_server_process(server: &ConsoleServer, console: &mut Console) -> libc::c_int {
+    ffi::guard_or(-1, || {
+        report_listener_errors(server);
+        server.process(console) as libc::c_int
+    })
+}
+
+/// Take the received lines without running them, the string must be freed with `free_string`
+///
+/// Errors of the listener thread are reported like `console_server_process`.
+///
+/// `extern char* console_server_read(ConsoleServer server);`
+///
+#[no_mangle]
+pub extern "C" fn console_server_read(server: &ConsoleServer) -> *mut libc::c_char {
+    ffi::guard_result_or(std::ptr::null_mut(), || {
+        report_listener_errors(server);
+        let s: CString = try!(CString::new(server.read()).map_err(io::Error::from));
+        Ok(s.into_raw())
+    })
+}
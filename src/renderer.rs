@@ -5,42 +5,88 @@ extern crate image;
 extern crate libc;
 extern crate nalgebra;
 
+use std::cell::RefCell;
 use std::collections::HashMap;
-use std::ffi::CStr;
-use std::io::Error;
-use std::io::ErrorKind;
 
 use camera::Camera;
 use common;
-use common::{Mesh,Vertex8f32};
+use common::{ImageBlob, Mesh, Vertex8f32};
 
 #[cfg(feature = "sqlite")]
 use dbloader::DBLoader;
 
+use error::Error;
+
 use frustum_query::frustum::Frustum;
-use scene::Scene;
+use picking::PickingBuffer;
+use profiler::Profiler;
+use scene::{Scene, DEFAULT_BLANK_TEXTURE};
+use stats::{RenderStats, StatsOverlay};
+use overlay::Overlay;
+use text::TextRenderer;
 
 use glium::backend::glutin_backend::GlutinFacade;
-use glium::Surface;
-
-use nalgebra::Vector4;
+use glium::draw_parameters::TimeElapsedQuery;
+use glium::{Rect, Surface};
+use ffi;
 
 implement_vertex!(Vertex8f32, position, normal, texcoord);
 
+/// Decode an `ImageBlob` into 8 bit RGBA pixels, starting with the top row
+///
+pub fn decode_image(blob: &ImageBlob) -> Result<image::ImageBuffer<image::Rgba<u8>, Vec<u8>>, Error> {
+    //TODO: determine image format by name extension if nessisary
+    let image = try!(
+        image::load_from_memory(&blob.image).map_err(RendererError::ImageLoadingError)
+    ).to_rgba();
+    Ok(image)
+}
+
+/// Decode an `ImageBlob` into a texture, the first row of the image is the top of the texture
+///
+fn load_texture(display: &GlutinFacade, blob: &ImageBlob) -> Result<glium::texture::CompressedSrgbTexture2d, Error> {
+    let image = try!(decode_image(blob));
+    let image_dimensions = image.dimensions();
+    let image = glium::texture::RawImage2d::from_raw_rgba_reversed(image.into_raw(), image_dimensions);
+    let opengl_texture: glium::texture::CompressedSrgbTexture2d = try!(
+        glium::texture::CompressedSrgbTexture2d::new(display, image).map_err(RendererError::TextureCreationError)
+    );
+    Ok(opengl_texture)
+}
+
+/// An estimate of the memory used by a texture, as uncompressed 8 bit RGBA
+///
+fn texture_memory(texture: &glium::texture::CompressedSrgbTexture2d) -> u64 {
+    let (width, height) = texture.dimensions();
+    width as u64 * height as u64 * 4
+}
+
 /// A representation of the Glium data needed for rendering
 ///
 pub struct Renderer {
     pub index_buffer: glium::index::NoIndices,
+    pub overlay: Overlay,
+    pub picking: Option<PickingBuffer>,
+    pub profiler: Profiler,
     pub scene: Scene,
+    pub stats: RefCell<RenderStats>,
+    pub stats_overlay: Option<StatsOverlay>,
+    pub text: Option<TextRenderer>,
     pub textures: HashMap<String, glium::texture::CompressedSrgbTexture2d>,
     pub vertex_buffers: Vec<glium::vertex::VertexBuffer<common::Vertex8f32>>,
 }
 
 #[derive(Debug)]
 pub enum RendererError {
+    DisplayCreationError(glium::GliumCreationError<glium::glutin::CreationError>),
     DrawError(glium::DrawError),
     EmptySceneError,
+    FontNotLoadedError,
+    FrameBufferValidationError(glium::framebuffer::ValidationError),
     ImageLoadingError(self::image::ImageError),
+    InvalidFontError,
+    ProgramCreationError(glium::ProgramCreationError),
+    RenderBufferCreationError(glium::framebuffer::RenderBufferCreationError),
     SwapBuffersError(glium::SwapBuffersError),
     TextureCreationError(glium::texture::TextureCreationError),
     VertexBufferCreationError(glium::vertex::BufferCreationError),
@@ -49,9 +95,9 @@ pub enum RendererError {
 impl Renderer {
     /// Create a new renderer from a `Scene` struct
     ///
-    pub fn new(display: &GlutinFacade, scene: Scene) -> Result<Renderer, RendererError> {
+    pub fn new(display: &GlutinFacade, scene: Scene) -> Result<Renderer, Error> {
         let num_meshes = match scene.meshes.len() {
-            0 => return Err(RendererError::EmptySceneError),
+            0 => return Err(Error::Renderer(RendererError::EmptySceneError)),
             n => n,
         };
     
@@ -70,29 +116,44 @@ impl Renderer {
             glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList);
         
         let mut textures: HashMap<String, glium::texture::CompressedSrgbTexture2d> = HashMap::new();
+        let mut stats: RenderStats = RenderStats::new();
         
         for i in 0..scene.images.len() {
-            //TODO: determine image format by name extension if nessisary
-            let image = try!(
-                image::load_from_memory(&scene.images[i].image).map_err(RendererError::ImageLoadingError)
-            ).to_rgba();
-            
-            let image_dimensions = image.dimensions();
-            let image = glium::texture::RawImage2d::from_raw_rgba_reversed(image.into_raw(),
-                                                                           image_dimensions);
-            let opengl_texture: glium::texture::CompressedSrgbTexture2d = try!(
-                glium::texture::CompressedSrgbTexture2d::new(display, image).map_err(RendererError::TextureCreationError)
-            );
+            let opengl_texture: glium::texture::CompressedSrgbTexture2d = try!(load_texture(display, &scene.images[i]));
+            stats.texture_memory += texture_memory(&opengl_texture);
             textures.insert(scene.images[i].name.clone(), opengl_texture);        
         }
         
         Ok(Renderer { 
             index_buffer: index_buffer,
+            overlay: Overlay::new(),
+            picking: None,
+            profiler: Profiler::new(),
             scene: scene,
+            stats: RefCell::new(stats),
+            stats_overlay: None,
+            text: None,
             textures: textures,
             vertex_buffers: vertex_buffers,
         })
     }
+
+    /// Add an encoded image to the `Scene` and create it's texture, replacing any with the same name
+    ///
+    pub fn add_image(&mut self, display: &GlutinFacade, name: &str, image: Vec<u8>) -> Result<(), Error> {
+        let blob: ImageBlob = ImageBlob {
+            name: String::from(name),
+            image: image,
+        };
+        let opengl_texture: glium::texture::CompressedSrgbTexture2d = try!(load_texture(display, &blob));
+        let mut stats = self.stats.borrow_mut();
+        stats.texture_memory += texture_memory(&opengl_texture);
+        if let Some(old_texture) = self.textures.insert(blob.name.clone(), opengl_texture) {
+            stats.texture_memory -= texture_memory(&old_texture);
+        }
+        self.overlay.clear_atlas();
+        self.scene.add_image(name, blob.image)
+    }
     
     /// Try to find the reference to a `Mesh` by name
     /// 
@@ -102,28 +163,62 @@ impl Renderer {
                 return Ok(&self.scene.meshes[i]);
             }
         }
-        return Err(Error::new(ErrorKind::NotFound, "Unable to load mesh"));
+        return Err(Error::InvalidArgument(format!("Unable to find mesh {}", name)));
+    }
+
+    /// Test the bounding sphere of a `Mesh`, moved by it's current matrix, against a `Frustum`
+    ///
+    pub fn is_mesh_visible(&self, index: usize, frustum: &Frustum) -> bool {
+        let (center, radius) = self.scene.meshes[index].world_bounding_sphere();
+        frustum.sphere_intersecting(&center[0], &center[1], &center[2], &radius)
+    }
+
+    /// Replace the vertices of a `Mesh`, it's vertex buffer and bounding sphere are recreated
+    ///
+    pub fn update_mesh_vertices(&mut self, display: &GlutinFacade, index: usize, vertices: Vec<Vertex8f32>) -> Result<(), Error> {
+        let vertex_buffer = try!(
+            glium::vertex::VertexBuffer::new(display, &vertices)
+                .map_err(RendererError::VertexBufferCreationError)
+        );
+        self.scene.meshes[index].set_vertices(vertices);
+        self.vertex_buffers[index] = vertex_buffer;
+        Ok(())
     }
     
-    /// Draw the `Scene` data consumed by self to the display
+    /// Draw the visible meshes of the `Scene` to a `Surface` from the point of view of a `Camera`
     ///
-    pub fn render(&self, display: &GlutinFacade, program: &glium::program::Program, camera: &Camera) -> Result<(), RendererError> {
-        
+    /// When a viewport is given drawing is restricted to that rectangle of the target, the
+    /// target is not cleared.
+    ///
+    pub fn draw<S: Surface>(&self,
+                            target: &mut S,
+                            program: &glium::program::Program,
+                            camera: &Camera,
+                            viewport: Option<glium::Rect>)
+                            -> Result<(), Error> {
+        self.draw_with_query(target, program, camera, viewport, None)
+    }
+
+    /// Like `draw`, measuring the GPU time of the draw calls with `query`
+    ///
+    pub fn draw_with_query<S: Surface>(&self,
+                                       target: &mut S,
+                                       program: &glium::program::Program,
+                                       camera: &Camera,
+                                       viewport: Option<glium::Rect>,
+                                       query: Option<&TimeElapsedQuery>)
+                                       -> Result<(), Error> {
         let frustum: Frustum = Frustum::from_modelview_and_projection_2d(
             &camera.modelview_matrix,
             &camera.projection_matrix,
         );
         
-        let mut target = display.draw();
         // TODO: generate this texture instead of loading from sqlite
-        let default_blank_texture = &self.textures["DEFAULT_BLANK_TEXTURE.png"];
-        target.clear_color_and_depth((0.0, 0.0, 0.0, 0.0), 1.0);
+        let default_blank_texture = &self.textures[DEFAULT_BLANK_TEXTURE];
         for i in 0..self.vertex_buffers.len() as usize {
             
             let matrix = *self.scene.meshes[i].matrix.borrow();
-            let local_center: Vector4<f32> = Vector4::new(self.scene.meshes[i].center[0].clone(), self.scene.meshes[i].center[1].clone(), self.scene.meshes[i].center[2].clone(), 1.0f32);
-            let center: Vector4<f32> = matrix * local_center;
-            if frustum.sphere_intersecting(&center.x, &center.y, &center.z, &self.scene.meshes[i].radius) {
+            if self.is_mesh_visible(i, &frustum) {
                 let material_index: usize = self.scene.meshes[i].material_index.clone();
                 let diffuse = self.scene.materials[material_index].diffuse.clone();
                 let diffuse_texname: String =
@@ -156,6 +251,8 @@ impl Renderer {
                         write: true,
                         .. Default::default()
                     },
+                    viewport: viewport,
+                    time_elapsed_query: query,
                     .. Default::default()
                 };
                 
@@ -166,98 +263,136 @@ impl Renderer {
                     &uniforms,
                     &params).map_err(RendererError::DrawError)
                 );
+                self.stats.borrow_mut().record_draw(self.vertex_buffers[i].len());
+            } else {
+                self.stats.borrow_mut().record_culled();
             }
         }
-        try!(target.finish().map_err(RendererError::SwapBuffersError));
+        Ok(())
+    }
+    
+    /// Draw the `Scene` data consumed by self to the display
+    ///
+    pub fn render(&self, display: &GlutinFacade, program: &glium::program::Program, camera: &Camera) -> Result<(), Error> {
+        let result = {
+            let _scope = self.profiler.scope("render");
+            self.render_frame(display, program, camera)
+        };
+        self.clear_overlay();
+        self.clear_text();
+        self.profiler.end_frame();
+        result
+    }
+
+    fn render_frame(&self, display: &GlutinFacade, program: &glium::program::Program, camera: &Camera) -> Result<(), Error> {
+        let mut target = display.draw();
+        target.clear_color_and_depth((0.0, 0.0, 0.0, 0.0), 1.0);
+        self.stats.borrow_mut().begin_frame();
+        let (width, height) = target.get_dimensions();
+        let view: Rect = Rect { left: 0, bottom: 0, width: width, height: height };
+        match self.draw_profiled(display, &mut target, program, camera, None, "scene")
+            .and_then(|_| self.profiler.profile_pass(display, "overlay", |query| {
+                self.draw_overlay(display, &mut target, query)
+            }))
+            .and_then(|_| self.profiler.profile_pass(display, "text", |query| {
+                self.draw_text_overlay(display, &mut target, &[(view, camera)], query)
+            }))
+            .and_then(|_| self.profiler.profile_pass(display, "stats_overlay", |query| {
+                self.draw_stats_overlay(display, &mut target, query)
+            })) {
+            Ok(_) => (),
+            Err(e) => {
+                // The frame must be finished before it is dropped
+                let _ = target.finish();
+                return Err(e);
+            },
+        }
+        self.stats.borrow_mut().end_frame();
+        {
+            let _scope = self.profiler.scope("swap_buffers");
+            try!(target.finish().map_err(RendererError::SwapBuffersError));
+        }
+        if self.picking.is_some() {
+            try!(self.profiler.profile_pass(display, "picking", |query| {
+                self.render_picking(display, camera, query)
+            }));
+        }
         Ok(())
     }
 }
 
-/// `extern Renderer create_renderer_from_db_loader(DBLoader loader, Display display);`
+/// `extern Renderer create_renderer_from_db_loader(DBLoader dbloader, Display display);`
 ///
 #[cfg(feature = "sqlite")]
 #[no_mangle]
 pub extern "C" fn create_renderer_from_db_loader(dbloader: &DBLoader,
                                                  display: &GlutinFacade)
-                                                 -> Box<Renderer> {
-    let scene: Scene = match dbloader.load_scene() {
-        Ok(s) => s,
-        Err(e) => panic!("Unable to load scene from SQLite: {:?}", e),
-    };
-    let renderer: Renderer = match Renderer::new(display, scene) {
-        Ok(r) => r,
-        Err(e) => panic!("Unable to create renderer: {:?}", e),
-    };
-    Box::new(renderer)
+                                                 -> Option<Box<Renderer>> {
+    ffi::guard_result(|| -> Result<Box<Renderer>, Error> {
+        let scene: Scene = try!(dbloader.load_scene());
+        let renderer: Renderer = try!(Renderer::new(display, scene));
+        Ok(Box::new(renderer))
+    })
 }
 
-/// `extern Renderer create_renderer_from_db_loader(DBLoader loader, Display display);`
+/// `extern Renderer create_renderer_from_db_loader(DBLoader dbloader, Display display);`
 ///
 #[cfg(not(feature = "sqlite"))]
 #[no_mangle]
 pub extern "C" fn create_renderer_from_db_loader(_dbloader: *const libc::c_void,
                                                  _display: &GlutinFacade)
-                                                 -> Box<Renderer> {
-    panic!("The SQLite feature is not enabled");
+                                                 -> Option<Box<Renderer>> {
+    ffi::guard_result(|| -> Result<Box<Renderer>, Error> {
+        Err(ffi::sqlite_disabled())
+    })
 }
                                                 
-/// `extern Renderer create_renderer_from_compressed_binary(const char* filename, Display display);`
+/// `extern Renderer create_renderer_from_compressed_binary(const char* file, Display display);`
 ///
 #[no_mangle]
 pub extern "C" fn create_renderer_from_compressed_binary(file: *const libc::c_char,
                                                  display: &GlutinFacade)
-                                                 -> Box<Renderer> {
-    let filename: String = unsafe{ CStr::from_ptr(file).to_string_lossy().into_owned() };
-    match Scene::from_compressed_binary_file(filename.clone()) {
-        Ok(s) => {
-            println!("Loaded compressed binary: {}", filename);
-            let renderer: Renderer = match Renderer::new(display, s) {
-                Ok(r) => r,
-                Err(e) => panic!("Unable to create renderer: {:?}", e),
-            };
-            return Box::new(renderer);
-        },
-        Err(e) => panic!("Unable to load compressed binary file {}: {:?}", filename, e),
-    };
+                                                 -> Option<Box<Renderer>> {
+    ffi::guard_result(|| -> Result<Box<Renderer>, Error> {
+        let filename: String = try!(ffi::c_string(file));
+        let scene: Scene = try!(Scene::from_compressed_binary_file(filename));
+        let renderer: Renderer = try!(Renderer::new(display, scene));
+        Ok(Box::new(renderer))
+    })
 }
                                                  
-/// `extern Renderer create_renderer_from_binary(const char* filename, Display display);`
+/// `extern Renderer create_renderer_from_binary(const char* file, Display display);`
 ///
 #[no_mangle]
 pub extern "C" fn create_renderer_from_binary(file: *const libc::c_char,
                                                  display: &GlutinFacade)
-                                                 -> Box<Renderer> {
-    let filename: String = unsafe{ CStr::from_ptr(file).to_string_lossy().into_owned() };
-    match Scene::from_binary_file(filename.clone()) {
-        Ok(s) => {
-            println!("Loaded binary: {}", filename);
-            let renderer: Renderer = match Renderer::new(display, s) {
-                Ok(r) => r,
-                Err(e) => panic!("Unable to create renderer: {:?}", e),
-            };
-            return Box::new(renderer);
-        },
-        Err(e) => panic!("Unable to load binary file {}: {:?}", filename, e),
-    };
+                                                 -> Option<Box<Renderer>> {
+    ffi::guard_result(|| -> Result<Box<Renderer>, Error> {
+        let filename: String = try!(ffi::c_string(file));
+        let scene: Scene = try!(Scene::from_binary_file(filename));
+        let renderer: Renderer = try!(Renderer::new(display, scene));
+        Ok(Box::new(renderer))
+    })
 }
                                                  
-/// `extern void free_renderer(Renderer renderer);`
+/// `extern void free_renderer(Renderer ptr);`
 ///
 #[no_mangle]
 pub extern "C" fn free_renderer(ptr: *mut Renderer) {
-    let box_ptr: Box<Renderer> = unsafe { Box::from_raw(ptr) };
-    drop(box_ptr)
+    ffi::guard_status(|| {
+        let box_ptr: Box<Renderer> = unsafe { Box::from_raw(ptr) };
+        drop(box_ptr)
+    });
 }
 
-/// `extern void render(Renderer renderer, Shader shader, Camera camera, Display display);`
+/// `extern bool render(Renderer renderer, Shader shader_program, Camera camera, Display display);`
 ///
 #[no_mangle]
 pub extern "C" fn render(renderer: &Renderer,
                          shader_program: &glium::program::Program,
                          camera: &Camera,
-                         display: &GlutinFacade) {
-    match renderer.render(display, shader_program, camera) {
-        Ok(_) => (),
-        Err(e) => panic!("{:?}", e),
-    };
+                         display: &GlutinFacade) -> bool {
+    ffi::guard_result_status(|| {
+        renderer.render(display, shader_program, camera)
+    })
 }
\ No newline at end of file
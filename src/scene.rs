@@ -1,22 +1,35 @@
 // Copyright 2016 (C) Chris Liebert
 
-use bincode::rustc_serialize::{encode_into, decode_from, EncodingError, DecodingError};
+extern crate image;
+extern crate libc;
+
+use bincode::rustc_serialize::{encode_into, decode_from};
 use bincode::SizeLimit::Infinite;
 use std::fs::File;
 use std::io::{BufWriter, BufReader};
+use std::slice;
 
 use flate2::write::ZlibEncoder;
 use flate2::read::ZlibDecoder;
 use flate2::Compression;
 
-use common::{ImageBlob, Material, Mesh};
+use glium::backend::glutin_backend::GlutinFacade;
+
+use common::{BoundingBox, ImageBlob, Material, Mesh, Vertex8f32};
+use error::Error;
+use renderer::{Renderer, RendererError};
+use ffi;
+
+/// The texture used by materials without a `diffuse_texname`, or with a missing texture
+///
+pub const DEFAULT_BLANK_TEXTURE: &'static str = "DEFAULT_BLANK_TEXTURE.png";
 
 /// Geometry and material information that can be rendered
 ///
 /// A `Scene` contains geometry that will be rendered along with reference materials and
 /// textures
 ///
-#[derive(PartialEq, RustcEncodable, RustcDecodable)]
+#[derive(Clone, PartialEq, RustcEncodable, RustcDecodable)]
 pub struct Scene {
     pub materials: Vec<Material>,
     pub meshes: Vec<Mesh>,
@@ -24,43 +37,303 @@ pub struct Scene {
 }
 
 impl Scene {
-    pub fn from_binary_file(filename: String) -> Result<Scene, DecodingError> {
-        let file = match File::open(filename.clone()) {
-            Ok(f) => f,
-            Err(e) => { return Err(DecodingError::from(e)); },
-        };
+    /// Create an empty `Scene`, to be filled with procedurally generated geometry
+    ///
+    pub fn new() -> Scene {
+        Scene {
+            materials: Vec::new(),
+            meshes: Vec::new(),
+            images: Vec::new(),
+        }
+    }
+
+    /// Add a `Material` and return it's index
+    ///
+    pub fn add_material(&mut self, material: Material) -> usize {
+        self.materials.push(material);
+        self.materials.len() - 1
+    }
+
+    /// Add a `Mesh` and return it's index
+    ///
+    /// The material index of the mesh must refer to a `Material` already in the `Scene`.
+    ///
+    pub fn add_mesh(&mut self, mesh: Mesh) -> Result<usize, Error> {
+        if mesh.material_index >= self.materials.len() {
+            return Err(Error::InvalidArgument(String::from("Material index out of range")));
+        }
+        self.meshes.push(mesh);
+        Ok(self.meshes.len() - 1)
+    }
+
+    /// Add a texture from an encoded image file (PNG, JPEG, ...) held in memory
+    ///
+    /// A texture with the same name is replaced.
+    ///
+    pub fn add_image(&mut self, name: &str, image: Vec<u8>) -> Result<(), Error> {
+        try!(image::guess_format(&image).map_err(RendererError::ImageLoadingError));
+        self.images.retain(|i| i.name != name);
+        self.images.push(ImageBlob {
+            name: String::from(name),
+            image: image,
+        });
+        Ok(())
+    }
+
+    /// Add a texture from raw 8 bit RGBA pixels, starting with the top row
+    ///
+    /// The pixels are stored as PNG so the `Scene` can still be written to a binary file.
+    ///
+    pub fn add_image_rgba(&mut self, name: &str, pixels: &[u8], width: u32, height: u32) -> Result<(), Error> {
+        if width == 0 || height == 0 || rgba_length(width, height) != Some(pixels.len()) {
+            return Err(Error::InvalidArgument(String::from("Pixel data does not match the image dimensions")));
+        }
+        let mut png: Vec<u8> = Vec::new();
+        try!(image::png::PNGEncoder::new(&mut png).encode(pixels, width, height, image::ColorType::RGBA(8)));
+        self.add_image(name, png)
+    }
+
+    /// Add the white `DEFAULT_BLANK_TEXTURE` needed by the `Renderer`, unless it already exists
+    ///
+    pub fn add_default_texture(&mut self) -> Result<(), Error> {
+        if self.images.iter().any(|i| i.name == DEFAULT_BLANK_TEXTURE) {
+            return Ok(());
+        }
+        self.add_image_rgba(DEFAULT_BLANK_TEXTURE, &[255, 255, 255, 255], 1, 1)
+    }
+
+    pub fn from_binary_file(filename: String) -> Result<Scene, Error> {
+        let file = try!(File::open(filename.clone()));
         let mut reader = BufReader::new(file);
-        let scene_result: Result<Scene, _> = decode_from(&mut reader, Infinite);
-        scene_result
+        let scene: Scene = try!(decode_from(&mut reader, Infinite));
+        Ok(scene)
     }
 
-    pub fn to_binary_file(&self, filename: String) -> Result<(), EncodingError> {
-        let file = match File::create(filename.clone()) {
-            Ok(f) => f,
-            Err(e) => { return Err(EncodingError::IoError(e)); },
-        };
+    pub fn to_binary_file(&self, filename: String) -> Result<(), Error> {
+        let file = try!(File::create(filename.clone()));
         let mut writer = BufWriter::new(file);
-        encode_into(&self, &mut writer, Infinite)
+        try!(encode_into(&self, &mut writer, Infinite));
+        Ok(())
     }
 
-    pub fn from_compressed_binary_file(filename: String) -> Result<Scene, DecodingError> {
-        let file = match File::open(filename.clone()) {
-            Ok(f) => f,
-            Err(e) => { return Err(DecodingError::from(e)); },
-        };
+    pub fn from_compressed_binary_file(filename: String) -> Result<Scene, Error> {
+        let file = try!(File::open(filename.clone()));
         let reader = BufReader::new(file);
         let mut decoder = ZlibDecoder::new(reader);
-        let scene_result: Result<Scene, _> = decode_from(&mut decoder, Infinite);
-        scene_result
+        let scene: Scene = try!(decode_from(&mut decoder, Infinite));
+        Ok(scene)
     }
 
-    pub fn to_compressed_binary_file(&self, filename: String) -> Result<(), EncodingError> {
-        let file = match File::create(filename.clone()) {
-            Ok(f) => f,
-            Err(e) => { return Err(EncodingError::IoError(e)); },
-        };
+    pub fn to_compressed_binary_file(&self, filename: String) -> Result<(), Error> {
+        let file = try!(File::create(filename.clone()));
         let writer = BufWriter::new(file);
         let mut encoder = ZlibEncoder::new(writer, Compression::Best);
-        encode_into(&self, &mut encoder, Infinite)
+        try!(encode_into(&self, &mut encoder, Infinite));
+        Ok(())
+    }
+
+    /// Recalculate the bounding sphere of every `Mesh` from it's vertices
+    ///
+    pub fn compute_bounds(&mut self) {
+        for mesh in self.meshes.iter_mut() {
+            mesh.compute_bounds();
+        }
+    }
+
+    /// The `BoundingBox` of all meshes in model space, ignoring their matrices
+    ///
+    pub fn local_bounding_box(&self) -> BoundingBox {
+        self.meshes.iter().fold(BoundingBox::empty(), |bounds, mesh| bounds.union(&mesh.local_bounding_box()))
+    }
+
+    /// The `BoundingBox` of all meshes moved by their current matrices
+    ///
+    pub fn world_bounding_box(&self) -> BoundingBox {
+        self.meshes.iter().fold(BoundingBox::empty(), |bounds, mesh| bounds.union(&mesh.world_bounding_box()))
+    }
+
+    /// The center and radius of a sphere containing every `Mesh` in world space
+    ///
+    pub fn world_bounding_sphere(&self) -> ([f32; 3], f32) {
+        let bounds = self.world_bounding_box();
+        if bounds.is_empty() {
+            return ([0.0; 3], 0.0);
+        }
+        let center = bounds.center();
+        let mut radius: f32 = 0.0;
+        for mesh in &self.meshes {
+            let (mesh_center, mesh_radius) = mesh.world_bounding_sphere();
+            let dx = mesh_center[0] - center[0];
+            let dy = mesh_center[1] - center[1];
+            let dz = mesh_center[2] - center[2];
+            radius = radius.max((dx * dx + dy * dy + dz * dz).sqrt() + mesh_radius);
+        }
+        // The box is always a valid bound, use whichever sphere is tighter
+        (center, radius.min(bounds.radius()))
     }
 }
+
+/// The number of bytes in `width * height` RGBA pixels, or `None` if it would overflow
+///
+fn rgba_length(width: u32, height: u32) -> Option<usize> {
+    (width as usize).checked_mul(height as usize).and_then(|pixels| pixels.checked_mul(4))
+}
+
+/// `extern Scene create_scene();`
+///
+/// Create an empty scene, which must be freed with `free_scene`.
+///
+#[no_mangle]
+pub extern "C" fn create_scene() -> Option<Box<Scene>> {
+    ffi::guard(|| Box::new(Scene::new()))
+}
+
+/// `extern void free_scene(Scene ptr);`
+///
+#[no_mangle]
+pub extern "C" fn free_scene(ptr: *mut Scene) {
+    ffi::guard_status(|| {
+        let box_ptr: Box<Scene> = unsafe { Box::from_raw(ptr) };
+        drop(box_ptr)
+    });
+}
+
+/// `extern int scene_add_material(Scene scene, const char* name, float r, float g, float b, const char* diffuse_texname);`
+///
+/// Returns the index of the material, `diffuse_texname` may be NULL for an untextured material.
+///
+#[no_mangle]
+pub extern "C" fn scene_add_material(scene: &mut Scene,
+                                     name_cstr: *const libc::c_char,
+                                     r: libc::c_float,
+                                     g: libc::c_float,
+                                     b: libc::c_float,
+                                     diffuse_texname_cstr: *const libc::c_char)
+                                     -> libc::c_int {
+    ffi::guard_result_or(-1, || {
+        let diffuse_texname: String = match diffuse_texname_cstr.is_null() {
+            true => String::new(),
+            false => try!(ffi::c_string(diffuse_texname_cstr)),
+        };
+        let material: Material = Material {
+            name: try!(ffi::c_string(name_cstr)),
+            diffuse: [r as f32, g as f32, b as f32],
+            diffuse_texname: diffuse_texname,
+        };
+        Ok(scene.add_material(material) as libc::c_int)
+    })
+}
+
+/// `extern int scene_add_mesh(Scene scene, const char* name, const float* positions, const float* normals, const float* texcoords, int vertex_count, int material_index);`
+///
+/// Adds `vertex_count` vertices, three per triangle. `positions` and `normals` hold three floats
+/// per vertex and `texcoords` holds two. `normals` and `texcoords` may be NULL, in which case they
+/// are zero. Returns the index of the mesh, or -1 on error.
+///
+#[no_mangle]
+pub extern "C" fn scene_add_mesh(scene: &mut Scene,
+                                 name_cstr: *const libc::c_char,
+                                 positions: *const libc::c_float,
+                                 normals: *const libc::c_float,
+                                 texcoords: *const libc::c_float,
+                                 vertex_count: libc::c_int,
+                                 material_index: libc::c_int)
+                                 -> libc::c_int {
+    ffi::guard_result_or(-1, || {
+        if positions.is_null() || vertex_count <= 0 || material_index < 0 {
+            return Err(Error::InvalidArgument(String::from("Invalid mesh data")));
+        }
+        let count: usize = vertex_count as usize;
+        let positions: &[f32] = unsafe { slice::from_raw_parts(positions, count * 3) };
+        let normals: Option<&[f32]> = match normals.is_null() {
+            true => None,
+            false => Some(unsafe { slice::from_raw_parts(normals, count * 3) }),
+        };
+        let texcoords: Option<&[f32]> = match texcoords.is_null() {
+            true => None,
+            false => Some(unsafe { slice::from_raw_parts(texcoords, count * 2) }),
+        };
+        let mut vertices: Vec<Vertex8f32> = Vec::with_capacity(count);
+        for i in 0..count {
+            let mut vertex: Vertex8f32 = Vertex8f32 {
+                position: [positions[i * 3], positions[i * 3 + 1], positions[i * 3 + 2]],
+                normal: [0.0; 3],
+                texcoord: [0.0; 2],
+            };
+            if let Some(n) = normals {
+                vertex.normal = [n[i * 3], n[i * 3 + 1], n[i * 3 + 2]];
+            }
+            if let Some(t) = texcoords {
+                vertex.texcoord = [t[i * 2], t[i * 2 + 1]];
+            }
+            vertices.push(vertex);
+        }
+        let mesh: Mesh = Mesh::new(&try!(ffi::c_string(name_cstr)), vertices, material_index as usize);
+        let index: usize = try!(scene.add_mesh(mesh));
+        Ok(index as libc::c_int)
+    })
+}
+
+/// `extern bool scene_add_texture(Scene scene, const char* name, const unsigned char* data, int length);`
+///
+/// Add a texture from the bytes of an encoded image file such as a PNG or JPEG.
+///
+#[no_mangle]
+pub extern "C" fn scene_add_texture(scene: &mut Scene,
+                                    name_cstr: *const libc::c_char,
+                                    data: *const libc::c_uchar,
+                                    length: libc::c_int) -> bool {
+    ffi::guard_result_status(|| {
+        if data.is_null() || length <= 0 {
+            return Err(Error::InvalidArgument(String::from("Invalid image data")));
+        }
+        let image: Vec<u8> = unsafe { slice::from_raw_parts(data, length as usize) }.to_vec();
+        scene.add_image(&try!(ffi::c_string(name_cstr)), image)
+    })
+}
+
+/// `extern bool scene_add_texture_rgba(Scene scene, const char* name, const unsigned char* pixels, int width, int height);`
+///
+/// Add a texture from `width * height` 8 bit RGBA pixels, starting with the top row.
+///
+#[no_mangle]
+pub extern "C" fn scene_add_texture_rgba(scene: &mut Scene,
+                                         name_cstr: *const libc::c_char,
+                                         pixels: *const libc::c_uchar,
+                                         width: libc::c_int,
+                                         height: libc::c_int) -> bool {
+    ffi::guard_result_status(|| {
+        if pixels.is_null() || width <= 0 || height <= 0 {
+            return Err(Error::InvalidArgument(String::from("Invalid image data")));
+        }
+        let length: usize = match rgba_length(width as u32, height as u32) {
+            Some(length) => length,
+            None => return Err(Error::InvalidArgument(String::from("Image dimensions are too large"))),
+        };
+        let pixels: &[u8] = unsafe { slice::from_raw_parts(pixels, length) };
+        scene.add_image_rgba(&try!(ffi::c_string(name_cstr)), pixels, width as u32, height as u32)
+    })
+}
+
+/// `extern int scene_mesh_count(Scene scene);`
+///
+#[no_mangle]
+pub extern "C" fn scene_mesh_count(scene: &Scene) -> libc::c_int {
+    ffi::guard_or(-1, || scene.meshes.len() as libc::c_int)
+}
+
+/// `extern Renderer create_renderer_from_scene(Scene scene, Display display);`
+///
+/// The scene is copied, so it can be modified or freed afterwards without affecting the renderer.
+///
+#[no_mangle]
+pub extern "C" fn create_renderer_from_scene(scene: &Scene,
+                                             display: &GlutinFacade)
+                                             -> Option<Box<Renderer>> {
+    ffi::guard_result(|| -> Result<Box<Renderer>, Error> {
+        let mut scene: Scene = scene.clone();
+        try!(scene.add_default_texture());
+        let renderer: Renderer = try!(Renderer::new(display, scene));
+        Ok(Box::new(renderer))
+    })
+}
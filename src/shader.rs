@@ -2,9 +2,8 @@
 
 extern crate libc;
 
-use std::ffi::CStr;
 use std::fs::File;
-use std::io::Error;
+use std::io;
 use std::io::prelude::*;
 use std::num::ParseIntError;
 
@@ -16,7 +15,9 @@ use glium::Program;
 use glium::Version;
 
 #[cfg(feature = "sqlite")]
-use dbloader::{DBLoader, DBLoaderError};
+use dbloader::DBLoader;
+use error::Error;
+use ffi;
 
 /// A representation for a GPU program
 ///
@@ -32,17 +33,17 @@ pub struct Shader {
 
 #[derive(Debug)]
 pub enum ShaderError {
-    IoError(Error),
+    IoError(io::Error),
     ProgramChooserCreationError(glium::program::ProgramChooserCreationError),
     ProgramCreationError(glium::ProgramCreationError),
     InvalidGLSLVersionStringError(ParseIntError),
-    UnsupportedGLSLVersionError(Error),
+    UnsupportedGLSLVersionError(io::Error),
 }
 
 impl Shader {
     /// Create a default shader with hardcoded source
     ///
-    pub fn default(display: &GlutinFacade) -> Result<glium::program::Program, ShaderError> {
+    pub fn default(display: &GlutinFacade) -> Result<glium::program::Program, Error> {
         Shader::from_source(r#"
 #version 130
  
@@ -133,8 +134,7 @@ void main(){
     ///
 	#[cfg(feature = "sqlite")]
     #[allow(unused_assignments)]
-    pub fn from_dbloader(shader_name: &str, dbloader: &DBLoader, display: &GlutinFacade) -> Result<glium::program::Program, ShaderError> {
-        use std::io::ErrorKind;
+    pub fn from_dbloader(shader_name: &str, dbloader: &DBLoader, display: &GlutinFacade) -> Result<glium::program::Program, Error> {
         use glium::Version;
         let supported_glsl_version: Version = display.get_supported_glsl_version();
         let api: glium::Api = supported_glsl_version.0;
@@ -157,22 +157,12 @@ void main(){
         
         let glsl_version_number: u32 = match glsl_version_string.parse() {
             Ok(s) => s,
-            Err(e) => return Err(ShaderError::InvalidGLSLVersionStringError(e)),
+            Err(e) => return Err(Error::Shader(ShaderError::InvalidGLSLVersionStringError(e))),
         };
         
         println!("Using glsl version {}", &glsl_version_string);
     
-        let shader: Shader = match dbloader.load_shader(shader_name, &glsl_version_string) {
-            Ok(s) => s,
-            Err(DBLoaderError::DBError(e)) => {
-                let description = format!("{:?}", e);
-                return Err(ShaderError::IoError(Error::new(ErrorKind::InvalidData, description)));
-            },
-            Err(e) => {
-                let description = format!("{:?}", e);
-                return Err(ShaderError::IoError(Error::new(ErrorKind::InvalidData, description)));
-            },
-        };
+        let shader: Shader = try!(dbloader.load_shader(shader_name, &glsl_version_string));
     
         let program: Program = match use_gles {
             true => try!(
@@ -199,10 +189,9 @@ void main(){
     /// Create a `glium::program::Program` object from a `DBLoader` that has a specific shader version
     ///
 	#[cfg(feature = "sqlite")]
-    pub fn from_dbloader_with_version(shader_name: &str, dbloader: &DBLoader, glsl_version: &Version, display: &GlutinFacade) -> Result<glium::program::Program, ShaderError> {
-        use std::io::ErrorKind;
+    pub fn from_dbloader_with_version(shader_name: &str, dbloader: &DBLoader, glsl_version: &Version, display: &GlutinFacade) -> Result<glium::program::Program, Error> {
         if !display.is_glsl_version_supported(&glsl_version) {
-            return Err(ShaderError::UnsupportedGLSLVersionError(Error::new(ErrorKind::InvalidData, "Unsupported GLSL version")));
+            return Err(Error::Shader(ShaderError::UnsupportedGLSLVersionError(io::Error::new(io::ErrorKind::InvalidData, "Unsupported GLSL version"))));
         }
         
         let api: glium::Api = glsl_version.0;
@@ -233,17 +222,7 @@ void main(){
         
         println!("Using glsl version {}", &glsl_version_string);
     
-        let shader: Shader = match dbloader.load_shader(shader_name, &glsl_version_string) {
-            Ok(s) => s,
-            Err(DBLoaderError::DBError(e)) => {
-                let description = format!("{:?}", e);
-                return Err(ShaderError::IoError(Error::new(ErrorKind::InvalidData, description)));
-            },
-            Err(e) => {
-                let description = format!("{:?}", e);
-                return Err(ShaderError::IoError(Error::new(ErrorKind::InvalidData, description)));
-            },
-        };
+        let shader: Shader = try!(dbloader.load_shader(shader_name, &glsl_version_string));
     
         Ok(match _use_gles {
             true => try!(
@@ -267,7 +246,7 @@ void main(){
     
     /// Create a `glium::program::Program` object from vertex and fragment file sources
     ///
-    pub fn from_file(vertex: &str, fragment: &str, display: &GlutinFacade) -> Result<Program, ShaderError> {
+    pub fn from_file(vertex: &str, fragment: &str, display: &GlutinFacade) -> Result<Program, Error> {
         let mut vf = try!(
         	File::open(vertex).map_err(ShaderError::IoError)
         );
@@ -291,7 +270,7 @@ void main(){
     
     /// Create a `glium::program::Program` object from vertex and fragment sources
     ///
-    pub fn from_source(vertex_source: &str, fragment_source: &str, display: &GlutinFacade) -> Result<Program, ShaderError> {
+    pub fn from_source(vertex_source: &str, fragment_source: &str, display: &GlutinFacade) -> Result<Program, Error> {
         let program = try!(
             Program::from_source(display, &vertex_source, &fragment_source, None)
                 .map_err(ShaderError::ProgramCreationError)
@@ -304,19 +283,21 @@ void main(){
 ///
 #[no_mangle]
 pub extern "C" fn shader_default(display: &GlutinFacade)
-                                        -> Box<glium::program::Program> {
-    match Shader::default(display) {
-        Ok(s) => Box::new(s),
-        Err(e) => panic!("Unable to load default shader: {:?}", e),
-    }
+                                        -> Option<Box<glium::program::Program>> {
+    ffi::guard_result(|| -> Result<Box<glium::program::Program>, Error> {
+        let shader_program = try!(Shader::default(display));
+        Ok(Box::new(shader_program))
+    })
 }
 
-/// `extern void free_shader(Shader shader);`
+/// `extern void free_shader(Shader ptr);`
 ///
 #[no_mangle]
 pub extern "C" fn free_shader(ptr: *mut glium::program::Program) {
-    let box_ptr: Box<glium::program::Program> = unsafe { Box::from_raw(ptr) };
-    drop(box_ptr)
+    ffi::guard_status(|| {
+        let box_ptr: Box<glium::program::Program> = unsafe { Box::from_raw(ptr) };
+        drop(box_ptr)
+    });
 }
 
 /// `extern Shader get_shader_from_db_loader(const char* name, DBLoader dbloader, Renderer renderer, Display display);`
@@ -326,12 +307,12 @@ pub extern "C" fn free_shader(ptr: *mut glium::program::Program) {
 pub extern "C" fn get_shader_from_dbloader(shader_name_cstr: *const libc::c_char,
                                             dbloader: &DBLoader,
                                             display: &GlutinFacade)
-                                            -> Box<glium::program::Program> {
-    unsafe {
-        let shader_name: String = CStr::from_ptr(shader_name_cstr).to_string_lossy().into_owned();
-        let shader_program = Shader::from_dbloader(&shader_name, dbloader, display).expect("Unable to load shader from database");
-        return Box::new(shader_program);
-    }
+                                            -> Option<Box<glium::program::Program>> {
+    ffi::guard_result(|| -> Result<Box<glium::program::Program>, Error> {
+        let shader_name: String = try!(ffi::c_string(shader_name_cstr));
+        let shader_program = try!(Shader::from_dbloader(&shader_name, dbloader, display));
+        Ok(Box::new(shader_program))
+    })
 }
                                             
 /// `extern Shader get_shader_from_db_loader(const char* name, DBLoader dbloader, Renderer renderer, Display display);`
@@ -341,8 +322,10 @@ pub extern "C" fn get_shader_from_dbloader(shader_name_cstr: *const libc::c_char
 pub extern "C" fn get_shader_from_dbloader(_shader_name_cstr: *const libc::c_char,
                                             _dbloader: *const libc::c_void,
                                             _display: &GlutinFacade)
-                                            -> Box<glium::program::Program> {
-    panic!("The SQLite feature is not enabled");
+                                            -> Option<Box<glium::program::Program>> {
+    ffi::guard_result(|| -> Result<Box<glium::program::Program>, Error> {
+        Err(ffi::sqlite_disabled())
+    })
 }
                                       
 /// `extern Shader get_shader_from_source(const char* vertex, const char* fragment, Display display);`
@@ -350,16 +333,13 @@ pub extern "C" fn get_shader_from_dbloader(_shader_name_cstr: *const libc::c_cha
 #[no_mangle]
 pub extern "C" fn get_shader_from_source(vertex_cstr: *const libc::c_char,
                                          fragment_cstr: *const libc::c_char,
-                                         display: &GlutinFacade) -> Box<glium::program::Program> {
-    unsafe {
-        let vertex: String = CStr::from_ptr(vertex_cstr).to_string_lossy().into_owned();
-        let fragment: String = CStr::from_ptr(fragment_cstr).to_string_lossy().into_owned();
-        let shader_program = match Shader::from_source(&vertex, &fragment, display) {
-	        Ok(s) => s,
-	        Err(e) => panic!("Unable to create shader program: {:?}", e),
-        };
-        return Box::new(shader_program);
-    }
+                                         display: &GlutinFacade) -> Option<Box<glium::program::Program>> {
+    ffi::guard_result(|| -> Result<Box<glium::program::Program>, Error> {
+        let vertex: String = try!(ffi::c_string(vertex_cstr));
+        let fragment: String = try!(ffi::c_string(fragment_cstr));
+        let shader_program = try!(Shader::from_source(&vertex, &fragment, display));
+        Ok(Box::new(shader_program))
+    })
 }
                                          
 /// `extern Shader is_shader_source_valid(const char* vertex, const char* fragment, Display display);`
@@ -368,17 +348,16 @@ pub extern "C" fn get_shader_from_source(vertex_cstr: *const libc::c_char,
 pub extern "C" fn shader_source_is_valid(vertex_cstr: *const libc::c_char,
                                          fragment_cstr: *const libc::c_char,
                                          display: &GlutinFacade) -> bool {
-    unsafe {
-        let vertex: String = CStr::from_ptr(vertex_cstr).to_string_lossy().into_owned();
-        let fragment: String = CStr::from_ptr(fragment_cstr).to_string_lossy().into_owned();
+    ffi::guard_result_or(false, || {
+        let vertex: String = try!(ffi::c_string(vertex_cstr));
+        let fragment: String = try!(ffi::c_string(fragment_cstr));
         match Shader::from_source(&vertex, &fragment, display) {
-	        Ok(s) =>  {
-	            drop(s);
-	            true
-	        },
-	        Err(_e) => {
-	        	false
-	        },
+            Ok(_) => Ok(true),
+            Err(e) => {
+                // An invalid shader is an answer rather than a failure, the reason is kept as the last error
+                ffi::set_last_error(&e.to_string());
+                Ok(false)
+            },
         }
-    }
+    })
 }
\ No newline at end of file
@@ -0,0 +1,288 @@
+// Copyright (C) 2016 Chris Liebert
+
+extern crate libc;
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use glium::backend::glutin_backend::GlutinFacade;
+use glium::draw_parameters::TimeElapsedQuery;
+use glium::Surface;
+
+use common;
+use error::Error;
+use overlay::SpriteBatch;
+use renderer::Renderer;
+use ffi;
+
+/// The number of frames kept for averages and the overlay graph
+///
+pub const STATS_HISTORY: usize = 120;
+
+/// The cost of a single frame drawn by a `Renderer`
+///
+/// Times are in milliseconds. `frame_time` is measured from the start of the previous frame,
+/// `render_time` is the CPU time spent drawing, before the buffers are swapped.
+/// `draw_calls` counts the draws of the meshes, the overlay, the text and the stats overlay,
+/// `vertices` includes the vertices of all of them.
+/// `texture_memory` is an estimate in bytes of the uncompressed textures.
+///
+/// ```c
+/// /* C representation */
+/// typedef struct FrameStats {
+///     float frame_time, render_time;
+///     unsigned int draw_calls, meshes_drawn, meshes_culled;
+///     unsigned long long vertices, texture_memory;
+/// } FrameStats;
+/// ```
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[repr(C)]
+pub struct FrameStats {
+    pub frame_time: f32,
+    pub render_time: f32,
+    pub draw_calls: u32,
+    pub meshes_drawn: u32,
+    pub meshes_culled: u32,
+    pub vertices: u64,
+    pub texture_memory: u64,
+}
+
+/// Statistics of the frames drawn by a `Renderer`, updated by `render` and `render_viewports`
+///
+pub struct RenderStats {
+    pub texture_memory: u64,
+    history: VecDeque<FrameStats>,
+    current: FrameStats,
+    frame_start: Option<Instant>,
+}
+
+impl RenderStats {
+    pub fn new() -> RenderStats {
+        RenderStats {
+            texture_memory: 0,
+            history: VecDeque::with_capacity(STATS_HISTORY),
+            current: FrameStats::default(),
+            frame_start: None,
+        }
+    }
+
+    pub fn begin_frame(&mut self) {
+        let now: Instant = Instant::now();
+        self.current = FrameStats::default();
+        if let Some(last_start) = self.frame_start {
+            self.current.frame_time = common::milliseconds(now.duration_since(last_start)) as f32;
+        }
+        self.frame_start = Some(now);
+    }
+
+    /// Count a draw call which isn't a mesh of the scene, like the overlay or text
+    ///
+    pub fn record_draw_call(&mut self, vertices: usize) {
+        self.current.draw_calls += 1;
+        self.current.vertices += vertices as u64;
+    }
+
+    pub fn record_draw(&mut self, vertices: usize) {
+        self.record_draw_call(vertices);
+        self.current.meshes_drawn += 1;
+    }
+
+    pub fn record_culled(&mut self) {
+        self.current.meshes_culled += 1;
+    }
+
+    pub fn end_frame(&mut self) {
+        let mut frame: FrameStats = self.current;
+        if let Some(start) = self.frame_start {
+            frame.render_time = common::milliseconds(start.elapsed()) as f32;
+        }
+        self.push(frame);
+    }
+
+    /// Add a finished frame to the history
+    ///
+    pub fn push(&mut self, mut frame: FrameStats) {
+        frame.texture_memory = self.texture_memory;
+        if self.history.len() == STATS_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(frame);
+    }
+
+    /// The last finished frame
+    ///
+    pub fn last(&self) -> FrameStats {
+        self.history.back().cloned().unwrap_or(FrameStats::default())
+    }
+
+    /// The mean of the frames in the history, counts are rounded down
+    ///
+    pub fn average(&self) -> FrameStats {
+        let count: usize = self.history.len();
+        if count == 0 {
+            return FrameStats::default();
+        }
+        let mut sum: [f64; 6] = [0.0; 6];
+        for frame in self.history.iter() {
+            sum[0] += frame.frame_time as f64;
+            sum[1] += frame.render_time as f64;
+            sum[2] += frame.draw_calls as f64;
+            sum[3] += frame.meshes_drawn as f64;
+            sum[4] += frame.meshes_culled as f64;
+            sum[5] += frame.vertices as f64;
+        }
+        let n: f64 = count as f64;
+        FrameStats {
+            frame_time: (sum[0] / n) as f32,
+            render_time: (sum[1] / n) as f32,
+            draw_calls: (sum[2] / n) as u32,
+            meshes_drawn: (sum[3] / n) as u32,
+            meshes_culled: (sum[4] / n) as u32,
+            vertices: (sum[5] / n) as u64,
+            texture_memory: self.texture_memory,
+        }
+    }
+
+    /// The frame times in milliseconds, oldest first
+    ///
+    pub fn frame_times(&self) -> Vec<f32> {
+        self.history.iter().map(|f| f.frame_time).collect()
+    }
+}
+
+/// A graph of the recent frame times drawn in the bottom left corner of the window
+///
+/// Each bar is one frame, green when it took less than 1/60 s, yellow for less than 1/30 s
+/// and red otherwise. The white line marks 1/60 s. The graph is drawn with the overlay shader.
+///
+pub struct StatsOverlay {
+    batch: RefCell<SpriteBatch>,
+}
+
+impl StatsOverlay {
+    pub fn new() -> StatsOverlay {
+        StatsOverlay { batch: RefCell::new(SpriteBatch::new()) }
+    }
+
+    /// Add the bars of the graph to `batch`, for a window `screen_height` pixels high
+    ///
+    pub fn build(batch: &mut SpriteBatch, frame_times: &[f32], screen_height: f32) {
+        const BAR_WIDTH: f32 = 2.0;
+        const MARGIN: f32 = 8.0;
+        // Pixels per millisecond, 1/60 s is a third of the height
+        const SCALE: f32 = 3.0;
+        const TARGET: f32 = 1000.0 / 60.0;
+        let width: f32 = STATS_HISTORY as f32 * BAR_WIDTH;
+        let height: f32 = TARGET * SCALE * 3.0;
+        // The bottom of the graph, in pixels from the top of the window
+        let bottom: f32 = screen_height - MARGIN;
+        batch.rect(MARGIN, bottom - height, width, height, [0.0, 0.0, 0.0, 0.5], 0);
+        for (i, time) in frame_times.iter().enumerate() {
+            let color: [f32; 4] = if *time < TARGET {
+                [0.2, 0.9, 0.2, 0.8]
+            } else if *time < TARGET * 2.0 {
+                [0.9, 0.9, 0.2, 0.8]
+            } else {
+                [0.9, 0.2, 0.2, 0.8]
+            };
+            let bar_height: f32 = (time * SCALE).min(height);
+            let x: f32 = MARGIN + i as f32 * BAR_WIDTH;
+            batch.rect(x, bottom - bar_height, BAR_WIDTH - 1.0, bar_height, color, 0);
+        }
+        batch.rect(MARGIN, bottom - TARGET * SCALE - 1.0, width, 1.0, [1.0, 1.0, 1.0, 0.8], 0);
+    }
+}
+
+impl Renderer {
+    /// Draw the frame time graph over each frame drawn by `render` and `render_viewports`
+    ///
+    pub fn enable_stats_overlay(&mut self, display: &GlutinFacade) -> Result<(), Error> {
+        try!(self.load_overlay_program(display));
+        self.stats_overlay = Some(StatsOverlay::new());
+        Ok(())
+    }
+
+    pub fn disable_stats_overlay(&mut self) {
+        self.stats_overlay = None;
+    }
+
+    /// The last frame drawn
+    ///
+    pub fn frame_stats(&self) -> FrameStats {
+        self.stats.borrow().last()
+    }
+
+    /// The average of the last `STATS_HISTORY` frames
+    ///
+    pub fn average_frame_stats(&self) -> FrameStats {
+        self.stats.borrow().average()
+    }
+
+    /// Draw the `StatsOverlay` if it is enabled
+    ///
+    pub fn draw_stats_overlay<S: Surface>(&self,
+                                          display: &GlutinFacade,
+                                          target: &mut S,
+                                          query: Option<&TimeElapsedQuery>)
+                                          -> Result<(), Error> {
+        let overlay: &StatsOverlay = match self.stats_overlay {
+            Some(ref o) => o,
+            None => return Ok(()),
+        };
+        let (_, height) = target.get_dimensions();
+        let mut batch = overlay.batch.borrow_mut();
+        batch.clear();
+        StatsOverlay::build(&mut batch, &self.stats.borrow().frame_times(), height as f32);
+        self.draw_sprite_batch(display, target, &batch, query)
+    }
+}
+
+/// `extern bool renderer_frame_stats(Renderer renderer, FrameStats* stats);`
+///
+/// Copy the statistics of the last frame drawn into `stats`.
+///
+#[no_mangle]
+pub extern "C" fn renderer_frame_stats(renderer: &Renderer, stats: *mut FrameStats) -> bool {
+    ffi::guard_result_status(|| {
+        if stats.is_null() {
+            return Err(Error::InvalidArgument(String::from("Unexpected null FrameStats")));
+        }
+        unsafe { *stats = renderer.frame_stats() };
+        Ok(())
+    })
+}
+
+/// `extern bool renderer_average_frame_stats(Renderer renderer, FrameStats* stats);`
+///
+/// Copy the average of the recent frames into `stats`.
+///
+#[no_mangle]
+pub extern "C" fn renderer_average_frame_stats(renderer: &Renderer, stats: *mut FrameStats) -> bool {
+    ffi::guard_result_status(|| {
+        if stats.is_null() {
+            return Err(Error::InvalidArgument(String::from("Unexpected null FrameStats")));
+        }
+        unsafe { *stats = renderer.average_frame_stats() };
+        Ok(())
+    })
+}
+
+/// `extern bool renderer_enable_stats_overlay(Renderer renderer, Display display);`
+///
+#[no_mangle]
+pub extern "C" fn renderer_enable_stats_overlay(renderer: &mut Renderer, display: &GlutinFacade) -> bool {
+    ffi::guard_result_status(|| {
+        renderer.enable_stats_overlay(display)
+    })
+}
+
+/// `extern bool renderer_disable_stats_overlay(Renderer renderer);`
+///
+#[no_mangle]
+pub extern "C" fn renderer_disable_stats_overlay(renderer: &mut Renderer) -> bool {
+    ffi::guard_status(|| {
+        renderer.disable_stats_overlay();
+    })
+}
@@ -0,0 +1,584 @@
+// Copyright (C) 2016 Chris Liebert
+
+extern crate libc;
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::char;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+
+use glium;
+use glium::backend::glutin_backend::GlutinFacade;
+use glium::draw_parameters::TimeElapsedQuery;
+use glium::texture::{ClientFormat, MipmapsOption, RawImage2d, Texture2d, UncompressedFloatFormat};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter};
+use glium::{Program, Rect, Surface};
+
+use rusttype;
+use rusttype::{point, Font, FontCollection, PositionedGlyph, Scale};
+
+use camera::Camera;
+use error::Error;
+use renderer::{Renderer, RendererError};
+use ffi;
+
+/// DejaVu Sans Mono, used when no font file is given, see fonts/LICENSE
+///
+pub const DEFAULT_FONT: &'static [u8] = include_bytes!("../fonts/DejaVuSansMono.ttf");
+
+pub const DEFAULT_FONT_SIZE: f32 = 16.0;
+
+/// The width of the glyph atlas in pixels, the height grows to fit the glyphs
+///
+pub const ATLAS_WIDTH: u32 = 512;
+
+/// Empty pixels between glyphs so neighbours don't bleed into each other when filtered
+const ATLAS_PADDING: u32 = 1;
+
+/// The characters rasterized into the atlas, printable ASCII and Latin-1
+///
+fn atlas_characters() -> Vec<char> {
+    (32u32..127).chain(160u32..256).filter_map(char::from_u32).collect()
+}
+
+/// A character in a `GlyphAtlas`
+///
+/// `offset` is the top left corner of the glyph relative to the pen position on the baseline,
+/// `offset`, `size` and `advance` are in pixels. `texcoords` are `[u0, v0, u1, v1]`.
+/// Characters without any pixels, like the space, have a size of zero.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtlasGlyph {
+    pub advance: f32,
+    pub offset: [f32; 2],
+    pub size: [f32; 2],
+    pub texcoords: [f32; 4],
+}
+
+/// A glyph placed by `GlyphAtlas::layout`
+///
+/// `rect` is `[x0, y0, x1, y1]` in pixels from the top left corner of the text, y increases
+/// downwards.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlyphQuad {
+    pub rect: [f32; 4],
+    pub texcoords: [f32; 4],
+}
+
+/// A TrueType font rasterized at a single size
+///
+/// `pixels` holds the coverage of each pixel from 0 to 255, one byte per pixel, starting with
+/// the top row.
+///
+pub struct GlyphAtlas {
+    pub font_size: f32,
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+    pub ascent: f32,
+    pub line_height: f32,
+    glyphs: HashMap<char, AtlasGlyph>,
+    font: Font<'static>,
+}
+
+impl GlyphAtlas {
+    /// Rasterize the contents of a .ttf file at `font_size` pixels
+    ///
+    pub fn new(font_data: Vec<u8>, font_size: f32) -> Result<GlyphAtlas, Error> {
+        let font: Font<'static> = match FontCollection::from_bytes(font_data).into_font() {
+            Some(f) => f,
+            None => return Err(Error::Renderer(RendererError::InvalidFontError)),
+        };
+        let scale: Scale = Scale::uniform(font_size);
+        let v_metrics = font.v_metrics(scale);
+
+        // Place the glyphs in rows, starting from the top left corner
+        let mut advances: Vec<(char, f32)> = Vec::new();
+        let mut placed: Vec<(char, PositionedGlyph<'static>, rusttype::Rect<i32>, u32, u32)> = Vec::new();
+        let mut x: u32 = ATLAS_PADDING;
+        let mut y: u32 = ATLAS_PADDING;
+        let mut row_height: u32 = 0;
+        for c in atlas_characters() {
+            let glyph = match font.glyph(c) {
+                Some(g) => g.scaled(scale),
+                None => continue,
+            };
+            advances.push((c, glyph.h_metrics().advance_width));
+            let positioned: PositionedGlyph<'static> = glyph.positioned(point(0.0, 0.0));
+            if let Some(bounds) = positioned.pixel_bounding_box() {
+                let width: u32 = bounds.width() as u32;
+                if x + width + ATLAS_PADDING > ATLAS_WIDTH {
+                    x = ATLAS_PADDING;
+                    y += row_height + ATLAS_PADDING;
+                    row_height = 0;
+                }
+                placed.push((c, positioned, bounds, x, y));
+                x += width + ATLAS_PADDING;
+                row_height = row_height.max(bounds.height() as u32);
+            }
+        }
+        let height: u32 = (y + row_height + ATLAS_PADDING).next_power_of_two();
+
+        let mut glyphs: HashMap<char, AtlasGlyph> = HashMap::new();
+        for &(c, advance) in advances.iter() {
+            glyphs.insert(c, AtlasGlyph {
+                advance: advance,
+                offset: [0.0, 0.0],
+                size: [0.0, 0.0],
+                texcoords: [0.0, 0.0, 0.0, 0.0],
+            });
+        }
+        let mut pixels: Vec<u8> = vec![0; (ATLAS_WIDTH * height) as usize];
+        for &(c, ref positioned, ref bounds, x0, y0) in placed.iter() {
+            positioned.draw(|gx, gy, coverage| {
+                let (px, py) = (x0 + gx, y0 + gy);
+                // A glyph wider than the atlas is cut off
+                if px < ATLAS_WIDTH && py < height {
+                    pixels[(py * ATLAS_WIDTH + px) as usize] = (coverage * 255.0).round() as u8;
+                }
+            });
+            let (w, h) = (bounds.width() as f32, bounds.height() as f32);
+            if let Some(glyph) = glyphs.get_mut(&c) {
+                glyph.offset = [bounds.min.x as f32, bounds.min.y as f32];
+                glyph.size = [w, h];
+                glyph.texcoords = [x0 as f32 / ATLAS_WIDTH as f32,
+                                   y0 as f32 / height as f32,
+                                   (x0 as f32 + w) / ATLAS_WIDTH as f32,
+                                   (y0 as f32 + h) / height as f32];
+            }
+        }
+
+        Ok(GlyphAtlas {
+            font_size: font_size,
+            width: ATLAS_WIDTH,
+            height: height,
+            pixels: pixels,
+            ascent: v_metrics.ascent,
+            line_height: v_metrics.ascent - v_metrics.descent + v_metrics.line_gap,
+            glyphs: glyphs,
+            font: font,
+        })
+    }
+
+    pub fn glyph(&self, c: char) -> Option<&AtlasGlyph> {
+        self.glyphs.get(&c)
+    }
+
+    /// Call `f` with each glyph of `text` and its pen position, returning the size of the text
+    ///
+    fn walk<F: FnMut(&AtlasGlyph, f32, f32)>(&self, text: &str, scale: f32, mut f: F) -> (f32, f32) {
+        let kerning_scale: Scale = Scale::uniform(self.font_size * scale);
+        let line_height: f32 = self.line_height * scale;
+        let mut x: f32 = 0.0;
+        let mut width: f32 = 0.0;
+        let mut lines: u32 = 1;
+        let mut previous: Option<char> = None;
+        for c in text.chars() {
+            if c == '\n' {
+                width = width.max(x);
+                x = 0.0;
+                lines += 1;
+                previous = None;
+                continue;
+            }
+            let (c, glyph) = match self.glyphs.get(&c) {
+                Some(g) => (c, g),
+                None => match self.glyphs.get(&'?') {
+                    Some(g) => ('?', g),
+                    None => continue,
+                },
+            };
+            if let Some(p) = previous {
+                x += self.font.pair_kerning(kerning_scale, p, c);
+            }
+            f(glyph, x, self.ascent * scale + (lines - 1) as f32 * line_height);
+            x += glyph.advance * scale;
+            previous = Some(c);
+        }
+        (width.max(x), lines as f32 * line_height)
+    }
+
+    /// Place the glyphs of `text` drawn at `scale` times the size of the atlas
+    ///
+    /// Lines are separated by '\n', characters which aren't in the atlas are drawn as '?'.
+    ///
+    pub fn layout(&self, text: &str, scale: f32) -> Vec<GlyphQuad> {
+        let mut quads: Vec<GlyphQuad> = Vec::with_capacity(text.len());
+        self.walk(text, scale, |glyph, x, baseline| {
+            if glyph.size[0] > 0.0 {
+                let x0: f32 = x + glyph.offset[0] * scale;
+                let y0: f32 = baseline + glyph.offset[1] * scale;
+                quads.push(GlyphQuad {
+                    rect: [x0, y0, x0 + glyph.size[0] * scale, y0 + glyph.size[1] * scale],
+                    texcoords: glyph.texcoords,
+                });
+            }
+        });
+        quads
+    }
+
+    /// The width and height of `text` in pixels
+    ///
+    pub fn measure(&self, text: &str, scale: f32) -> (f32, f32) {
+        self.walk(text, scale, |_, _, _| ())
+    }
+}
+
+#[derive(Copy, Clone)]
+struct TextVertex {
+    position: [f32; 2],
+    texcoord: [f32; 2],
+    color: [f32; 4],
+}
+
+implement_vertex!(TextVertex, position, texcoord, color);
+
+enum TextAnchor {
+    /// The top left corner of the text in pixels
+    Screen([f32; 2]),
+    /// The center of the text in world space
+    World([f32; 3]),
+}
+
+struct TextItem {
+    text: String,
+    anchor: TextAnchor,
+    color: [f32; 4],
+    scale: f32,
+}
+
+/// Draws text over the frames drawn by a `Renderer`
+///
+/// Text is queued with `queue` and `queue_world` and drawn in a single batch by `draw`.
+/// Screen space text is positioned in pixels from the top left corner of the window. World
+/// space text is centered on the projection of its position and always faces the camera,
+/// keeping the same size at any distance. Text scaled above 1.0 is blurry, load the font at
+/// the size it is drawn at instead.
+///
+pub struct TextRenderer {
+    pub atlas: GlyphAtlas,
+    texture: Texture2d,
+    program: Program,
+    queue: RefCell<Vec<TextItem>>,
+}
+
+impl TextRenderer {
+    pub fn new(display: &GlutinFacade, font_data: Vec<u8>, font_size: f32) -> Result<TextRenderer, Error> {
+        let atlas: GlyphAtlas = try!(GlyphAtlas::new(font_data, font_size));
+        let image = RawImage2d {
+            data: Cow::Borrowed(&atlas.pixels[..]),
+            width: atlas.width,
+            height: atlas.height,
+            format: ClientFormat::U8,
+        };
+        let texture: Texture2d = try!(
+            Texture2d::with_format(display, image, UncompressedFloatFormat::U8, MipmapsOption::NoMipmap)
+                .map_err(RendererError::TextureCreationError)
+        );
+        let program = try!(
+            Program::from_source(display, r#"
+#version 130
+
+in vec2 position;
+in vec2 texcoord;
+in vec4 color;
+
+uniform vec2 screen;
+
+out vec2 v_texcoord;
+out vec4 v_color;
+
+void main() {
+	v_texcoord = texcoord;
+	v_color = color;
+	gl_Position = vec4(position.x / screen.x * 2.0 - 1.0, 1.0 - position.y / screen.y * 2.0, 0.0, 1.0);
+}
+"#, r#"
+#version 130
+
+in vec2 v_texcoord;
+in vec4 v_color;
+
+uniform sampler2D atlas;
+
+out vec4 color;
+
+void main() {
+	color = vec4(v_color.rgb, v_color.a * texture(atlas, v_texcoord).r);
+}
+"#, None).map_err(RendererError::ProgramCreationError)
+        );
+        Ok(TextRenderer {
+            atlas: atlas,
+            texture: texture,
+            program: program,
+            queue: RefCell::new(Vec::new()),
+        })
+    }
+
+    /// Use the bundled `DEFAULT_FONT`
+    ///
+    pub fn default_font(display: &GlutinFacade, font_size: f32) -> Result<TextRenderer, Error> {
+        TextRenderer::new(display, DEFAULT_FONT.to_vec(), font_size)
+    }
+
+    pub fn from_file(display: &GlutinFacade, filename: &str, font_size: f32) -> Result<TextRenderer, Error> {
+        let mut font_data: Vec<u8> = Vec::new();
+        let mut file = try!(File::open(filename));
+        try!(file.read_to_end(&mut font_data));
+        TextRenderer::new(display, font_data, font_size)
+    }
+
+    /// Queue `text` with its top left corner at (x, y) pixels
+    ///
+    pub fn queue(&self, text: &str, x: f32, y: f32, color: [f32; 4], scale: f32) {
+        self.queue.borrow_mut().push(TextItem {
+            text: String::from(text),
+            anchor: TextAnchor::Screen([x, y]),
+            color: color,
+            scale: scale,
+        });
+    }
+
+    /// Queue `text` centered on a point in world space
+    ///
+    pub fn queue_world(&self, text: &str, position: [f32; 3], color: [f32; 4], scale: f32) {
+        self.queue.borrow_mut().push(TextItem {
+            text: String::from(text),
+            anchor: TextAnchor::World(position),
+            color: color,
+            scale: scale,
+        });
+    }
+
+    /// Remove the queued text
+    ///
+    pub fn clear(&self) {
+        self.queue.borrow_mut().clear();
+    }
+
+    fn push_text(&self, vertices: &mut Vec<TextVertex>, text: &str, origin: [f32; 2], color: [f32; 4], scale: f32) {
+        for quad in self.atlas.layout(text, scale) {
+            let (x0, y0) = (origin[0] + quad.rect[0], origin[1] + quad.rect[1]);
+            let (x1, y1) = (origin[0] + quad.rect[2], origin[1] + quad.rect[3]);
+            let (u0, v0, u1, v1) = (quad.texcoords[0], quad.texcoords[1], quad.texcoords[2], quad.texcoords[3]);
+            for &(x, y, u, v) in [(x0, y0, u0, v0), (x1, y0, u1, v0), (x1, y1, u1, v1),
+                                  (x0, y0, u0, v0), (x1, y1, u1, v1), (x0, y1, u0, v1)].iter() {
+                vertices.push(TextVertex { position: [x, y], texcoord: [u, v], color: color });
+            }
+        }
+    }
+
+    /// Build the quads of the queued text, world space text is placed once in each view
+    ///
+    fn vertices(&self, target_height: u32, views: &[(Rect, &Camera)]) -> Vec<TextVertex> {
+        let mut vertices: Vec<TextVertex> = Vec::new();
+        for item in self.queue.borrow().iter() {
+            match item.anchor {
+                TextAnchor::Screen(origin) => self.push_text(&mut vertices, &item.text, origin, item.color, item.scale),
+                TextAnchor::World(position) => {
+                    let (width, height) = self.atlas.measure(&item.text, item.scale);
+                    for &(rect, camera) in views {
+                        if let Some(p) = camera.screen_from_world(position, rect.width as f32, rect.height as f32) {
+                            // Viewports are measured from the bottom of the window
+                            let top: f32 = target_height as f32 - (rect.bottom + rect.height) as f32;
+                            let origin: [f32; 2] = [rect.left as f32 + p[0] - width * 0.5,
+                                                    top + p[1] - height * 0.5];
+                            self.push_text(&mut vertices, &item.text, origin, item.color, item.scale);
+                        }
+                    }
+                },
+            }
+        }
+        vertices
+    }
+
+    /// Draw the queued text over `target`, the queue is kept
+    ///
+    /// Returns the number of vertices drawn, nothing is drawn when there is no text.
+    ///
+    pub fn draw<S: Surface>(&self,
+                            display: &GlutinFacade,
+                            target: &mut S,
+                            views: &[(Rect, &Camera)],
+                            query: Option<&TimeElapsedQuery>)
+                            -> Result<usize, Error> {
+        let (width, height) = target.get_dimensions();
+        let vertices: Vec<TextVertex> = self.vertices(height, views);
+        if vertices.is_empty() {
+            return Ok(0);
+        }
+        let vertex_buffer = try!(
+            glium::VertexBuffer::new(display, &vertices).map_err(RendererError::VertexBufferCreationError)
+        );
+        let uniforms = uniform! {
+            screen: [width as f32, height as f32],
+            atlas: self.texture.sampled()
+                .minify_filter(MinifySamplerFilter::Linear)
+                .magnify_filter(MagnifySamplerFilter::Linear),
+        };
+        let params = glium::DrawParameters {
+            blend: glium::Blend::alpha_blending(),
+            time_elapsed_query: query,
+            .. Default::default()
+        };
+        try!(target.draw(&vertex_buffer,
+                         &glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList),
+                         &self.program,
+                         &uniforms,
+                         &params).map_err(RendererError::DrawError));
+        Ok(vertices.len())
+    }
+}
+
+impl Renderer {
+    /// Enable text with a font loaded from the contents of a .ttf file
+    ///
+    pub fn load_font(&mut self, display: &GlutinFacade, font_data: Vec<u8>, font_size: f32) -> Result<(), Error> {
+        self.text = Some(try!(TextRenderer::new(display, font_data, font_size)));
+        Ok(())
+    }
+
+    fn text_renderer(&self) -> Result<&TextRenderer, Error> {
+        match self.text {
+            Some(ref t) => Ok(t),
+            None => Err(Error::Renderer(RendererError::FontNotLoadedError)),
+        }
+    }
+
+    /// Draw `text` over the next frame drawn by `render` or `render_viewports`
+    ///
+    /// (x, y) is the top left corner of the text in pixels from the top left of the window.
+    ///
+    pub fn draw_text(&self, text: &str, x: f32, y: f32, color: [f32; 4], scale: f32) -> Result<(), Error> {
+        let text_renderer: &TextRenderer = try!(self.text_renderer());
+        text_renderer.queue(text, x, y, color, scale);
+        Ok(())
+    }
+
+    /// Draw `text` centered on a point in world space over the next frame, in every viewport
+    ///
+    pub fn draw_text_world(&self, text: &str, position: [f32; 3], color: [f32; 4], scale: f32) -> Result<(), Error> {
+        let text_renderer: &TextRenderer = try!(self.text_renderer());
+        text_renderer.queue_world(text, position, color, scale);
+        Ok(())
+    }
+
+    /// The width and height of `text` in pixels
+    ///
+    pub fn measure_text(&self, text: &str, scale: f32) -> Result<(f32, f32), Error> {
+        let text_renderer: &TextRenderer = try!(self.text_renderer());
+        Ok(text_renderer.atlas.measure(text, scale))
+    }
+
+    /// Draw the queued text if a font is loaded
+    ///
+    pub fn draw_text_overlay<S: Surface>(&self,
+                                         display: &GlutinFacade,
+                                         target: &mut S,
+                                         views: &[(Rect, &Camera)],
+                                         query: Option<&TimeElapsedQuery>)
+                                         -> Result<(), Error> {
+        match self.text {
+            Some(ref t) => {
+                let vertices: usize = try!(t.draw(display, target, views, query));
+                if vertices > 0 {
+                    self.stats.borrow_mut().record_draw_call(vertices);
+                }
+                Ok(())
+            },
+            None => Ok(()),
+        }
+    }
+
+    /// Remove the queued text, called once a frame has been drawn
+    ///
+    pub fn clear_text(&self) {
+        if let Some(ref t) = self.text {
+            t.clear();
+        }
+    }
+}
+
+/// `extern bool renderer_load_font(Renderer renderer, Display display, const char* file, float size);`
+///
+/// Load a TrueType font to draw text with, or the bundled font when `file` is NULL.
+///
+#[no_mangle]
+pub extern "C" fn renderer_load_font(renderer: &mut Renderer,
+                                     display: &GlutinFacade,
+                                     file: *const libc::c_char,
+                                     size: libc::c_float)
+                                     -> bool {
+    ffi::guard_result_status(|| {
+        let text_renderer: TextRenderer = match file.is_null() {
+            true => try!(TextRenderer::default_font(display, size)),
+            false => try!(TextRenderer::from_file(display, &try!(ffi::c_string(file)), size)),
+        };
+        renderer.text = Some(text_renderer);
+        Ok(())
+    })
+}
+
+/// `extern bool renderer_draw_text(Renderer renderer, const char* text, float x, float y, float r, float g, float b, float a, float scale);`
+///
+/// Draw `text` over the next frame, (x, y) is the top left corner of the text in pixels.
+///
+#[no_mangle]
+pub extern "C" fn renderer_draw_text(renderer: &Renderer,
+                                     text: *const libc::c_char,
+                                     x: libc::c_float,
+                                     y: libc::c_float,
+                                     r: libc::c_float,
+                                     g: libc::c_float,
+                                     b: libc::c_float,
+                                     a: libc::c_float,
+                                     scale: libc::c_float)
+                                     -> bool {
+    ffi::guard_result_status(|| {
+        renderer.draw_text(&try!(ffi::c_string(text)), x, y, [r, g, b, a], scale)
+    })
+}
+
+/// `extern bool renderer_draw_text_world(Renderer renderer, const char* text, float x, float y, float z, float r, float g, float b, float a, float scale);`
+///
+/// Draw `text` over the next frame centered on the world space point (x, y, z).
+///
+#[no_mangle]
+pub extern "C" fn renderer_draw_text_world(renderer: &Renderer,
+                                           text: *const libc::c_char,
+                                           x: libc::c_float,
+                                           y: libc::c_float,
+                                           z: libc::c_float,
+                                           r: libc::c_float,
+                                           g: libc::c_float,
+                                           b: libc::c_float,
+                                           a: libc::c_float,
+                                           scale: libc::c_float)
+                                           -> bool {
+    ffi::guard_result_status(|| {
+        renderer.draw_text_world(&try!(ffi::c_string(text)), [x, y, z], [r, g, b, a], scale)
+    })
+}
+
+/// `extern bool renderer_measure_text(Renderer renderer, const char* text, float scale, float* width, float* height);`
+///
+#[no_mangle]
+pub extern "C" fn renderer_measure_text(renderer: &Renderer,
+                                        text: *const libc::c_char,
+                                        scale: libc::c_float,
+                                        width: *mut libc::c_float,
+                                        height: *mut libc::c_float)
+                                        -> bool {
+    ffi::guard_result_status(|| {
+        let (w, h) = try!(renderer.measure_text(&try!(ffi::c_string(text)), scale));
+        unsafe {
+            *width = w;
+            *height = h;
+        }
+        Ok(())
+    })
+}
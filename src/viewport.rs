@@ -0,0 +1,268 @@
+// Copyright (C) 2016 Chris Liebert
+
+extern crate libc;
+
+use std::slice;
+
+use glium;
+use glium::backend::glutin_backend::GlutinFacade;
+use glium::{Rect, Surface};
+
+use nalgebra::Vector3;
+
+use camera::Camera;
+use error::Error;
+use renderer::{Renderer, RendererError};
+use ffi;
+
+/// A sub-rectangle of the display in pixels, measured from the bottom left corner
+///
+/// When `clear` is true the color and depth of the rectangle are cleared before drawing,
+/// otherwise only the depth is cleared so viewports can be layered on top of each other.
+///
+/// ```c
+/// /* C representation */
+/// typedef struct Viewport {
+///     unsigned int x, y, width, height;
+///     bool clear;
+///     float clear_color[4];
+/// } Viewport;
+/// ```
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct Viewport {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub clear: bool,
+    pub clear_color: [f32; 4],
+}
+
+impl Viewport {
+    /// Create a new `Viewport` which is cleared to black
+    ///
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Viewport {
+        Viewport {
+            x: x,
+            y: y,
+            width: width,
+            height: height,
+            clear: true,
+            clear_color: [0.0, 0.0, 0.0, 0.0],
+        }
+    }
+
+    /// Divide the display into a grid of equally sized viewports
+    ///
+    /// The viewports are ordered left to right, top to bottom.
+    ///
+    pub fn split(screen_width: u32, screen_height: u32, columns: u32, rows: u32) -> Vec<Viewport> {
+        let columns: u32 = columns.max(1);
+        let rows: u32 = rows.max(1);
+        let width: u32 = screen_width / columns;
+        let height: u32 = screen_height / rows;
+        let mut viewports: Vec<Viewport> = Vec::with_capacity((columns * rows) as usize);
+        for row in 0..rows {
+            for column in 0..columns {
+                viewports.push(Viewport::new(column * width, (rows - row - 1) * height, width, height));
+            }
+        }
+        viewports
+    }
+
+    /// The left and right halves of the display
+    ///
+    pub fn side_by_side(screen_width: u32, screen_height: u32) -> (Viewport, Viewport) {
+        let halves: Vec<Viewport> = Viewport::split(screen_width, screen_height, 2, 1);
+        (halves[0], halves[1])
+    }
+
+    /// The ratio of width to height, used to set up the projection of the `Camera` drawn into it
+    ///
+    pub fn aspect_ratio(&self) -> f32 {
+        self.width as f32 / self.height.max(1) as f32
+    }
+
+    pub fn rect(&self) -> Rect {
+        Rect {
+            left: self.x,
+            bottom: self.y,
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+/// A pair of eye cameras derived from a center `Camera`
+///
+/// The eyes are offset along the right vector of the center camera by half the
+/// `interpupillary_distance` each and keep it's orientation. Their projections are sheared
+/// (off-axis) so that objects at the `convergence` distance appear at the same place for both eyes.
+///
+#[derive(Clone, Debug)]
+pub struct StereoCamera {
+    pub interpupillary_distance: f32,
+    pub convergence: f32,
+}
+
+impl StereoCamera {
+    pub fn new(interpupillary_distance: f32, convergence: f32) -> StereoCamera {
+        StereoCamera {
+            interpupillary_distance: interpupillary_distance,
+            convergence: convergence,
+        }
+    }
+
+    /// Create the camera for an eye offset by `offset` along the right vector
+    ///
+    fn eye(&self, camera: &Camera, offset: f32) -> Camera {
+        let position: Vector3<f32> = camera.position + camera.right * offset;
+        let mut eye: Camera = camera.set_position(&position);
+        if !camera.orthographic && self.convergence > 0.0 {
+            // Shear the frustum back towards the center camera at the convergence distance
+            eye.projection_matrix[2][0] = -eye.projection_matrix[0][0] * offset / self.convergence;
+        }
+        eye
+    }
+
+    pub fn left_eye(&self, camera: &Camera) -> Camera {
+        self.eye(camera, -0.5 * self.interpupillary_distance)
+    }
+
+    pub fn right_eye(&self, camera: &Camera) -> Camera {
+        self.eye(camera, 0.5 * self.interpupillary_distance)
+    }
+}
+
+impl Renderer {
+    /// Draw the `Scene` once per (`Viewport`, `Camera`) pair into a single frame
+    ///
+    pub fn render_viewports(&self,
+                            display: &GlutinFacade,
+                            program: &glium::program::Program,
+                            views: &[(Viewport, &Camera)])
+                            -> Result<(), Error> {
+        let result = {
+            let _scope = self.profiler.scope("render_viewports");
+            self.render_viewports_frame(display, program, views)
+        };
+        self.clear_overlay();
+        self.clear_text();
+        self.profiler.end_frame();
+        result
+    }
+
+    fn render_viewports_frame(&self,
+                              display: &GlutinFacade,
+                              program: &glium::program::Program,
+                              views: &[(Viewport, &Camera)])
+                              -> Result<(), Error> {
+        let mut target = display.draw();
+        self.stats.borrow_mut().begin_frame();
+        for &(ref viewport, camera) in views {
+            let rect: Rect = viewport.rect();
+            let color = match viewport.clear {
+                true => {
+                    let c = viewport.clear_color;
+                    Some((c[0], c[1], c[2], c[3]))
+                },
+                false => None,
+            };
+            target.clear(Some(&rect), color, false, Some(1.0), None);
+            match self.draw_profiled(display, &mut target, program, camera, Some(rect), "viewport") {
+                Ok(_) => (),
+                Err(e) => {
+                    let _ = target.finish();
+                    return Err(e);
+                },
+            }
+        }
+        let text_views: Vec<(Rect, &Camera)> = views.iter().map(|&(ref v, camera)| (v.rect(), camera)).collect();
+        if let Err(e) = self.profiler.profile_pass(display, "overlay", |query| {
+                self.draw_overlay(display, &mut target, query)
+            })
+            .and_then(|_| self.profiler.profile_pass(display, "text", |query| {
+                self.draw_text_overlay(display, &mut target, &text_views, query)
+            }))
+            .and_then(|_| self.profiler.profile_pass(display, "stats_overlay", |query| {
+                self.draw_stats_overlay(display, &mut target, query)
+            })) {
+            let _ = target.finish();
+            return Err(e);
+        }
+        self.stats.borrow_mut().end_frame();
+        try!(target.finish().map_err(RendererError::SwapBuffersError));
+        Ok(())
+    }
+
+    /// Draw the left and right eyes of a `StereoCamera` side by side
+    ///
+    /// The aspect ratio of `camera` should match one half of the display.
+    ///
+    pub fn render_stereo(&self,
+                         display: &GlutinFacade,
+                         program: &glium::program::Program,
+                         camera: &Camera,
+                         stereo: &StereoCamera)
+                         -> Result<(), Error> {
+        let (width, height) = display.get_framebuffer_dimensions();
+        let (left, right) = Viewport::side_by_side(width, height);
+        let left_camera: Camera = stereo.left_eye(camera);
+        let right_camera: Camera = stereo.right_eye(camera);
+        self.render_viewports(display, program, &[(left, &left_camera), (right, &right_camera)])
+    }
+}
+
+/// `extern bool render_viewports(Renderer renderer, Shader shader_program, Viewport* viewports, Camera* cameras, int count, Display display);`
+///
+#[no_mangle]
+pub extern "C" fn render_viewports(renderer: &Renderer,
+                                   shader_program: &glium::program::Program,
+                                   viewports: *const Viewport,
+                                   cameras: *const &Camera,
+                                   count: libc::c_int,
+                                   display: &GlutinFacade) -> bool {
+    ffi::guard_result_status(|| {
+        let count: usize = count.max(0) as usize;
+        let viewports: &[Viewport] = unsafe { slice::from_raw_parts(viewports, count) };
+        let cameras: &[&Camera] = unsafe { slice::from_raw_parts(cameras, count) };
+        let views: Vec<(Viewport, &Camera)> = viewports.iter().cloned().zip(cameras.iter().cloned()).collect();
+        renderer.render_viewports(display, shader_program, &views)
+    })
+}
+
+/// `extern bool render_stereo(Renderer renderer, Shader shader_program, Camera camera, float interpupillary_distance, float convergence, Display display);`
+///
+#[no_mangle]
+pub extern "C" fn render_stereo(renderer: &Renderer,
+                                shader_program: &glium::program::Program,
+                                camera: &Camera,
+                                interpupillary_distance: libc::c_float,
+                                convergence: libc::c_float,
+                                display: &GlutinFacade) -> bool {
+    ffi::guard_result_status(|| {
+        let stereo: StereoCamera = StereoCamera::new(interpupillary_distance as f32, convergence as f32);
+        renderer.render_stereo(display, shader_program, camera, &stereo)
+    })
+}
+
+/// `extern Camera camera_stereo_eye(Camera camera, float interpupillary_distance, float convergence, bool right);`
+///
+/// Returns a new `Camera` which must be freed with `free_camera`.
+///
+#[no_mangle]
+pub extern "C" fn camera_stereo_eye(camera: &Camera,
+                                    interpupillary_distance: libc::c_float,
+                                    convergence: libc::c_float,
+                                    right: bool)
+                                    -> Option<Box<Camera>> {
+    ffi::guard(|| {
+        let stereo: StereoCamera = StereoCamera::new(interpupillary_distance as f32, convergence as f32);
+        match right {
+            true => Box::new(stereo.right_eye(camera)),
+            false => Box::new(stereo.left_eye(camera)),
+        }
+    })
+}